@@ -1,8 +1,10 @@
-use rand::seq::SliceRandom;
+use crate::markov::{LogLines, LogMode};
+use rand::Rng;
 
 /// Manages build log messages for authentic compilation output
+#[derive(Clone, Debug)]
 pub struct BuildLogs {
-    logs: Vec<String>,
+    logs: LogLines,
 }
 
 impl BuildLogs {
@@ -15,26 +17,26 @@ impl BuildLogs {
             .map(String::from)
             .collect();
 
-        Self { logs }
+        Self::from_lines(logs)
     }
 
-    /// Get all build logs
-    pub fn all_logs(&self) -> &[String] {
-        &self.logs
+    /// Uses `logs` as the build output
+    pub fn from_lines(logs: Vec<String>) -> Self {
+        Self {
+            logs: LogLines::new(logs),
+        }
     }
 
-    #[allow(dead_code)]
-    pub fn random_batch(&self, count: usize) -> Vec<&str> {
-        let mut rng = rand::thread_rng();
-        let mut batch = Vec::new();
-
-        for _ in 0..count {
-            if let Some(log) = self.logs.choose(&mut rng) {
-                batch.push(log.as_str());
-            }
+    /// Trains the model for `LogMode::Synthesize`
+    pub fn trained(self) -> Self {
+        Self {
+            logs: self.logs.trained(),
         }
+    }
 
-        batch
+    /// Get as many build log lines as the log has, replayed, sampled or made up
+    pub fn lines(&self, mode: LogMode, rng: &mut impl Rng) -> Vec<String> {
+        self.logs.take(mode, self.logs.all().len(), rng)
     }
 }
 
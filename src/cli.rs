@@ -1,27 +1,42 @@
-use clap::{Parser, ValueEnum};
+use crate::clock::{MAX_SPEED, MIN_SPEED};
+use crate::corpus::CorpusName;
+use crate::input::{DEFAULT_BOSS_KEY, RESERVED_KEYS};
+use crate::markov::LogMode;
+use crate::ui::SpinnerStyle;
+use crate::wrap;
+use chrono::{NaiveDateTime, NaiveTime};
+use clap::builder::{PossibleValue, TypedValueParser};
+use clap::{ArgGroup, Parser, Subcommand, ValueEnum};
+use serde::{Serialize, Serializer};
+use std::ffi::OsStr;
+use std::fmt;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, ValueEnum)]
 pub enum Stage {
     /// BIOS initialization
     Bios,
     /// Boot sequence
     Boot,
-    /// Bootloader installation
-    Bootloader,
     /// Filesystem setup
     Filesystem,
+    /// Bootloader installation
+    Bootloader,
     /// System installation
     System,
-    /// Network configuration
-    Network,
     /// Driver installation
     Drivers,
+    /// Network configuration
+    Network,
+    /// Linux kernel compilation
+    Kernel,
     /// Initramfs generation
     Initramfs,
     /// Package installation
     Packages,
-    /// Linux kernel compilation
-    Kernel,
     /// Compilation
     Compilation,
     /// Deno runtime compilation
@@ -50,14 +65,14 @@ impl Stage {
         vec![
             Stage::Bios,
             Stage::Boot,
-            Stage::Bootloader,
             Stage::Filesystem,
+            Stage::Bootloader,
             Stage::System,
-            Stage::Network,
             Stage::Drivers,
+            Stage::Network,
+            Stage::Kernel,
             Stage::Initramfs,
             Stage::Packages,
-            Stage::Kernel,
             Stage::Compilation,
             Stage::Deno,
             Stage::Database,
@@ -72,7 +87,117 @@ impl Stage {
     }
 }
 
-#[derive(Parser, Debug)]
+/// A stage picked by name, built in or loaded from a stage script
+///
+/// Built-in stages sort in installation order, before every scripted one.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StageId {
+    Builtin(Stage),
+    /// The file name of a stage script, without its extension
+    Script(String),
+    /// The name of a stage added by a program that embeds the installer,
+    /// see `Installer::add_stage`
+    Custom(String),
+}
+
+impl StageId {
+    /// Every built-in stage in installation order, followed by `scripts`
+    pub fn all(scripts: &[String]) -> Vec<StageId> {
+        Stage::all()
+            .into_iter()
+            .map(StageId::Builtin)
+            .chain(scripts.iter().cloned().map(StageId::Script))
+            .collect()
+    }
+}
+
+impl From<Stage> for StageId {
+    fn from(stage: Stage) -> Self {
+        StageId::Builtin(stage)
+    }
+}
+
+impl fmt::Display for StageId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StageId::Builtin(stage) => match stage.to_possible_value() {
+                Some(value) => f.write_str(value.get_name()),
+                None => write!(f, "{:?}", stage),
+            },
+            StageId::Script(name) | StageId::Custom(name) => f.write_str(name),
+        }
+    }
+}
+
+impl Serialize for StageId {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Parses stage names, listing the built-in stages in `--help` while letting
+/// any other name through as a scripted stage
+#[derive(Clone)]
+struct StageParser;
+
+impl TypedValueParser for StageParser {
+    type Value = StageId;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &OsStr,
+    ) -> Result<StageId, clap::Error> {
+        let name = clap::builder::NonEmptyStringValueParser::new().parse_ref(cmd, arg, value)?;
+        Ok(match Stage::from_str(&name, false) {
+            Ok(stage) => StageId::Builtin(stage),
+            Err(_) => StageId::Script(name),
+        })
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            Stage::value_variants()
+                .iter()
+                .filter_map(ValueEnum::to_possible_value),
+        ))
+    }
+}
+
+/// How the selected stages are ordered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Order {
+    /// Installation order, as listed by `Stage::all`
+    Fixed,
+    /// Random order
+    #[default]
+    Shuffle,
+    /// Random order that respects the dependencies between stages
+    Realistic,
+}
+
+/// What the installer writes to stdout
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Colored terminal output, redrawn in place
+    #[default]
+    Ansi,
+    /// One JSON object per event, for programs
+    Jsonl,
+}
+
+/// When the output is colored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ColorChoice {
+    /// On a terminal, unless `NO_COLOR` is set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(Parser, Debug, Default)]
 #[command(
     name = "install-nothing",
     version,
@@ -80,25 +205,269 @@ impl Stage {
     long_about = "Universal System Installer - simulates an endless installation process.\n\
                   Select which stages to run, or use --all to run everything (default)."
 )]
+#[command(group(
+    ArgGroup::new("wrapped")
+        .args(["wrap", "command_line"])
+        .conflicts_with_all(["instant", "cycles", "duration", "until"])
+))]
 pub struct Cli {
-    /// Stages to install (defaults to all if none specified)
-    #[arg(value_enum)]
-    pub stages: Vec<Stage>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Stages to install (defaults to all if none specified), built in or scripted
+    #[arg(value_parser = StageParser)]
+    pub stages: Vec<StageId>,
 
     /// Install all stages (default behavior)
     #[arg(short, long, conflicts_with = "stages")]
     pub all: bool,
 
     /// Exclude specific stages from installation
-    #[arg(short, long, value_enum, num_args = 0.., conflicts_with = "stages")]
-    pub exclude: Vec<Stage>,
+    #[arg(short, long, value_parser = StageParser, num_args = 0.., conflicts_with = "stages")]
+    pub exclude: Vec<StageId>,
+
+    /// Seed for the random number generator, to replay a previous run
+    #[arg(long)]
+    pub seed: Option<u64>,
+
+    /// Order in which the stages run
+    #[arg(long, value_enum, default_value_t = Order::Shuffle)]
+    pub order: Order,
+
+    /// Simulation config file (TOML or JSON), defaults to ~/.config/install-nothing/config.toml
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Speed factor for every delay, from 0.0625 to 64, e.g. 0.25 for a slow demo or 10 for a quick look
+    #[arg(long, value_name = "FACTOR", default_value_t = 1.0, value_parser = parse_speed)]
+    pub speed: f64,
+
+    /// Record the session to an asciicast v2 file, playable with `asciinema play`
+    #[arg(long, value_name = "FILE")]
+    pub record: Option<PathBuf>,
+
+    /// Output format: ansi for people, jsonl for programs
+    #[arg(long, value_enum, default_value_t = Format::Ansi)]
+    pub format: Format,
+
+    /// Full-screen dashboard with a stage list, log pane and progress gauge
+    #[arg(long)]
+    pub tui: bool,
+
+    /// Write lines that are never redrawn, the default when stdout is not a terminal
+    #[arg(long, conflicts_with_all = ["tui", "record"])]
+    pub plain: bool,
+
+    /// When to use colors: auto (only on a terminal), always or never
+    #[arg(long, value_enum, value_name = "WHEN", default_value_t = ColorChoice::Auto)]
+    pub color: ColorChoice,
+
+    /// Skip every delay and stop after one installation cycle, e.g. to make a recording
+    #[arg(long)]
+    pub instant: bool,
+
+    /// Stop after this many installation cycles
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u32).range(1..))]
+    pub cycles: Option<u32>,
+
+    /// Stop after this long, e.g. 90s, 15m or 1h30m, pacing the last cycle to fit
+    #[arg(long, value_name = "TIME", value_parser = parse_duration, conflicts_with = "cycles")]
+    pub duration: Option<Duration>,
+
+    /// Stop at this time of day (HH:MM), pacing the last cycle to fit
+    #[arg(
+        long,
+        value_name = "HH:MM",
+        value_parser = parse_time_of_day,
+        conflicts_with_all = ["cycles", "duration"]
+    )]
+    pub until: Option<NaiveTime>,
+
+    /// Key that cuts straight to a serious-looking build when someone walks by
+    #[arg(long, value_name = "KEY", default_value_t = DEFAULT_BOSS_KEY, value_parser = parse_boss_key)]
+    pub boss_key: char,
+
+    /// No easter eggs, only believable output
+    #[arg(long)]
+    pub serious: bool,
+
+    /// Write the session statistics to this JSON file when the run ends
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Replace a built-in log with your own file, e.g. kernel=/var/log/dmesg
+    /// (kernel, build, deno or deno-error)
+    #[arg(long, value_name = "NAME=FILE", value_parser = parse_corpus)]
+    pub corpus: Vec<(CorpusName, PathBuf)>,
+
+    /// How the boot, kernel, compilation and Deno stages use their logs,
+    /// instead of what the config file says
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub log_mode: Option<LogMode>,
+
+    /// Color theme: default, amber, green, ibm, high-contrast, no-color or one
+    /// from the config file's [themes]
+    #[arg(long, value_name = "NAME")]
+    pub theme: Option<String>,
+
+    /// Spinner style for every stage, instead of the one each stage picks
+    #[arg(long, value_enum, value_name = "STYLE")]
+    pub spinner: Option<SpinnerStyle>,
+
+    /// Directory of stage scripts, defaults to ~/.config/install-nothing/stages
+    #[arg(long, value_name = "DIR")]
+    pub stages_dir: Option<PathBuf>,
+
+    /// Run this shell command and install until it exits, with its exit code
+    #[arg(long, value_name = "COMMAND")]
+    pub wrap: Option<String>,
+
+    /// Where the output of the wrapped command goes
+    #[arg(
+        long,
+        value_name = "FILE",
+        default_value = "install-nothing.log",
+        requires = "wrapped"
+    )]
+    pub wrap_log: PathBuf,
+
+    /// A command to run like --wrap, without a shell, e.g. -- cargo build --release
+    #[arg(last = true, value_name = "COMMAND")]
+    pub command_line: Vec<String>,
+}
+
+/// Other ways to run the installer than in this terminal
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Stream the installer to browsers on localhost
+    Serve {
+        /// Port of the page, http://localhost:PORT
+        #[arg(long, default_value_t = 8080)]
+        port: u16,
+    },
+    /// Run an installer for every telnet client that connects
+    Telnet {
+        /// Address to listen on
+        #[arg(long, value_name = "ADDR", default_value = "0.0.0.0:2323")]
+        listen: SocketAddr,
+    },
+}
+
+fn parse_speed(value: &str) -> Result<f64, String> {
+    let speed: f64 = value
+        .parse()
+        .map_err(|_| format!("`{}` is not a number", value))?;
+    if (MIN_SPEED..=MAX_SPEED).contains(&speed) {
+        Ok(speed)
+    } else {
+        Err(format!(
+            "speed must be between {} and {}",
+            MIN_SPEED, MAX_SPEED
+        ))
+    }
+}
+
+fn parse_boss_key(value: &str) -> Result<char, String> {
+    let mut chars = value.chars();
+    let key = match (chars.next(), chars.next()) {
+        (Some(key), None) => key,
+        _ => return Err(format!("`{}` is not a single key", value)),
+    };
+    if RESERVED_KEYS.contains(&key) {
+        return Err(format!("`{}` is already a hotkey", key));
+    }
+    Ok(key)
+}
+
+fn parse_corpus(value: &str) -> Result<(CorpusName, PathBuf), String> {
+    let (name, path) = value
+        .split_once('=')
+        .ok_or_else(|| format!("`{}` is not NAME=FILE", value))?;
+    let name = CorpusName::from_str(name, false).map_err(|_| {
+        format!(
+            "unknown corpus `{}`, expected kernel, build, deno or deno-error",
+            name
+        )
+    })?;
+    Ok((name, PathBuf::from(path)))
+}
+
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let invalid = || format!("`{}` is not a duration like 90s, 15m or 1h30m", value);
+    let too_long = || format!("`{}` is too long a duration", value);
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+
+    for c in value.trim().chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let unit = match c {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return Err(invalid()),
+        };
+        let amount: u64 = digits.parse().map_err(|_| invalid())?;
+        total = amount
+            .checked_mul(unit)
+            .and_then(|seconds| total.checked_add(seconds))
+            .ok_or_else(too_long)?;
+        digits.clear();
+    }
+
+    // A bare number is a number of seconds
+    if !digits.is_empty() {
+        let seconds = digits.parse::<u64>().map_err(|_| invalid())?;
+        total = total.checked_add(seconds).ok_or_else(too_long)?;
+    }
+    if total == 0 {
+        return Err("duration must be greater than zero".to_string());
+    }
+    Ok(Duration::from_secs(total))
+}
+
+fn parse_time_of_day(value: &str) -> Result<NaiveTime, String> {
+    NaiveTime::parse_from_str(value, "%H:%M")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M:%S"))
+        .map_err(|_| format!("`{}` is not a time like 14:30", value))
+}
+
+/// Time from `now` until the next `time` of day, tomorrow if it has passed today
+pub fn time_until(time: NaiveTime, now: NaiveDateTime) -> Duration {
+    let mut target = now.date().and_time(time);
+    if target <= now {
+        target += chrono::Duration::days(1);
+    }
+    (target - now).to_std().unwrap_or_default()
 }
 
 impl Cli {
-    /// Returns the selected stages, defaulting to all if none specified
-    pub fn get_stages(&self) -> Vec<Stage> {
+    /// The command to install alongside, given with `--wrap` or after `--`
+    pub fn wrapped(&self) -> Option<process::Command> {
+        if let Some(line) = &self.wrap {
+            return Some(wrap::shell(line));
+        }
+        let (program, args) = self.command_line.split_first()?;
+        let mut command = process::Command::new(program);
+        command.args(args);
+        Some(command)
+    }
+
+    /// Returns the selected stages, defaulting to all if none specified,
+    /// given the names of the loaded stage scripts
+    pub fn get_stages(&self, scripts: &[String]) -> Result<Vec<StageId>, String> {
+        for stage in self.stages.iter().chain(&self.exclude) {
+            if let StageId::Script(name) = stage {
+                if !scripts.contains(name) {
+                    return Err(format!("unknown stage `{}`", name));
+                }
+            }
+        }
+
         let mut stages = if self.all || self.stages.is_empty() {
-            Stage::all()
+            StageId::all(scripts)
         } else {
             self.stages.clone()
         };
@@ -107,7 +476,7 @@ impl Cli {
             stages.retain(|stage| !self.exclude.contains(stage));
         }
 
-        stages
+        Ok(stages)
     }
 }
 
@@ -115,14 +484,19 @@ impl Cli {
 mod tests {
     use super::*;
 
+    fn ids(stages: Vec<Stage>) -> Vec<StageId> {
+        stages.into_iter().map(StageId::from).collect()
+    }
+
     #[test]
     fn test_default_to_all() {
         let cli = Cli {
             stages: vec![],
             all: false,
             exclude: vec![],
+            ..Default::default()
         };
-        assert_eq!(cli.get_stages(), Stage::all());
+        assert_eq!(cli.get_stages(&[]).unwrap(), ids(Stage::all()));
     }
 
     #[test]
@@ -131,18 +505,23 @@ mod tests {
             stages: vec![],
             all: true,
             exclude: vec![],
+            ..Default::default()
         };
-        assert_eq!(cli.get_stages(), Stage::all());
+        assert_eq!(cli.get_stages(&[]).unwrap(), ids(Stage::all()));
     }
 
     #[test]
     fn test_specific_stages() {
         let cli = Cli {
-            stages: vec![Stage::Bios, Stage::Boot],
+            stages: ids(vec![Stage::Bios, Stage::Boot]),
             all: false,
             exclude: vec![],
+            ..Default::default()
         };
-        assert_eq!(cli.get_stages(), vec![Stage::Bios, Stage::Boot]);
+        assert_eq!(
+            cli.get_stages(&[]).unwrap(),
+            ids(vec![Stage::Bios, Stage::Boot])
+        );
     }
 
     #[test]
@@ -150,10 +529,11 @@ mod tests {
         let cli = Cli {
             stages: vec![],
             all: false,
-            exclude: vec![Stage::Ai],
+            exclude: ids(vec![Stage::Ai]),
+            ..Default::default()
         };
-        let result = cli.get_stages();
-        assert!(!result.contains(&Stage::Ai));
+        let result = cli.get_stages(&[]).unwrap();
+        assert!(!result.contains(&Stage::Ai.into()));
         assert_eq!(result.len(), Stage::all().len() - 1);
     }
 
@@ -162,11 +542,12 @@ mod tests {
         let cli = Cli {
             stages: vec![],
             all: true,
-            exclude: vec![Stage::Ai, Stage::Cloud],
+            exclude: ids(vec![Stage::Ai, Stage::Cloud]),
+            ..Default::default()
         };
-        let result = cli.get_stages();
-        assert!(!result.contains(&Stage::Ai));
-        assert!(!result.contains(&Stage::Cloud));
+        let result = cli.get_stages(&[]).unwrap();
+        assert!(!result.contains(&Stage::Ai.into()));
+        assert!(!result.contains(&Stage::Cloud.into()));
         assert_eq!(result.len(), Stage::all().len() - 2);
     }
 
@@ -175,9 +556,73 @@ mod tests {
         let cli = Cli {
             stages: vec![],
             all: false,
-            exclude: Stage::all(),
+            exclude: ids(Stage::all()),
+            ..Default::default()
         };
-        let result = cli.get_stages();
+        let result = cli.get_stages(&[]).unwrap();
         assert_eq!(result.len(), 0);
     }
+
+    #[test]
+    fn test_scripted_stages() {
+        let scripts = vec!["vpn".to_string()];
+        let cli = Cli::parse_from(["install-nothing", "bios", "vpn"]);
+        assert_eq!(
+            cli.get_stages(&scripts).unwrap(),
+            vec![Stage::Bios.into(), StageId::Script("vpn".to_string())]
+        );
+        assert!(cli.get_stages(&[]).is_err());
+
+        let cli = Cli::parse_from(["install-nothing", "--exclude", "vpn"]);
+        assert_eq!(cli.get_stages(&scripts).unwrap(), ids(Stage::all()));
+    }
+
+    #[test]
+    fn test_wrapped_command() {
+        let cli = Cli::parse_from(["install-nothing", "bios", "--", "cargo", "build", "-q"]);
+        assert_eq!(cli.stages, ids(vec![Stage::Bios]));
+        let command = cli.wrapped().unwrap();
+        assert_eq!(command.get_program(), "cargo");
+        assert_eq!(command.get_args().collect::<Vec<_>>(), ["build", "-q"]);
+
+        assert!(Cli::parse_from(["install-nothing"]).wrapped().is_none());
+        assert!(Cli::try_parse_from(["install-nothing", "--wrap", "make", "--", "make"]).is_err());
+        assert!(
+            Cli::try_parse_from(["install-nothing", "--wrap", "make", "--cycles", "1"]).is_err()
+        );
+        assert!(Cli::try_parse_from(["install-nothing", "--wrap-log", "make.log"]).is_err());
+    }
+
+    #[test]
+    fn test_parse_speed() {
+        assert_eq!(parse_speed("0.25"), Ok(0.25));
+        assert_eq!(parse_speed("64"), Ok(64.0));
+        assert!(parse_speed("100").is_err());
+        assert!(parse_speed("0").is_err());
+        assert!(parse_speed("NaN").is_err());
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("15m"), Ok(Duration::from_secs(900)));
+        assert_eq!(parse_duration("1h30m"), Ok(Duration::from_secs(5400)));
+        assert_eq!(parse_duration("45"), Ok(Duration::from_secs(45)));
+        assert!(parse_duration("15x").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("0m").is_err());
+        assert!(parse_duration("9999999999999999999h").is_err());
+        assert!(parse_duration("18446744073709551615s1s").is_err());
+    }
+
+    #[test]
+    fn test_time_until() {
+        let now =
+            NaiveDateTime::parse_from_str("2024-05-01 14:00:00", "%Y-%m-%d %H:%M:%S").unwrap();
+        let later = parse_time_of_day("14:30").unwrap();
+        assert_eq!(time_until(later, now), Duration::from_secs(30 * 60));
+        let earlier = parse_time_of_day("13:00").unwrap();
+        assert_eq!(time_until(earlier, now), Duration::from_secs(23 * 3600));
+        assert!(parse_time_of_day("25:00").is_err());
+    }
 }
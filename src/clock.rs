@@ -0,0 +1,198 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// Slowest speed a clock runs at
+pub const MIN_SPEED: f64 = 0.0625;
+/// Fastest speed a clock runs at
+pub const MAX_SPEED: f64 = 64.0;
+
+/// Source of all delays in the installer
+///
+/// Stages ask the clock to wait a number of simulated milliseconds, and the
+/// clock turns that into real time according to the current speed factor.
+/// Clones share the same speed, so it can be changed while a stage runs.
+#[derive(Clone)]
+pub struct Clock {
+    speed: Arc<AtomicU64>,
+    /// Microseconds of playback time waited so far
+    elapsed: Arc<AtomicU64>,
+    /// Simulated milliseconds waited so far, regardless of speed
+    simulated: Arc<AtomicU64>,
+    instant: bool,
+}
+
+impl Clock {
+    /// Creates a clock running at `speed` times real time, within
+    /// `MIN_SPEED` and `MAX_SPEED`
+    pub fn new(speed: f64) -> Self {
+        Self {
+            speed: Arc::new(AtomicU64::new(bounded(speed).to_bits())),
+            elapsed: Arc::new(AtomicU64::new(0)),
+            simulated: Arc::new(AtomicU64::new(0)),
+            instant: false,
+        }
+    }
+
+    /// Creates a clock that never sleeps, for tests and offline rendering
+    pub fn instant() -> Self {
+        Self::new(1.0).without_waiting()
+    }
+
+    /// Keeps counting elapsed time but returns from every sleep immediately
+    pub fn without_waiting(mut self) -> Self {
+        self.instant = true;
+        self
+    }
+
+    pub fn is_instant(&self) -> bool {
+        self.instant
+    }
+
+    pub fn speed(&self) -> f64 {
+        f64::from_bits(self.speed.load(Ordering::Relaxed))
+    }
+
+    pub fn set_speed(&self, speed: f64) {
+        self.speed
+            .store(bounded(speed).to_bits(), Ordering::Relaxed);
+    }
+
+    /// Doubles the speed
+    pub fn faster(&self) {
+        self.set_speed(self.speed() * 2.0);
+    }
+
+    /// Halves the speed
+    pub fn slower(&self) {
+        self.set_speed(self.speed() / 2.0);
+    }
+
+    /// Converts simulated milliseconds into the time they take at the current speed
+    pub fn playback_duration(&self, ms: u64) -> Duration {
+        Duration::from_secs_f64(ms as f64 / 1000.0 / self.speed())
+    }
+
+    /// Converts simulated milliseconds into the real time they take
+    pub fn real_duration(&self, ms: u64) -> Duration {
+        if self.instant {
+            Duration::ZERO
+        } else {
+            self.playback_duration(ms)
+        }
+    }
+
+    /// Playback time waited so far by this clock and its clones
+    ///
+    /// An instant clock counts the delays it skips, so recordings made with it
+    /// keep the timing of a live run.
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_micros(self.elapsed.load(Ordering::Relaxed))
+    }
+
+    /// Simulated milliseconds waited so far, as if the speed had always been 1
+    pub fn simulated_ms(&self) -> u64 {
+        self.simulated.load(Ordering::Relaxed)
+    }
+
+    /// Counts `ms` simulated milliseconds as waited and returns the real time
+    /// the caller should wait for them
+    pub fn advance(&self, ms: u64) -> Duration {
+        self.simulated.fetch_add(ms, Ordering::Relaxed);
+        let playback = self.playback_duration(ms);
+        self.elapsed
+            .fetch_add(playback.as_micros() as u64, Ordering::Relaxed);
+
+        self.real_duration(ms)
+    }
+
+    /// Waits for `ms` simulated milliseconds
+    pub fn sleep(&self, ms: u64) {
+        let duration = self.advance(ms);
+        if !duration.is_zero() {
+            thread::sleep(duration);
+        }
+    }
+}
+
+/// Formats a duration as `HH:MM:SS`
+pub fn format_hms(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Self::new(1.0)
+    }
+}
+
+/// `speed` within `MIN_SPEED` and `MAX_SPEED`, NaN counts as too slow
+fn bounded(speed: f64) -> f64 {
+    if speed.is_nan() {
+        MIN_SPEED
+    } else {
+        speed.clamp(MIN_SPEED, MAX_SPEED)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_speed_scales_delays() {
+        let clock = Clock::new(4.0);
+        assert_eq!(clock.real_duration(1000), Duration::from_millis(250));
+        clock.set_speed(0.5);
+        assert_eq!(clock.real_duration(1000), Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn test_speed_is_clamped() {
+        let clock = Clock::default();
+        for _ in 0..20 {
+            clock.faster();
+        }
+        assert_eq!(clock.speed(), MAX_SPEED);
+        for _ in 0..40 {
+            clock.slower();
+        }
+        assert_eq!(clock.speed(), MIN_SPEED);
+
+        assert_eq!(Clock::new(0.0).speed(), MIN_SPEED);
+        assert_eq!(Clock::new(100.0).speed(), MAX_SPEED);
+    }
+
+    #[test]
+    fn test_clones_share_speed() {
+        let clock = Clock::default();
+        clock.clone().faster();
+        assert_eq!(clock.speed(), 2.0);
+    }
+
+    #[test]
+    fn test_instant_clock_does_not_sleep() {
+        let clock = Clock::instant();
+        let start = Instant::now();
+        clock.sleep(60_000);
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_elapsed_counts_skipped_delays() {
+        let clock = Clock::instant();
+        clock.sleep(1500);
+        clock.set_speed(2.0);
+        clock.clone().sleep(1000);
+        assert_eq!(clock.elapsed(), Duration::from_millis(2000));
+        assert_eq!(clock.simulated_ms(), 2500);
+    }
+}
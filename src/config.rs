@@ -1,6 +1,21 @@
+use crate::corpus::{CorpusName, LogCorpus, Synthesized};
+use crate::markov::LogMode;
+use crate::render::Style;
+use crate::stages::StageScript;
+use crate::ui::SpinnerStyle;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Display;
+use std::fs;
+use std::io;
+use std::num::NonZeroUsize;
 use std::ops::Range;
+use std::path::{Path, PathBuf};
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct SimulationConfig {
     pub ai: AiConfig,
     pub cloud: CloudConfig,
@@ -8,27 +23,126 @@ pub struct SimulationConfig {
     pub bios: BiosConfig,
     pub boot: BootConfig,
     pub bootloader: BootloaderConfig,
+    pub kernel: KernelConfig,
+    pub compilation: CompilationConfig,
+    pub network: NetworkConfig,
+    pub database: DatabaseConfig,
+    pub locale: LocaleConfig,
+    pub retro: RetroConfig,
+    pub packages: PackagesConfig,
+    pub system: SystemConfig,
+    pub drivers: DriversConfig,
+    pub filesystem: FilesystemConfig,
+    pub initramfs: InitramfsConfig,
+    pub services: ServicesConfig,
+    pub deno: DenoConfig,
+    pub corpus: CorpusConfig,
+    /// Custom themes by name, picked with `--theme`
+    pub themes: BTreeMap<String, ThemeConfig>,
+    /// Spinner style for every stage, instead of the one each stage picks
+    pub spinner: Option<SpinnerStyle>,
+    /// Log lines loaded from the files in `corpus`, see `LogCorpus::load`
+    #[serde(skip)]
+    pub logs: LogCorpus,
+    /// Stages loaded from stage scripts, see `stages::load_scripts`
+    #[serde(skip)]
+    pub scripts: Vec<StageScript>,
 }
 
-impl Default for SimulationConfig {
-    fn default() -> Self {
-        Self {
-            ai: AiConfig::default(),
-            cloud: CloudConfig::default(),
-            container: ContainerConfig::default(),
-            bios: BiosConfig::default(),
-            boot: BootConfig::default(),
-            bootloader: BootloaderConfig::default(),
+impl SimulationConfig {
+    /// Loads the configuration from `path`, or from the user's config directory
+    /// when no path is given. Without any config file the defaults are used.
+    pub fn load(path: Option<&Path>) -> io::Result<Self> {
+        match path.map(Path::to_path_buf).or_else(Self::discover) {
+            Some(path) => Self::from_file(&path),
+            None => Ok(Self::default()),
+        }
+    }
+
+    /// Makes every stage that replays a log use `mode`
+    pub fn set_log_mode(&mut self, mode: LogMode) {
+        self.boot.log_mode = mode;
+        self.kernel.log_mode = mode;
+        self.compilation.log_mode = mode;
+        self.deno.log_mode = mode;
+    }
+
+    /// The logs that stages make up lines from, for `LogCorpus::load`
+    pub fn synthesized(&self) -> Synthesized {
+        let synthesize = |mode| mode == LogMode::Synthesize;
+        Synthesized {
+            kernel: synthesize(self.boot.log_mode) || synthesize(self.kernel.log_mode),
+            build: synthesize(self.compilation.log_mode),
+            deno: synthesize(self.deno.log_mode),
         }
     }
+
+    /// Returns `~/.config/install-nothing/config.toml` if it exists
+    fn discover() -> Option<PathBuf> {
+        let path = config_dir()?.join("config.toml");
+        path.is_file().then_some(path)
+    }
+
+    /// Reads a config file, treating `.json` files as JSON and anything else as TOML
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot read config file {}: {}", path.display(), e),
+            )
+        })?;
+
+        let parsed = if path.extension().is_some_and(|ext| ext == "json") {
+            Self::from_json(&content)
+        } else {
+            Self::from_toml(&content)
+        };
+
+        parsed.map_err(|message| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid config file {}: {}", path.display(), message),
+            )
+        })
+    }
+
+    pub fn from_toml(content: &str) -> Result<Self, String> {
+        serde_path_to_error::deserialize(toml::Deserializer::new(content)).map_err(|e| {
+            if e.path().iter().next().is_none() {
+                e.inner().to_string()
+            } else {
+                format!("`{}`: {}", e.path(), e.inner().message())
+            }
+        })
+    }
+
+    pub fn from_json(content: &str) -> Result<Self, String> {
+        let mut deserializer = serde_json::Deserializer::from_str(content);
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+            if e.path().iter().next().is_none() {
+                e.inner().to_string()
+            } else {
+                format!("`{}`: {}", e.path(), e.inner())
+            }
+        })
+    }
 }
 
-#[derive(Clone)]
+/// Returns `~/.config/install-nothing`, whether it exists or not
+pub fn config_dir() -> Option<PathBuf> {
+    let config_home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("install-nothing"))
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct BiosConfig {
-    pub vendor: &'static str,
-    pub version: &'static str,
-    pub new_version: &'static str,
-    pub bios_date: &'static str,
+    pub vendor: String,
+    pub version: String,
+    pub new_version: String,
+    pub bios_date: String,
     pub header_delay: u64,
     pub post_start_delay: u64,
     pub cpu_detect_time: u64,
@@ -51,25 +165,26 @@ pub struct BiosConfig {
     pub backup_time: u64,
     pub verify_time: u64,
     pub warning_delay: u64,
-    pub erase_min: u64,
-    pub erase_max: u64,
-    pub write_min: u64,
-    pub write_max: u64,
-    pub verify_min: u64,
-    pub verify_max: u64,
+    #[serde(deserialize_with = "range")]
+    pub erase_time_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub write_time_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub verify_time_range: Range<u64>,
     pub complete_time: u64,
     pub escd_time: u64,
     pub success_delay: u64,
+    #[serde(deserialize_with = "probability")]
     pub cmos_error_chance: f64,
 }
 
 impl Default for BiosConfig {
     fn default() -> Self {
         Self {
-            vendor: "American Megatrends BIOS (C)2003-2025",
-            version: "AMIBIOS v08.00.15",
-            new_version: "v08.00.16",
-            bios_date: "11/15/2025",
+            vendor: "American Megatrends BIOS (C)2003-2025".to_string(),
+            version: "AMIBIOS v08.00.15".to_string(),
+            new_version: "v08.00.16".to_string(),
+            bios_date: "11/15/2025".to_string(),
             header_delay: 400,
             post_start_delay: 400,
             cpu_detect_time: 800,
@@ -92,12 +207,9 @@ impl Default for BiosConfig {
             backup_time: 1800,
             verify_time: 1200,
             warning_delay: 800,
-            erase_min: 1500,
-            erase_max: 2500,
-            write_min: 3000,
-            write_max: 5000,
-            verify_min: 2000,
-            verify_max: 3500,
+            erase_time_range: 1500..2500,
+            write_time_range: 3000..5000,
+            verify_time_range: 2000..3500,
             complete_time: 800,
             escd_time: 1000,
             success_delay: 600,
@@ -106,11 +218,15 @@ impl Default for BiosConfig {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct BootConfig {
+    #[serde(deserialize_with = "range")]
     pub log_count_range: Range<usize>,
+    #[serde(deserialize_with = "range")]
     pub log_delay_range: Range<u64>,
     pub final_delay: u64,
+    pub log_mode: LogMode,
 }
 
 impl Default for BootConfig {
@@ -119,19 +235,47 @@ impl Default for BootConfig {
             log_count_range: 8..15,
             log_delay_range: 50..200,
             final_delay: 300,
+            log_mode: LogMode::Sample,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KernelConfig {
+    pub log_mode: LogMode,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CompilationConfig {
+    pub log_mode: LogMode,
+    /// Files compiled at the same time, as with `make -j`
+    pub jobs: NonZeroUsize,
+}
+
+impl Default for CompilationConfig {
+    fn default() -> Self {
+        Self {
+            log_mode: LogMode::default(),
+            jobs: NonZeroUsize::new(4).expect("4 is not zero"),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct BootloaderConfig {
     pub install_delay: u64,
     pub probe_delay: u64,
     pub device_install_delay: u64,
     pub config_gen_delay: u64,
+    #[serde(deserialize_with = "range")]
     pub kernel_scan_delay_range: Range<u64>,
+    #[serde(deserialize_with = "probability")]
     pub windows_found_chance: f64,
     pub windows_delay: u64,
+    #[serde(deserialize_with = "range")]
     pub write_stage_delay_range: Range<u64>,
     pub finish_delay: u64,
 }
@@ -152,22 +296,31 @@ impl Default for BootloaderConfig {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct AiConfig {
+    #[serde(deserialize_with = "range")]
     pub model_download_speed_range: Range<u64>,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_network: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_checksum: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_kernel_panic: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_oom: f64,
+    #[serde(deserialize_with = "range")]
     pub layer_load_delay_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
     pub compilation_speed_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
     pub checksum_delay_range: Range<u64>,
 }
 
 impl Default for AiConfig {
     fn default() -> Self {
         Self {
-            model_download_speed_range: 1000..3000,
+            model_download_speed_range: 3000..6000,
             failure_rate_network: 0.15,
             failure_rate_checksum: 0.05,
             failure_rate_kernel_panic: 0.1,
@@ -179,12 +332,18 @@ impl Default for AiConfig {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CloudConfig {
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_rate_limit: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_insufficient_capacity: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_dependency_violation: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_checksum_mismatch: f64,
+    #[serde(deserialize_with = "range")]
     pub provision_speed_range: Range<u64>,
 }
 
@@ -200,15 +359,25 @@ impl Default for CloudConfig {
     }
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct ContainerConfig {
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_image_pull: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_readiness_probe: f64,
+    #[serde(deserialize_with = "probability")]
     pub failure_rate_crash_loop: f64,
+    #[serde(deserialize_with = "probability")]
     pub probability_volume_mount: f64,
+    #[serde(deserialize_with = "probability")]
     pub probability_secret_mount: f64,
+    #[serde(deserialize_with = "probability")]
     pub probability_sidecar_injection: f64,
+    #[serde(deserialize_with = "range")]
     pub layer_pull_speed_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub kubectl_download_range: Range<u64>,
 }
 
 impl Default for ContainerConfig {
@@ -221,6 +390,475 @@ impl Default for ContainerConfig {
             probability_secret_mount: 0.3,
             probability_sidecar_injection: 0.6,
             layer_pull_speed_range: 150..2500,
+            kubectl_download_range: 2000..4000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct NetworkConfig {
+    #[serde(deserialize_with = "non_empty")]
+    pub interfaces: Vec<String>,
+    pub dns_servers: String,
+    pub dhcp_time: u64,
+    pub dns_time: u64,
+    #[serde(deserialize_with = "probability")]
+    pub connectivity_test_chance: f64,
+    pub connectivity_test_time: u64,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            interfaces: strings(&["eth0", "enp0s3", "wlan0"]),
+            dns_servers: "8.8.8.8, 8.8.4.4".to_string(),
+            dhcp_time: 2000,
+            dns_time: 1200,
+            connectivity_test_chance: 0.3,
+            connectivity_test_time: 1500,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DatabaseConfig {
+    #[serde(deserialize_with = "probability")]
+    pub mysql_chance: f64,
+    pub cluster_init_time: u64,
+    #[serde(deserialize_with = "range")]
+    pub file_delay_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub system_tables_time_range: Range<u64>,
+    pub template_time: u64,
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            mysql_chance: 0.5,
+            cluster_init_time: 2000,
+            file_delay_range: 150..300,
+            system_tables_time_range: 2000..3500,
+            template_time: 1500,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LocaleConfig {
+    #[serde(deserialize_with = "non_empty")]
+    pub locales: Vec<String>,
+    #[serde(deserialize_with = "non_empty")]
+    pub timezones: Vec<String>,
+    #[serde(deserialize_with = "range")]
+    pub locale_delay_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub archive_time_range: Range<u64>,
+}
+
+impl Default for LocaleConfig {
+    fn default() -> Self {
+        Self {
+            locales: strings(&[
+                "en_US.UTF-8",
+                "en_GB.UTF-8",
+                "de_DE.UTF-8",
+                "fr_FR.UTF-8",
+                "es_ES.UTF-8",
+                "ja_JP.UTF-8",
+                "zh_CN.UTF-8",
+            ]),
+            timezones: strings(&[
+                "America/New_York",
+                "America/Los_Angeles",
+                "Europe/London",
+                "Europe/Berlin",
+                "Asia/Tokyo",
+            ]),
+            locale_delay_range: 300..700,
+            archive_time_range: 2000..3000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetroConfig {
+    #[serde(deserialize_with = "probability")]
+    pub previous_install_chance: f64,
+    #[serde(deserialize_with = "range")]
+    pub download_time_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub extract_time_range: Range<u64>,
+    #[serde(deserialize_with = "probability")]
+    pub file_associations_chance: f64,
+}
+
+impl Default for RetroConfig {
+    fn default() -> Self {
+        Self {
+            previous_install_chance: 0.2,
+            download_time_range: 1000..3000,
+            extract_time_range: 2000..4000,
+            file_associations_chance: 0.3,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PackagesConfig {
+    pub read_lists_time: u64,
+    pub dependency_tree_time: u64,
+    #[serde(deserialize_with = "range")]
+    pub size_kb_range: Range<u32>,
+    #[serde(deserialize_with = "speed_range")]
+    pub speed_kb_range: Range<u32>,
+    #[serde(deserialize_with = "range")]
+    pub unpack_time_range: Range<u64>,
+    #[serde(deserialize_with = "probability")]
+    pub setup_chance: f64,
+}
+
+impl Default for PackagesConfig {
+    fn default() -> Self {
+        Self {
+            read_lists_time: 1200,
+            dependency_tree_time: 1500,
+            size_kb_range: 512..8192,
+            speed_kb_range: 2048..8192,
+            unpack_time_range: 300..900,
+            setup_chance: 0.4,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SystemConfig {
+    #[serde(deserialize_with = "range")]
+    pub component_delay_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub module_deps_time_range: Range<u64>,
+}
+
+impl Default for SystemConfig {
+    fn default() -> Self {
+        Self {
+            component_delay_range: 300..800,
+            module_deps_time_range: 2000..3500,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DriversConfig {
+    #[serde(deserialize_with = "range")]
+    pub detect_delay_range: Range<u64>,
+    pub load_delay: u64,
+}
+
+impl Default for DriversConfig {
+    fn default() -> Self {
+        Self {
+            detect_delay_range: 400..900,
+            load_delay: 300,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FilesystemConfig {
+    #[serde(deserialize_with = "range")]
+    pub group_tables_time_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub inode_tables_time_range: Range<u64>,
+    #[serde(deserialize_with = "probability")]
+    pub fsck_chance: f64,
+}
+
+impl Default for FilesystemConfig {
+    fn default() -> Self {
+        Self {
+            group_tables_time_range: 2000..3000,
+            inode_tables_time_range: 2500..4000,
+            fsck_chance: 0.4,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct InitramfsConfig {
+    pub kernel_version: String,
+    #[serde(deserialize_with = "range")]
+    pub module_delay_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub copy_time_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub image_time_range: Range<u64>,
+}
+
+impl Default for InitramfsConfig {
+    fn default() -> Self {
+        Self {
+            kernel_version: "5.4.0-42-generic".to_string(),
+            module_delay_range: 100..250,
+            copy_time_range: 2000..3500,
+            image_time_range: 1000..2000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServicesConfig {
+    #[serde(deserialize_with = "range")]
+    pub start_delay_range: Range<u64>,
+    #[serde(deserialize_with = "range")]
+    pub settle_delay_range: Range<u64>,
+}
+
+impl Default for ServicesConfig {
+    fn default() -> Self {
+        Self {
+            start_delay_range: 300..800,
+            settle_delay_range: 100..300,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DenoConfig {
+    #[serde(deserialize_with = "probability")]
+    pub failure_chance: f64,
+    pub log_mode: LogMode,
+}
+
+impl Default for DenoConfig {
+    fn default() -> Self {
+        Self {
+            failure_chance: 0.3,
+            log_mode: LogMode::Replay,
         }
     }
 }
+
+/// Log files that replace the built-in ones
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CorpusConfig {
+    /// Directory holding any of `kernel.log`, `build.log`, `deno.log` and
+    /// `deno-error.log`
+    pub dir: Option<PathBuf>,
+    pub kernel: Option<PathBuf>,
+    pub build: Option<PathBuf>,
+    pub deno: Option<PathBuf>,
+    pub deno_error: Option<PathBuf>,
+}
+
+impl CorpusConfig {
+    pub fn set(&mut self, name: CorpusName, path: PathBuf) {
+        let slot = match name {
+            CorpusName::Kernel => &mut self.kernel,
+            CorpusName::Build => &mut self.build,
+            CorpusName::Deno => &mut self.deno,
+            CorpusName::DenoError => &mut self.deno_error,
+        };
+        *slot = Some(path);
+    }
+
+    /// The file to read for `name`, if any: the one set explicitly, or else
+    /// the one in `dir` when it exists
+    pub fn path(&self, name: CorpusName) -> Option<PathBuf> {
+        let explicit = match name {
+            CorpusName::Kernel => &self.kernel,
+            CorpusName::Build => &self.build,
+            CorpusName::Deno => &self.deno,
+            CorpusName::DenoError => &self.deno_error,
+        };
+        explicit.clone().or_else(|| {
+            let path = self.dir.as_ref()?.join(name.file_name());
+            path.is_file().then_some(path)
+        })
+    }
+}
+
+/// A theme from the config file: a preset with some of its styles replaced
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ThemeConfig {
+    /// Preset to start from, defaults to the preset of the same name or `default`
+    pub base: Option<String>,
+    pub plain: Option<Style>,
+    pub dim: Option<Style>,
+    pub bright: Option<Style>,
+    pub header: Option<Style>,
+    pub accent: Option<Style>,
+    pub highlight: Option<Style>,
+    pub success: Option<Style>,
+    pub warning: Option<Style>,
+    pub error: Option<Style>,
+    pub special: Option<Style>,
+    pub info: Option<Style>,
+    pub progress_fill: Option<Style>,
+    pub progress_empty: Option<Style>,
+}
+
+impl ThemeConfig {
+    /// The styles in the order a theme keeps them, `None` where the base is kept
+    pub fn styles(&self) -> [Option<Style>; 13] {
+        [
+            self.plain,
+            self.dim,
+            self.bright,
+            self.header,
+            self.accent,
+            self.highlight,
+            self.success,
+            self.warning,
+            self.error,
+            self.special,
+            self.info,
+            self.progress_fill,
+            self.progress_empty,
+        ]
+    }
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(|item| item.to_string()).collect()
+}
+
+/// Deserializes a `[min, max]` pair into a non-empty `min..max` range
+pub(crate) fn range<'de, D, T>(deserializer: D) -> Result<Range<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + PartialOrd + Display,
+{
+    let [min, max] = <[T; 2]>::deserialize(deserializer)?;
+    if min >= max {
+        return Err(de::Error::custom(format!(
+            "range [{}, {}] is empty, expected [min, max] with min < max",
+            min, max
+        )));
+    }
+    Ok(min..max)
+}
+
+/// Deserializes a range of speeds that durations are divided by, which must
+/// not start at zero
+pub(crate) fn speed_range<'de, D>(deserializer: D) -> Result<Range<u32>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let speeds = range(deserializer)?;
+    if speeds.start == 0 {
+        return Err(de::Error::custom(
+            "speed range starts at 0, expected speeds above 0",
+        ));
+    }
+    Ok(speeds)
+}
+
+/// Deserializes a list that stages pick random entries from
+pub(crate) fn non_empty<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let items = Vec::<String>::deserialize(deserializer)?;
+    if items.is_empty() {
+        return Err(de::Error::custom(
+            "list is empty, expected at least one entry",
+        ));
+    }
+    Ok(items)
+}
+
+/// Deserializes a chance, rejecting values outside of `0.0..=1.0`
+pub(crate) fn probability<'de, D>(deserializer: D) -> Result<f64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = f64::deserialize(deserializer)?;
+    if !(0.0..=1.0).contains(&value) {
+        return Err(de::Error::custom(format!(
+            "probability {} is out of range, expected a value between 0 and 1",
+            value
+        )));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_config_uses_defaults() {
+        let config = SimulationConfig::from_toml("").unwrap();
+        assert_eq!(config.boot.log_count_range, 8..15);
+        assert_eq!(config.bios.vendor, BiosConfig::default().vendor);
+    }
+
+    #[test]
+    fn test_partial_override() {
+        let config = SimulationConfig::from_toml(
+            r#"
+            [bios]
+            vendor = "Phoenix BIOS 4.0"
+
+            [boot]
+            log_count_range = [2, 4]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.bios.vendor, "Phoenix BIOS 4.0");
+        assert_eq!(config.bios.version, BiosConfig::default().version);
+        assert_eq!(config.boot.log_count_range, 2..4);
+        assert_eq!(config.boot.final_delay, BootConfig::default().final_delay);
+    }
+
+    #[test]
+    fn test_json_config() {
+        let config =
+            SimulationConfig::from_json(r#"{ "network": { "interfaces": ["eth1"] } }"#).unwrap();
+        assert_eq!(config.network.interfaces, vec!["eth1".to_string()]);
+    }
+
+    #[test]
+    fn test_unknown_key_is_named() {
+        let err = SimulationConfig::from_toml("[bios]\nvendr = \"x\"").unwrap_err();
+        assert!(err.contains("vendr"), "{}", err);
+    }
+
+    #[test]
+    fn test_bad_value_names_key() {
+        let err = SimulationConfig::from_toml("[bios]\ncpu_detect_time = \"fast\"").unwrap_err();
+        assert!(err.contains("bios.cpu_detect_time"), "{}", err);
+    }
+
+    #[test]
+    fn test_empty_range_rejected() {
+        let err = SimulationConfig::from_toml("[boot]\nlog_count_range = [5, 3]").unwrap_err();
+        assert!(err.contains("boot.log_count_range"), "{}", err);
+        let err = SimulationConfig::from_toml("[packages]\nspeed_kb_range = [0, 10]").unwrap_err();
+        assert!(err.contains("packages.speed_kb_range"), "{}", err);
+        let err =
+            SimulationConfig::from_toml("[bios]\nerase_time_range = [3000, 2500]").unwrap_err();
+        assert!(err.contains("bios.erase_time_range"), "{}", err);
+    }
+
+    #[test]
+    fn test_probability_out_of_range_rejected() {
+        let err =
+            SimulationConfig::from_json(r#"{ "ai": { "failure_rate_oom": 1.5 } }"#).unwrap_err();
+        assert!(err.contains("ai.failure_rate_oom"), "{}", err);
+    }
+}
@@ -0,0 +1,345 @@
+use crate::clock::Clock;
+use crate::input::{Input, Key};
+use crate::log_generator::LogGenerator;
+use crate::render::{Event, Line, Renderer, Tone};
+use crate::stats::Stats;
+use crate::ui::SpinnerStyle;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Display;
+use std::io;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Why the current stage should stop early
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stop {
+    /// End the whole run
+    Quit,
+    /// Move on to the next stage
+    Skip,
+    /// Cut to something that looks like real work
+    Boss,
+    /// The run was ended from outside, see `end_when`
+    End,
+}
+
+/// How long a wait lasts at most while the run can be ended from outside
+const END_CHECK: Duration = Duration::from_millis(100);
+
+/// Per-session state handed to every stage
+pub struct Context {
+    /// Source of every random choice, seeded so that a run can be replayed
+    pub rng: StdRng,
+    pub clock: Clock,
+    /// What happened so far, for the summary at the end
+    pub stats: Stats,
+    /// Spinner style that replaces the one each stage picks
+    pub spinner: Option<SpinnerStyle>,
+    renderer: Box<dyn Renderer>,
+    input: Input,
+    stop: Option<Stop>,
+    error: Option<io::Error>,
+    ended: Option<Arc<AtomicBool>>,
+    /// Simulated uptime of the last stamped line, in seconds
+    uptime: f64,
+}
+
+impl Context {
+    /// A session that draws with `renderer` and reads hotkeys from `input`
+    pub fn new(seed: u64, clock: Clock, renderer: Box<dyn Renderer>, input: Input) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            clock,
+            stats: Stats::default(),
+            spinner: None,
+            renderer,
+            input,
+            stop: None,
+            error: None,
+            ended: None,
+            uptime: 0.0,
+        }
+    }
+
+    /// Stops the current stage with `Stop::End` once `ended` is set, by
+    /// another thread
+    pub fn end_when(&mut self, ended: Arc<AtomicBool>) {
+        self.ended = Some(ended);
+    }
+
+    /// Whether the run was ended from outside
+    pub fn has_ended(&self) -> bool {
+        self.ended
+            .as_ref()
+            .is_some_and(|ended| ended.load(Ordering::Relaxed))
+    }
+
+    /// Waits up to `timeout` for a key, noticing meanwhile when the run was
+    /// ended from outside
+    fn next_key(&mut self, timeout: Duration) -> Option<Key> {
+        let timeout = match self.ended {
+            Some(_) => timeout.min(END_CHECK),
+            None => timeout,
+        };
+        let key = self.input.next(timeout);
+        if self.stop.is_none() && self.has_ended() {
+            self.stop = Some(Stop::End);
+        }
+        key
+    }
+
+    /// Waits for `ms` simulated milliseconds, reacting to keys meanwhile
+    ///
+    /// Returns early once a key asks to skip or quit, and does not count the
+    /// time spent paused.
+    pub fn sleep(&mut self, ms: u64) {
+        let mut until = Instant::now() + self.clock.advance(ms);
+        while self.stop.is_none() {
+            let now = Instant::now();
+            if now >= until {
+                break;
+            }
+            if let Some(key) = self.next_key(until - now) {
+                // Only pausing and the help take time, which is added back
+                let handled = Instant::now();
+                self.handle_key(key);
+                until += handled.elapsed();
+            }
+        }
+    }
+
+    /// Waits for a random number of simulated milliseconds within `range`
+    pub fn sleep_range(&mut self, range: Range<u64>) {
+        let ms = self.rng.gen_range(range);
+        self.sleep(ms);
+    }
+
+    /// Handles pending key presses, returning true when the current stage
+    /// should stop, because of a key or because the output can no longer be
+    /// written
+    pub fn check_exit(&mut self) -> bool {
+        while self.stop.is_none() {
+            match self.next_key(Duration::ZERO) {
+                Some(key) => self.handle_key(key),
+                None => break,
+            }
+        }
+        self.stop.is_some() || self.error.is_some()
+    }
+
+    /// Clears and returns the reason the last stage stopped early
+    pub fn take_stop(&mut self) -> Option<Stop> {
+        self.stop.take()
+    }
+
+    /// Whether someone can answer prompts
+    pub fn is_interactive(&self) -> bool {
+        self.input.is_interactive()
+    }
+
+    /// Waits up to `timeout` for a character that is not a hotkey, for prompts
+    pub fn read_char(&mut self, timeout: Duration) -> Option<char> {
+        match self.next_key(timeout) {
+            Some(Key::Char(c)) => Some(c),
+            Some(key) => {
+                self.handle_key(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn handle_key(&mut self, key: Key) {
+        match key {
+            Key::Quit => self.stop = Some(Stop::Quit),
+            Key::Skip => self.stop = Some(Stop::Skip),
+            Key::Boss => self.stop = Some(Stop::Boss),
+            Key::Faster => self.clock.faster(),
+            Key::Slower => self.clock.slower(),
+            Key::Pause => self.pause(),
+            Key::Help => self.show_help(),
+            Key::Char(_) => {}
+        }
+    }
+
+    /// Blocks until the user resumes, leaving the screen as it is
+    fn pause(&mut self) {
+        self.emit(&Event::Paused(true));
+        while self.stop.is_none() && self.error.is_none() {
+            match self.next_key(Duration::from_millis(100)) {
+                Some(Key::Pause) => break,
+                Some(Key::Help) => self.show_help(),
+                Some(key) => self.handle_key(key),
+                None => {}
+            }
+        }
+        self.emit(&Event::Paused(false));
+    }
+
+    /// Shows the hotkeys until any key is pressed
+    fn show_help(&mut self) {
+        let hotkeys = self.input.hotkeys();
+        self.emit(&Event::Help(Some(&hotkeys)));
+        while self.stop.is_none() && self.error.is_none() {
+            match self.next_key(Duration::from_millis(100)) {
+                Some(Key::Quit) => {
+                    self.stop = Some(Stop::Quit);
+                    break;
+                }
+                Some(Key::Boss) => {
+                    self.stop = Some(Stop::Boss);
+                    break;
+                }
+                Some(_) => break,
+                None => {}
+            }
+        }
+        self.emit(&Event::Help(None));
+    }
+
+    /// Columns the renderer has for a line, for output that should fit on one
+    pub fn columns(&self) -> usize {
+        self.renderer.columns()
+    }
+
+    /// Sends an event to the renderer
+    ///
+    /// A failed write is kept until the installer picks it up with
+    /// `take_error`, meanwhile `check_exit` asks stages to stop.
+    pub fn emit(&mut self, event: &Event) {
+        if self.error.is_none() {
+            if let Err(e) = self.renderer.render(event) {
+                self.error = Some(e);
+            }
+        }
+    }
+
+    pub fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+
+    /// Puts the next simulated timestamp in front of `line`
+    pub fn stamp(&mut self, line: Line) -> Line {
+        self.uptime = LogGenerator::timestamp(self.uptime, &mut self.rng);
+        line.stamped(self.uptime)
+    }
+
+    /// Logs a timestamped line in a single tone
+    pub fn log(&mut self, tone: Tone, text: impl Display) {
+        self.log_line(Line::styled(tone, text));
+    }
+
+    /// Logs a timestamped line
+    pub fn log_line(&mut self, line: Line) {
+        let line = self.stamp(line);
+        self.emit(&Event::Line(&line));
+    }
+
+    /// Starts a timestamped line that is completed with `resolve`
+    pub fn log_pending(&mut self, line: Line) {
+        let line = self.stamp(line);
+        self.emit(&Event::Pending(&line));
+    }
+
+    /// Shows a timestamped status line that the next output replaces
+    pub fn log_transient(&mut self, line: Line) {
+        let line = self.stamp(line);
+        self.emit(&Event::Transient(&line));
+    }
+
+    /// Logs a timestamped line about trying again, counting the retry
+    pub fn log_retry(&mut self, line: Line) {
+        let line = self.stamp(line);
+        self.retry(line);
+    }
+
+    /// Prints a line about trying again, counting the retry
+    pub fn retry(&mut self, line: Line) {
+        self.stats.retries += 1;
+        self.emit(&Event::Retry(&line));
+    }
+
+    /// Prints a line in a single tone, without a timestamp
+    pub fn print(&mut self, tone: Tone, text: impl Display) {
+        self.print_line(Line::styled(tone, text));
+    }
+
+    pub fn print_line(&mut self, line: Line) {
+        self.emit(&Event::Line(&line));
+    }
+
+    /// Starts a line that is completed with `resolve`
+    pub fn pending(&mut self, line: Line) {
+        self.emit(&Event::Pending(&line));
+    }
+
+    pub fn resolve(&mut self, line: Line) {
+        self.emit(&Event::Resolve(&line));
+    }
+
+    pub fn prompt(&mut self, line: Line) {
+        self.emit(&Event::Prompt(&line));
+    }
+
+    pub fn blank(&mut self) {
+        self.emit(&Event::Blank);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{AnsiRenderer, Theme};
+    use std::sync::mpsc;
+    use std::thread;
+
+    #[test]
+    fn test_pause_does_not_shorten_sleep() {
+        let (keys, chars) = mpsc::channel();
+        let renderer = AnsiRenderer::new(io::sink(), Theme::default());
+        let mut ctx = Context::new(1, Clock::new(1.0), Box::new(renderer), Input::remote(chars));
+        keys.send(' ').unwrap();
+        let resume = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            keys.send(' ').unwrap();
+            // Keeps the channel open until the sleep is over
+            thread::sleep(Duration::from_millis(300));
+        });
+
+        let started = Instant::now();
+        ctx.sleep(100);
+        assert!(
+            started.elapsed() >= Duration::from_millis(250),
+            "{:?}",
+            started.elapsed()
+        );
+        resume.join().unwrap();
+    }
+
+    #[test]
+    fn test_sessions_keep_their_own_uptime() {
+        let session = || {
+            let (_keys, chars) = mpsc::channel();
+            let renderer = AnsiRenderer::new(io::sink(), Theme::default());
+            Context::new(
+                7,
+                Clock::instant(),
+                Box::new(renderer),
+                Input::remote(chars),
+            )
+        };
+        let uptime = |ctx: &mut Context| {
+            ctx.stamp(Line::styled(Tone::Plain, ""))
+                .stamp
+                .unwrap()
+                .seconds
+        };
+        let mut first = session();
+        let earlier = uptime(&mut first);
+        assert!(uptime(&mut first) > earlier);
+        assert_eq!(uptime(&mut session()), earlier);
+    }
+}
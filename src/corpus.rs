@@ -0,0 +1,209 @@
+use crate::build_logs::BuildLogs;
+use crate::config::CorpusConfig;
+use crate::deno_logs::DenoLogs;
+use crate::kernel_logs::KernelLogs;
+use clap::ValueEnum;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// A built-in log that can be swapped for a file of your own
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum CorpusName {
+    /// Kernel messages, for the boot and kernel stages
+    Kernel,
+    /// Build output, for the compilation stage
+    Build,
+    /// Output of a successful Deno build
+    Deno,
+    /// Output of a failed Deno build
+    DenoError,
+}
+
+impl CorpusName {
+    /// Name of the file looked up in a corpus directory
+    pub fn file_name(self) -> &'static str {
+        match self {
+            CorpusName::Kernel => "kernel.log",
+            CorpusName::Build => "build.log",
+            CorpusName::Deno => "deno.log",
+            CorpusName::DenoError => "deno-error.log",
+        }
+    }
+}
+
+/// Logs that some stage makes up new lines from, which get their model
+/// trained as they are loaded
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Synthesized {
+    pub kernel: bool,
+    pub build: bool,
+    pub deno: bool,
+}
+
+/// Log lines replayed by the boot, kernel, compilation and Deno stages
+#[derive(Clone, Debug)]
+pub struct LogCorpus {
+    pub kernel: KernelLogs,
+    pub build: BuildLogs,
+    pub deno: DenoLogs,
+}
+
+impl LogCorpus {
+    /// Reads the files picked in `config`, keeping the built-in logs for the
+    /// rest, and trains the models of the `synthesized` ones
+    pub fn load(config: &CorpusConfig, synthesized: Synthesized) -> io::Result<Self> {
+        if let Some(dir) = &config.dir {
+            if !dir.is_dir() {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("corpus directory {} does not exist", dir.display()),
+                ));
+            }
+        }
+
+        let mut corpus = Self::default();
+        if let Some(path) = config.path(CorpusName::Kernel) {
+            // Every kernel line has a timestamp, the stages print their own
+            let logs = read_lines(&path)?
+                .iter()
+                .map(|line| KernelLogs::strip_timestamp(line))
+                .collect();
+            corpus.kernel = KernelLogs::from_lines(logs);
+        }
+        if let Some(path) = config.path(CorpusName::Build) {
+            corpus.build = BuildLogs::from_lines(read_stamped_lines(&path)?);
+        }
+        if let Some(path) = config.path(CorpusName::Deno) {
+            corpus.deno = corpus.deno.with_success_logs(read_stamped_lines(&path)?);
+        }
+        if let Some(path) = config.path(CorpusName::DenoError) {
+            corpus.deno = corpus.deno.with_error_logs(read_stamped_lines(&path)?);
+        }
+
+        if synthesized.kernel {
+            corpus.kernel = corpus.kernel.trained();
+        }
+        if synthesized.build {
+            corpus.build = corpus.build.trained();
+        }
+        if synthesized.deno {
+            corpus.deno = corpus.deno.trained();
+        }
+        Ok(corpus)
+    }
+}
+
+impl Default for LogCorpus {
+    fn default() -> Self {
+        Self {
+            kernel: KernelLogs::load(),
+            build: BuildLogs::load(),
+            deno: DenoLogs::load(),
+        }
+    }
+}
+
+/// Reads the non-empty lines of a log file
+fn read_lines(path: &Path) -> io::Result<Vec<String>> {
+    let content = fs::read_to_string(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("cannot read corpus {}: {}", path.display(), e),
+        )
+    })?;
+    let lines: Vec<String> = content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(String::from)
+        .collect();
+    if lines.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("corpus {} has no lines", path.display()),
+        ));
+    }
+    Ok(lines)
+}
+
+/// Reads a log file, dropping the timestamp in front of lines that have one,
+/// a kernel uptime or a clock time such as `[12:00:01]`
+fn read_stamped_lines(path: &Path) -> io::Result<Vec<String>> {
+    Ok(read_lines(path)?
+        .into_iter()
+        .map(|line| strip_clock_time(&KernelLogs::strip_timestamp(&line)))
+        .collect())
+}
+
+/// Converts "[12:00:01] message" to "message", other lines are kept
+fn strip_clock_time(line: &str) -> String {
+    let message = line.strip_prefix('[').and_then(|rest| {
+        let (time, message) = rest.split_once(']')?;
+        let fields: Vec<&str> = time.split(':').collect();
+        let clock = fields.len() == 3
+            && fields
+                .iter()
+                .all(|field| field.len() == 2 && field.bytes().all(|b| b.is_ascii_digit()));
+        clock.then(|| message.trim_start())
+    });
+    message.unwrap_or(line).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::markov::LogMode;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::env;
+
+    #[test]
+    fn test_load_from_directory() {
+        let dir = env::temp_dir().join(format!("install-nothing-corpus-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("kernel.log"),
+            "[    0.000000] Linux version 6.1.0\n\n[    0.000001] Command line: quiet\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("build.log"),
+            "[12:00:01] CC      init/main.o\n  LD      vmlinux\n[ 45%] Building C object\n",
+        )
+        .unwrap();
+
+        let config = CorpusConfig {
+            dir: Some(dir.clone()),
+            ..Default::default()
+        };
+        let synthesized = Synthesized {
+            kernel: true,
+            ..Default::default()
+        };
+        let corpus = LogCorpus::load(&config, synthesized).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(
+            corpus.kernel.all_logs(),
+            ["Linux version 6.1.0", "Command line: quiet"]
+        );
+        let mut rng = StdRng::seed_from_u64(0);
+        // Made up by the model trained on load, with new version numbers
+        let made_up = corpus.kernel.lines(LogMode::Synthesize, 20, &mut rng);
+        assert!(made_up
+            .iter()
+            .any(|line| !corpus.kernel.all_logs().contains(line)));
+        assert_eq!(
+            corpus.build.lines(LogMode::Replay, &mut rng),
+            [
+                "CC      init/main.o",
+                "  LD      vmlinux",
+                "[ 45%] Building C object"
+            ]
+        );
+        assert_eq!(
+            corpus.deno.success_lines(LogMode::Replay, &mut rng),
+            DenoLogs::load().success_lines(LogMode::Replay, &mut rng)
+        );
+    }
+}
@@ -1,7 +1,11 @@
+use crate::markov::{LogLines, LogMode};
+use rand::Rng;
+
 /// Manages Deno compilation logs (both success and error cases)
+#[derive(Clone, Debug)]
 pub struct DenoLogs {
-    success_logs: Vec<String>,
-    error_logs: Vec<String>,
+    success_logs: LogLines,
+    error_logs: LogLines,
 }
 
 impl DenoLogs {
@@ -10,31 +14,52 @@ impl DenoLogs {
         let success_content = include_str!("../data/deno.log");
         let error_content = include_str!("../data/error/deno.log");
 
-        let success_logs = success_content
+        let success_logs: Vec<String> = success_content
             .lines()
             .filter(|line| !line.trim().is_empty())
             .map(String::from)
             .collect();
 
-        let error_logs = error_content
+        let error_logs: Vec<String> = error_content
             .lines()
             .filter(|line| !line.trim().is_empty())
             .map(String::from)
             .collect();
 
         Self {
-            success_logs,
-            error_logs,
+            success_logs: LogLines::new(success_logs),
+            error_logs: LogLines::new(error_logs),
+        }
+    }
+
+    /// Replaces the logs of a successful build
+    pub fn with_success_logs(mut self, logs: Vec<String>) -> Self {
+        self.success_logs = LogLines::new(logs);
+        self
+    }
+
+    /// Replaces the logs of a failed build
+    pub fn with_error_logs(mut self, logs: Vec<String>) -> Self {
+        self.error_logs = LogLines::new(logs);
+        self
+    }
+
+    /// Trains the models for `LogMode::Synthesize`, of both builds
+    pub fn trained(self) -> Self {
+        Self {
+            success_logs: self.success_logs.trained(),
+            error_logs: self.error_logs.trained(),
         }
     }
 
-    /// Get success logs
-    pub fn success_logs(&self) -> &[String] {
-        &self.success_logs
+    /// Output of a successful build, replayed, sampled or made up
+    pub fn success_lines(&self, mode: LogMode, rng: &mut impl Rng) -> Vec<String> {
+        self.success_logs
+            .take(mode, self.success_logs.all().len(), rng)
     }
 
-    /// Get error logs
-    pub fn error_logs(&self) -> &[String] {
-        &self.error_logs
+    /// Output of a failed build, replayed, sampled or made up
+    pub fn error_lines(&self, mode: LogMode, rng: &mut impl Rng) -> Vec<String> {
+        self.error_logs.take(mode, self.error_logs.all().len(), rng)
     }
 }
@@ -0,0 +1,206 @@
+use crossterm::event::{self, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal;
+use std::io::{self, IsTerminal};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::Duration;
+
+/// Hotkeys and what they do, for the help overlay; the boss key comes last
+const HOTKEYS: &[(&str, &str)] = &[
+    ("space", "pause / resume"),
+    ("n", "skip to the next stage"),
+    ("+ / -", "double / halve the speed"),
+    ("q", "quit"),
+    ("?", "show this help"),
+];
+
+/// Key that cuts to a serious-looking stage unless `--boss-key` says otherwise
+pub const DEFAULT_BOSS_KEY: char = 'b';
+
+/// Keys the boss key cannot be moved to, because they already do something
+pub const RESERVED_KEYS: &[char] = &[' ', 'n', '+', '=', '-', 'q', '?'];
+
+/// A key press the installer reacts to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Quit,
+    Pause,
+    Skip,
+    Faster,
+    Slower,
+    Help,
+    /// Someone walked by
+    Boss,
+    /// Any other character, for prompts
+    Char(char),
+}
+
+impl Key {
+    fn from_event(key: KeyEvent, boss: char) -> Option<Self> {
+        if key.kind == KeyEventKind::Release {
+            return None;
+        }
+        match key.code {
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => Some(Key::Quit),
+            KeyCode::Esc => Some(Key::Quit),
+            KeyCode::Char(c) => Some(Key::from_char(c, boss)),
+            _ => None,
+        }
+    }
+
+    /// The key behind a character, control characters included
+    fn from_char(c: char, boss: char) -> Self {
+        match c {
+            c if c == boss => Key::Boss,
+            // Ctrl+C and Ctrl+D
+            'q' | '\x03' | '\x04' => Key::Quit,
+            ' ' => Key::Pause,
+            'n' => Key::Skip,
+            '+' | '=' => Key::Faster,
+            '-' => Key::Slower,
+            '?' => Key::Help,
+            c => Key::Char(c),
+        }
+    }
+}
+
+/// Where keys come from
+enum Source {
+    /// Nowhere, no key ever arrives
+    None,
+    /// The terminal, in raw mode
+    Terminal,
+    /// Characters typed by a remote user, such as a telnet client
+    Remote(Receiver<char>),
+}
+
+/// Keyboard input from the terminal the installer runs in
+///
+/// Puts the terminal in raw mode so keys arrive one at a time and Ctrl+C is
+/// read as a key instead of killing the process. Without a terminal on stdin
+/// it never yields a key, unless the keys come from elsewhere with `remote`.
+pub struct Input {
+    source: Source,
+    boss: char,
+}
+
+impl Input {
+    pub fn new() -> io::Result<Self> {
+        let source = if io::stdin().is_terminal() {
+            terminal::enable_raw_mode()?;
+            Source::Terminal
+        } else {
+            Source::None
+        };
+        Ok(Self {
+            source,
+            boss: DEFAULT_BOSS_KEY,
+        })
+    }
+
+    /// Input typed somewhere else and sent over `chars`, which quits once
+    /// the sending end is gone
+    pub fn remote(chars: Receiver<char>) -> Self {
+        Self {
+            source: Source::Remote(chars),
+            boss: DEFAULT_BOSS_KEY,
+        }
+    }
+
+    /// Uses `key` as the boss key instead of the default
+    pub fn with_boss_key(mut self, key: char) -> Self {
+        self.boss = key;
+        self
+    }
+
+    /// Hotkeys and what they do, for the help overlay
+    pub fn hotkeys(&self) -> Vec<(String, &'static str)> {
+        HOTKEYS
+            .iter()
+            .map(|&(key, action)| (key.to_string(), action))
+            .chain([(self.boss.to_string(), "boss key, look busy")])
+            .collect()
+    }
+
+    /// Input that never yields a key
+    pub fn none() -> Self {
+        Self {
+            source: Source::None,
+            boss: DEFAULT_BOSS_KEY,
+        }
+    }
+
+    /// Whether keys can arrive at all
+    pub fn is_interactive(&self) -> bool {
+        !matches!(self.source, Source::None)
+    }
+
+    /// Waits up to `timeout` for a key press
+    pub fn next(&self, timeout: Duration) -> Option<Key> {
+        match &self.source {
+            Source::None => {
+                thread::sleep(timeout);
+                None
+            }
+            Source::Terminal => {
+                if !event::poll(timeout).unwrap_or(false) {
+                    return None;
+                }
+                match event::read() {
+                    Ok(event::Event::Key(key)) => Key::from_event(key, self.boss),
+                    _ => None,
+                }
+            }
+            Source::Remote(chars) => match chars.recv_timeout(timeout) {
+                Ok(c) => Some(Key::from_char(c, self.boss)),
+                Err(RecvTimeoutError::Timeout) => None,
+                // Nobody is left to watch
+                Err(RecvTimeoutError::Disconnected) => Some(Key::Quit),
+            },
+        }
+    }
+}
+
+impl Drop for Input {
+    fn drop(&mut self) {
+        if let Source::Terminal = self.source {
+            let _ = terminal::disable_raw_mode();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(code: KeyCode) -> KeyEvent {
+        KeyEvent::new(code, KeyModifiers::NONE)
+    }
+
+    #[test]
+    fn test_boss_key_is_configurable() {
+        assert_eq!(
+            Key::from_event(press(KeyCode::Char('b')), 'b'),
+            Some(Key::Boss)
+        );
+        assert_eq!(
+            Key::from_event(press(KeyCode::Char('b')), 'x'),
+            Some(Key::Char('b'))
+        );
+        assert_eq!(
+            Key::from_event(press(KeyCode::Char('x')), 'x'),
+            Some(Key::Boss)
+        );
+        assert_eq!(Key::from_char('\x03', 'b'), Key::Quit);
+    }
+
+    #[test]
+    fn test_help_shows_the_boss_key() {
+        let hotkeys = Input::none().with_boss_key('x').hotkeys();
+        assert_eq!(
+            hotkeys.last(),
+            Some(&("x".to_string(), "boss key, look busy"))
+        );
+        assert!(hotkeys.iter().all(|(key, _)| key != "b"));
+    }
+}
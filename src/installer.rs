@@ -1,176 +1,499 @@
-use crate::cli::Stage;
+use crate::cli::{Order, Stage, StageId};
+use crate::clock::{format_hms, Clock};
+use crate::config::SimulationConfig;
+use crate::context::{Context, Stop};
+use crate::input::Input;
 use crate::messages::{EASTER_EGGS, RETRY_MESSAGES, WARNINGS};
-use crate::stages::selected_stages;
-use crate::ui::Spinner;
-use colored::*;
-use crossterm::{
-    cursor,
-    event::{self, Event, KeyCode},
-    execute,
-    terminal::{self, ClearType},
-};
+use crate::order::arrange;
+use crate::pacing::{Finish, Pacer};
+use crate::render::{AnsiRenderer, Event, Line, Renderer, Theme, Tone};
+use crate::stages::{selected_stages, InstallationStage};
+use crate::stats::Stats;
+use crate::ui::layout::{centered, fit_width};
+use crate::ui::{Spinner, SpinnerStyle};
 use rand::Rng;
+use std::collections::BTreeMap;
 use std::io;
-use std::thread;
-use std::time::Duration;
+use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 
+/// Runs the selected stages cycle after cycle, with the header, warnings and
+/// hotkeys around them
 pub struct Installer {
-    rng: rand::rngs::ThreadRng,
-    selected_stages: Vec<Stage>,
+    ctx: Context,
+    seed: u64,
+    selected_stages: Vec<StageId>,
+    config: SimulationConfig,
+    /// Stages added with `add_stage`, by name
+    custom: BTreeMap<String, Rc<dyn InstallationStage>>,
+    finish: Finish,
+    serious: bool,
 }
 
 impl Installer {
-    pub fn new(stages: Vec<Stage>) -> Self {
+    /// Creates an installer whose every random choice derives from `seed`,
+    /// including the order in which the selected stages are run
+    pub fn new(
+        stages: Vec<StageId>,
+        order: Order,
+        seed: u64,
+        clock: Clock,
+        config: SimulationConfig,
+        renderer: Box<dyn Renderer>,
+        input: Input,
+    ) -> Self {
+        let mut ctx = Context::new(seed, clock, renderer, input);
+        ctx.spinner = config.spinner;
+        let stages = arrange(stages, order, &mut ctx.rng);
+
         Self {
-            rng: rand::thread_rng(),
+            ctx,
+            seed,
             selected_stages: stages,
+            config,
+            custom: BTreeMap::new(),
+            finish: Finish::Never,
+            serious: false,
         }
     }
 
-    fn check_exit(&self) -> bool {
-        if event::poll(Duration::from_millis(0)).unwrap_or(false) {
-            if let Ok(Event::Key(key_event)) = event::read() {
-                if key_event.code == KeyCode::Char('c')
-                    && key_event.modifiers.contains(event::KeyModifiers::CONTROL)
-                {
-                    return true;
-                }
-            }
-        }
-        false
+    /// Adds a stage of the embedding program, run under `name` after the
+    /// stages given to `new`
+    pub fn add_stage(&mut self, name: impl Into<String>, stage: Box<dyn InstallationStage>) {
+        let name = name.into();
+        self.custom.insert(name.clone(), Rc::from(stage));
+        self.selected_stages.push(StageId::Custom(name));
     }
 
-    fn print_header(&self) {
-        println!(
-            "{}",
-            "=================================================================".bright_cyan()
-        );
-        println!(
-            "{}",
-            "         UNIVERSAL SYSTEM INSTALLER v3.2.1 (Build 1999)"
-                .bright_white()
-                .bold()
+    /// The stages behind `ids`, in the same order
+    fn stages(&self, ids: &[StageId]) -> io::Result<Vec<Rc<dyn InstallationStage>>> {
+        ids.iter()
+            .map(|id| match id {
+                StageId::Custom(name) => self.custom.get(name).map(Rc::clone).ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("unknown stage `{}`, it was not added with add_stage", name),
+                    )
+                }),
+                id => Ok(Rc::from(
+                    selected_stages(std::slice::from_ref(id), &self.config)?.remove(0),
+                )),
+            })
+            .collect()
+    }
+
+    /// Sets when the run ends, by default it loops forever
+    pub fn finish(&mut self, finish: Finish) {
+        self.finish = finish;
+    }
+
+    /// Ends the run as soon as `ended` is set, by another thread, cutting
+    /// the current stage short
+    ///
+    /// The run then returns `Ok`, as if it had reached its end.
+    pub fn end_when(&mut self, ended: Arc<AtomicBool>) {
+        self.ctx.end_when(ended);
+    }
+
+    /// Leaves out the easter eggs when set
+    pub fn serious(&mut self, serious: bool) {
+        self.serious = serious;
+    }
+
+    /// Ends the session, returning what happened during it
+    pub fn into_stats(self) -> Stats {
+        let mut stats = self.ctx.stats;
+        stats.elapsed = self.ctx.clock.elapsed();
+        stats
+    }
+
+    fn print_header(&mut self) {
+        let width = fit_width(65, self.ctx.columns());
+        let rule = "=".repeat(width);
+        self.ctx.print(Tone::Highlight, &rule);
+        self.ctx.print_line(
+            Line::styled(
+                Tone::Bright,
+                centered("UNIVERSAL SYSTEM INSTALLER v3.2.1 (Build 1999)", width),
+            )
+            .bold(),
         );
-        println!(
-            "{}",
-            "=================================================================".bright_cyan()
+        self.ctx.print(Tone::Highlight, &rule);
+        self.ctx.print(
+            Tone::Dim,
+            format!(
+                "Session seed: {} (replay with --seed {})",
+                self.seed, self.seed
+            ),
         );
-        println!();
-        thread::sleep(Duration::from_millis(1500));
+        self.ctx.blank();
+        self.ctx.sleep(1500);
     }
 
     fn show_easter_egg(&mut self) -> io::Result<()> {
-        if self.rng.gen_bool(0.15) {
-            println!();
-            let egg = EASTER_EGGS[self.rng.gen_range(0..EASTER_EGGS.len())];
-            let mut spinner = Spinner::new();
-            spinner.animate(egg, 1500, &|| self.check_exit())?;
-            println!();
+        if !self.serious && self.ctx.rng.gen_bool(0.15) {
+            self.ctx.blank();
+            let egg = EASTER_EGGS[self.ctx.rng.gen_range(0..EASTER_EGGS.len())];
+            let mut spinner = Spinner::with_style(SpinnerStyle::Moon);
+            spinner.animate(egg, 1500, &mut self.ctx)?;
+            self.ctx.blank();
         }
         Ok(())
     }
 
     fn show_warning(&mut self) {
-        if self.rng.gen_bool(0.2) {
-            let warning = WARNINGS[self.rng.gen_range(0..WARNINGS.len())];
-            println!("\n{}", warning.yellow());
-            thread::sleep(Duration::from_millis(1000));
-            println!("{}", "Continuing anyway...".dimmed());
-            println!();
+        if self.ctx.rng.gen_bool(0.2) {
+            let warning = WARNINGS[self.ctx.rng.gen_range(0..WARNINGS.len())];
+            self.ctx.blank();
+            self.ctx.print(Tone::Warning, warning);
+            self.ctx.stats.warnings += 1;
+            self.ctx.sleep(1000);
+            self.ctx.print(Tone::Dim, "Continuing anyway...");
+            self.ctx.blank();
         }
     }
 
     fn show_retry(&mut self) -> io::Result<()> {
-        if self.rng.gen_bool(0.1) {
-            let message = RETRY_MESSAGES[self.rng.gen_range(0..RETRY_MESSAGES.len())];
-            println!("\n{}", message.yellow());
-            thread::sleep(Duration::from_millis(800));
-
-            let mut spinner = Spinner::new();
-            spinner.animate("Reconnecting to mirror.oldsoft.org", 1200, &|| {
-                self.check_exit()
-            })?;
-            println!();
+        if self.ctx.rng.gen_bool(0.1) {
+            let message = RETRY_MESSAGES[self.ctx.rng.gen_range(0..RETRY_MESSAGES.len())];
+            self.ctx.blank();
+            self.ctx.retry(Line::styled(Tone::Warning, message));
+            self.ctx.sleep(800);
+
+            let mut spinner = Spinner::with_style(SpinnerStyle::Clock);
+            spinner.animate("Reconnecting to mirror.oldsoft.org", 1200, &mut self.ctx)?;
+            self.ctx.blank();
         }
         Ok(())
     }
 
+    /// Shows the extras that come before a stage, then runs it
+    fn run_stage(&mut self, stage: &dyn InstallationStage) -> io::Result<()> {
+        self.show_easter_egg()?;
+        self.show_warning();
+        self.show_retry()?;
+
+        self.ctx.emit(&Event::StageStart {
+            name: stage.name(),
+            tone: stage.tone(),
+        });
+        stage.run(&mut self.ctx)
+    }
+
+    /// Deals with the hotkeys that cut a stage short, given how it ended,
+    /// returning whether the stage ran to its end
+    fn settle(&mut self, mut result: io::Result<()>) -> io::Result<bool> {
+        let mut completed = true;
+        loop {
+            match result {
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => match self.ctx.take_stop() {
+                    Some(Stop::Skip) => {
+                        self.ctx.emit(&Event::ProgressEnd(None));
+                        self.ctx.print(Tone::Dim, "Skipped by user.");
+                        return Ok(false);
+                    }
+                    Some(Stop::Boss) => {
+                        self.ctx.emit(&Event::ProgressEnd(None));
+                        completed = false;
+                        result = self.run_cover_stage();
+                    }
+                    Some(Stop::End) => {
+                        self.ctx.emit(&Event::ProgressEnd(None));
+                        return Err(e);
+                    }
+                    _ => return Err(e),
+                },
+                result => return result.map(|()| completed),
+            }
+        }
+    }
+
+    /// Cuts straight to a long build that looks like real work, for when
+    /// someone walks by
+    fn run_cover_stage(&mut self) -> io::Result<()> {
+        let cover = StageId::Builtin(if self.ctx.rng.gen_bool(0.5) {
+            Stage::Kernel
+        } else {
+            Stage::Compilation
+        });
+        let stage = selected_stages(std::slice::from_ref(&cover), &self.config)?.remove(0);
+        self.ctx.stats.stage_run(cover);
+
+        self.ctx.emit(&Event::StageStart {
+            name: stage.name(),
+            tone: stage.tone(),
+        });
+        let result = stage.run(&mut self.ctx);
+        self.ctx.emit(&Event::StageEnd { name: stage.name() });
+        result
+    }
+
+    /// Runs until the end set with `finish` or `end_when`, or until the user
+    /// quits
     pub fn run(&mut self) -> io::Result<()> {
-        terminal::enable_raw_mode()?;
-        execute!(
-            io::stdout(),
-            terminal::Clear(ClearType::All),
-            cursor::MoveTo(0, 0)
-        )?;
-        terminal::disable_raw_mode()?;
+        let result = match self.run_cycles() {
+            // Ended from outside, which is how such a run is meant to end
+            Err(e) if e.kind() == io::ErrorKind::Interrupted && self.ctx.has_ended() => Ok(()),
+            result => result,
+        };
+
+        // A renderer that failed to write wins over the interrupt it caused
+        match self.ctx.take_error() {
+            Some(e) => Err(e),
+            None => result,
+        }
+    }
+
+    fn run_cycles(&mut self) -> io::Result<()> {
+        self.ctx.emit(&Event::Clear);
 
         self.print_header();
 
-        println!(
-            "{}",
-            "Initializing installation environment...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(1000));
+        self.ctx
+            .print(Tone::Bright, "Initializing installation environment...");
+        self.ctx.sleep(1000);
 
         let mut spinner = Spinner::new();
-        spinner.animate("Detecting hardware configuration...", 1500, &|| {
-            self.check_exit()
-        })?;
-        println!();
+        spinner.animate("Detecting hardware configuration...", 1500, &mut self.ctx)?;
+        self.ctx.blank();
 
         let mut cycle = 0;
+        let mut pacer = Pacer::default();
+        let order = self.selected_stages.clone();
         loop {
             cycle += 1;
 
+            let last_cycle = match self.finish {
+                Finish::Never => false,
+                Finish::Cycles(cycles) => cycle >= cycles,
+                Finish::Deadline(deadline) => pacer.is_last_cycle(
+                    &order,
+                    self.ctx.clock.speed(),
+                    deadline.saturating_sub(self.ctx.clock.elapsed()),
+                ),
+            };
+
             if cycle > 1 {
-                println!(
-                    "\n{}",
-                    "═══════════════════════════════════════════════════════════════"
-                        .bright_magenta()
+                let rule = "═".repeat(fit_width(63, self.ctx.columns()));
+                self.ctx.blank();
+                self.ctx.print(Tone::Special, &rule);
+                self.ctx.print_line(
+                    Line::styled(
+                        Tone::Special,
+                        format!("Beginning installation cycle #{}...", cycle),
+                    )
+                    .bold(),
                 );
-                println!(
-                    "{}",
-                    format!("Beginning installation cycle #{}...", cycle)
-                        .bright_magenta()
-                        .bold()
-                );
-                println!(
-                    "{}",
-                    "═══════════════════════════════════════════════════════════════"
-                        .bright_magenta()
-                );
-                thread::sleep(Duration::from_millis(1000));
+                self.ctx.print(Tone::Special, &rule);
+                self.ctx.sleep(1000);
             }
 
-            let stages = selected_stages(&self.selected_stages);
+            let stages = self.stages(&order)?;
+            let names: Vec<&str> = stages.iter().map(|stage| stage.name()).collect();
+            self.ctx.emit(&Event::CycleStart {
+                number: cycle,
+                stages: &names,
+            });
 
-            for stage in stages {
-                if self.check_exit() {
-                    return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
+            for (i, stage) in stages.iter().enumerate() {
+                // A skip pressed between two stages applies to the next one
+                if self.ctx.check_exit() {
+                    match self.ctx.take_stop() {
+                        Some(Stop::Skip) => {}
+                        Some(Stop::Boss) => {
+                            let result = self.run_cover_stage();
+                            self.settle(result)?;
+                        }
+                        _ => {
+                            return Err(io::Error::new(
+                                io::ErrorKind::Interrupted,
+                                "User interrupt",
+                            ))
+                        }
+                    }
                 }
 
-                self.show_easter_egg()?;
-                self.show_warning();
-                self.show_retry()?;
+                // Spread the time left over the rest of the last cycle
+                if let (true, Finish::Deadline(deadline)) = (last_cycle, self.finish) {
+                    let remaining = deadline.saturating_sub(self.ctx.clock.elapsed());
+                    let speed = pacer.speed_for(&order[i..], remaining);
+                    self.ctx.clock.set_speed(speed);
+                }
+                let started = self.ctx.clock.simulated_ms();
 
-                stage.run(&|| self.check_exit())?;
+                self.ctx.stats.stage_run(order[i].clone());
+                let result = self.run_stage(stage.as_ref());
+                let completed = self.settle(result)?;
+                self.ctx.emit(&Event::StageEnd { name: stage.name() });
 
-                thread::sleep(Duration::from_millis(self.rng.gen_range(300..800)));
+                self.ctx.sleep_range(300..800);
+                // A stage cut short says nothing about how long it takes
+                if completed {
+                    pacer.record(order[i].clone(), self.ctx.clock.simulated_ms() - started);
+                }
             }
 
-            println!(
-                "\n{}",
-                "Installation complete! Restarting installation process..."
-                    .bright_green()
-                    .bold()
+            self.ctx.stats.cycles += 1;
+            self.ctx.emit(&Event::CycleEnd { number: cycle });
+            self.ctx.blank();
+            if last_cycle {
+                return self.show_completion(cycle);
+            }
+            self.ctx.print_line(
+                Line::styled(
+                    Tone::Success,
+                    "Installation complete! Restarting installation process...",
+                )
+                .bold(),
             );
-            thread::sleep(Duration::from_millis(2000));
+            self.ctx.sleep(2000);
         }
     }
+
+    /// Shows the final screen of a finite run, kept up until its deadline
+    fn show_completion(&mut self, cycles: u32) -> io::Result<()> {
+        let rule = "═".repeat(fit_width(63, self.ctx.columns()));
+        let installed = cycles as usize * self.selected_stages.len();
+        self.ctx.print(Tone::Success, &rule);
+        self.ctx
+            .print_line(Line::styled(Tone::Success, "  Installation complete!").bold());
+        self.ctx.print(
+            Tone::Dim,
+            format!(
+                "  {} stage(s) installed over {} cycle(s) in {}",
+                installed,
+                cycles,
+                format_hms(self.ctx.clock.elapsed())
+            ),
+        );
+        self.ctx.print(Tone::Success, &rule);
+
+        if let Finish::Deadline(deadline) = self.finish {
+            self.ctx.clock.set_speed(1.0);
+            while self.ctx.clock.elapsed() < deadline {
+                if self.ctx.check_exit() {
+                    return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
+                }
+                let remaining = deadline - self.ctx.clock.elapsed();
+                self.ctx.sleep(remaining.as_millis().clamp(1, 100) as u64);
+            }
+        }
+        Ok(())
+    }
 }
 
 impl Default for Installer {
     fn default() -> Self {
-        Self::new(Stage::all())
+        Self::new(
+            StageId::all(&[]),
+            Order::default(),
+            rand::random(),
+            Clock::default(),
+            SimulationConfig::default(),
+            Box::new(AnsiRenderer::new(io::stdout(), Theme::default())),
+            Input::none(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    /// Counts how often it runs
+    struct Probe(Rc<Cell<u32>>);
+
+    impl InstallationStage for Probe {
+        fn name(&self) -> &str {
+            "Probe"
+        }
+
+        fn run(&self, _ctx: &mut Context) -> io::Result<()> {
+            self.0.set(self.0.get() + 1);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_added_stage_runs_every_cycle() {
+        let runs = Rc::new(Cell::new(0));
+        let mut installer = Installer::new(
+            vec![Stage::Boot.into()],
+            Order::Fixed,
+            1,
+            Clock::instant(),
+            SimulationConfig::default(),
+            Box::new(AnsiRenderer::new(io::sink(), Theme::default())),
+            Input::none(),
+        );
+        installer.serious(true);
+        installer.add_stage("probe", Box::new(Probe(Rc::clone(&runs))));
+        installer.finish(Finish::Cycles(2));
+        installer.run().unwrap();
+
+        assert_eq!(runs.get(), 2);
+        let stats = installer.into_stats();
+        assert_eq!(stats.stages[&StageId::Custom("probe".to_string())], 2);
+    }
+
+    /// Ends the run from its first stage
+    struct Ender(Arc<AtomicBool>);
+
+    impl InstallationStage for Ender {
+        fn name(&self) -> &str {
+            "Ender"
+        }
+
+        fn run(&self, _ctx: &mut Context) -> io::Result<()> {
+            self.0.store(true, std::sync::atomic::Ordering::Relaxed);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_end_when_stops_an_endless_run() {
+        let ended = Arc::new(AtomicBool::new(false));
+        let mut installer = Installer::new(
+            vec![],
+            Order::Fixed,
+            1,
+            Clock::instant(),
+            SimulationConfig::default(),
+            Box::new(AnsiRenderer::new(io::sink(), Theme::default())),
+            Input::none(),
+        );
+        installer.serious(true);
+        installer.add_stage("ender", Box::new(Ender(Arc::clone(&ended))));
+        installer.add_stage("probe", Box::new(Probe(Rc::new(Cell::new(0)))));
+        installer.end_when(ended);
+        installer.run().unwrap();
+
+        let stats = installer.into_stats();
+        assert_eq!(stats.stages[&StageId::Custom("ender".to_string())], 1);
+        assert!(!stats
+            .stages
+            .contains_key(&StageId::Custom("probe".to_string())));
+    }
+
+    #[test]
+    fn test_unknown_stage_is_an_error() {
+        for stage in [
+            StageId::Custom("missing".to_string()),
+            StageId::Script("missing".to_string()),
+        ] {
+            let mut installer = Installer::new(
+                vec![stage],
+                Order::Fixed,
+                1,
+                Clock::instant(),
+                SimulationConfig::default(),
+                Box::new(AnsiRenderer::new(io::sink(), Theme::default())),
+                Input::none(),
+            );
+            let err = installer.run().unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        }
     }
 }
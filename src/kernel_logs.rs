@@ -1,8 +1,10 @@
-use rand::seq::SliceRandom;
+use crate::markov::{LogLines, LogMode};
+use rand::Rng;
 
 /// Manages kernel log messages for authentic system output
+#[derive(Clone, Debug)]
 pub struct KernelLogs {
-    logs: Vec<String>,
+    logs: LogLines,
 }
 
 impl KernelLogs {
@@ -12,41 +14,46 @@ impl KernelLogs {
         let logs = content
             .lines()
             .filter(|line| !line.trim().is_empty())
-            .map(|line| Self::strip_timestamp(line))
+            .map(Self::strip_timestamp)
             .collect();
 
-        Self { logs }
+        Self::from_lines(logs)
     }
 
-    /// Strip kernel timestamp from log line
-    /// Converts "[    0.000000] message" to "message"
-    fn strip_timestamp(line: &str) -> String {
-        if let Some(start) = line.find('[') {
-            if let Some(end) = line[start..].find(']') {
-                let timestamp_end = start + end + 1;
-                return line[timestamp_end..].trim_start().to_string();
-            }
+    /// Uses `logs` as the kernel messages, already stripped of timestamps
+    pub fn from_lines(logs: Vec<String>) -> Self {
+        Self {
+            logs: LogLines::new(logs),
         }
-        line.to_string()
     }
 
-    /// Get all kernel logs
-    pub fn all_logs(&self) -> &[String] {
-        &self.logs
+    /// Trains the model for `LogMode::Synthesize`
+    pub fn trained(self) -> Self {
+        Self {
+            logs: self.logs.trained(),
+        }
     }
 
-    /// Get multiple random kernel log messages
-    pub fn random_batch(&self, count: usize) -> Vec<&str> {
-        let mut rng = rand::thread_rng();
-        let mut batch = Vec::new();
+    /// Strip kernel timestamp from log line
+    /// Converts "[    0.000000] message" to "message", other lines are kept
+    pub fn strip_timestamp(line: &str) -> String {
+        let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        let message = line.strip_prefix('[').and_then(|rest| {
+            let (stamp, message) = rest.split_once(']')?;
+            let (seconds, fraction) = stamp.trim_start().split_once('.')?;
+            (digits(seconds) && digits(fraction)).then(|| message.trim_start())
+        });
+        message.unwrap_or(line).to_string()
+    }
 
-        for _ in 0..count {
-            if let Some(log) = self.logs.choose(&mut rng) {
-                batch.push(log.as_str());
-            }
-        }
+    /// Get all kernel logs
+    pub fn all_logs(&self) -> &[String] {
+        self.logs.all()
+    }
 
-        batch
+    /// Get `count` kernel log messages, replayed, sampled or made up
+    pub fn lines(&self, mode: LogMode, count: usize, rng: &mut impl Rng) -> Vec<String> {
+        self.logs.take(mode, count, rng)
     }
 }
 
@@ -55,3 +62,28 @@ impl Default for KernelLogs {
         Self::load()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_timestamp() {
+        assert_eq!(
+            KernelLogs::strip_timestamp("[    0.000000] Linux version 6.1.0"),
+            "Linux version 6.1.0"
+        );
+        assert_eq!(
+            KernelLogs::strip_timestamp("[12345.678901]   usb 1-1: new device"),
+            "usb 1-1: new device"
+        );
+        // Brackets that are not a timestamp belong to the message
+        for line in [
+            "ACPI: [Firmware Bug]: BIOS _OSI(Linux) query ignored",
+            "[ 45%] Building CXX object src/CMakeFiles/core.dir/main.cpp.o",
+            "Linux version 6.1.0 (gcc 12.2.0)",
+        ] {
+            assert_eq!(KernelLogs::strip_timestamp(line), line);
+        }
+    }
+}
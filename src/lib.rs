@@ -0,0 +1,107 @@
+//! An installer that installs nothing.
+//!
+//! The `install-nothing` binary is a thin client of this library. Other
+//! programs can run the same stages, add their own and reuse the widgets
+//! that draw them.
+//!
+//! A stage implements [`InstallationStage`] and writes its output through
+//! the [`Context`] it is given, as events that a [`render::Renderer`] turns
+//! into terminal output:
+//!
+//! ```no_run
+//! use install_nothing::render::{AnsiRenderer, Theme, Tone};
+//! use install_nothing::ui::{ProgressBar, ProgressStyle};
+//! use install_nothing::{
+//!     Clock, Context, Finish, Input, InstallationStage, Installer, Order, SimulationConfig, Stage,
+//! };
+//! use std::io;
+//!
+//! struct Coffee;
+//!
+//! impl InstallationStage for Coffee {
+//!     fn name(&self) -> &str {
+//!         "Coffee Machine Firmware"
+//!     }
+//!
+//!     fn run(&self, ctx: &mut Context) -> io::Result<()> {
+//!         ctx.log(Tone::Plain, "Descaling boiler...");
+//!         ProgressBar::new(ProgressStyle::Hash).animate("Brewing", 2000, ctx)
+//!     }
+//! }
+//!
+//! let mut installer = Installer::new(
+//!     vec![Stage::Bios.into(), Stage::Kernel.into()],
+//!     Order::Fixed,
+//!     42,
+//!     Clock::new(1.0),
+//!     SimulationConfig::default(),
+//!     Box::new(AnsiRenderer::new(io::stdout(), Theme::default())),
+//!     Input::none(),
+//! );
+//! installer.add_stage("coffee", Box::new(Coffee));
+//! installer.finish(Finish::Cycles(1));
+//! installer.run()?;
+//! # Ok::<(), io::Error>(())
+//! ```
+//!
+//! A single stage can also run on its own, in a [`Context`] made with
+//! `Context::new`.
+
+/// The build log replayed by the compilation stage
+pub mod build_logs;
+/// Command line options, and the stage names they take
+pub mod cli;
+/// Simulated time, which can run faster or slower than the real one
+pub mod clock;
+/// Settings of every stage, read from a TOML or JSON file
+pub mod config;
+/// The session a stage runs in
+pub mod context;
+/// The logs stages replay, built in or read from files
+pub mod corpus;
+/// The cargo output replayed by the Deno stage
+pub mod deno_logs;
+/// Hotkeys read from the terminal
+pub mod input;
+/// Runs stages in cycles
+pub mod installer;
+/// The kernel log replayed by the boot and kernel stages
+pub mod kernel_logs;
+/// Made-up values for log lines
+pub mod log_generator;
+/// Ways of turning a log into output, including made-up lines
+pub mod markov;
+mod messages;
+mod order;
+/// When a run ends, and how fast it goes to end on time
+pub mod pacing;
+/// What the installer shows, as events, and the renderers that draw them
+pub mod render;
+/// Serves the installer to browsers on localhost
+pub mod serve;
+/// The built-in stages and stage scripts
+pub mod stages;
+/// What happened during a session
+pub mod stats;
+/// Runs an installer for every telnet client
+pub mod telnet;
+/// Progress bars, spinners and layout helpers
+pub mod ui;
+/// Runs a real command while the installer is shown
+pub mod wrap;
+
+pub use build_logs::BuildLogs;
+pub use cli::{Order, Stage, StageId};
+pub use clock::Clock;
+pub use config::SimulationConfig;
+pub use context::Context;
+pub use corpus::{LogCorpus, Synthesized};
+pub use deno_logs::DenoLogs;
+pub use input::Input;
+pub use installer::Installer;
+pub use kernel_logs::KernelLogs;
+pub use log_generator::LogGenerator;
+pub use pacing::Finish;
+pub use stages::InstallationStage;
+pub use stats::Stats;
+pub use ui::{ProgressBar, Spinner};
@@ -1,27 +1,26 @@
 use rand::Rng;
-use std::sync::{LazyLock, Mutex};
 
-#[allow(dead_code)]
+/// Made-up values for log lines: uptimes, addresses and versions
 pub struct LogGenerator;
 
-static LAST_TIMESTAMP: LazyLock<Mutex<f64>> = LazyLock::new(|| Mutex::new(0.0));
-
 impl LogGenerator {
-    pub fn timestamp() -> String {
-        let mut rng = rand::thread_rng();
-        let mut last = LAST_TIMESTAMP.lock().unwrap();
-        *last += rng.gen_range(0.01..0.5);
-        format!("[{:12.6}]", *last)
+    /// The simulated uptime in seconds a little after `last`
+    pub fn timestamp(last: f64, rng: &mut impl Rng) -> f64 {
+        last + rng.gen_range(0.01..0.5)
+    }
+
+    /// Formats seconds of uptime the way the kernel log does
+    pub fn format_timestamp(seconds: f64) -> String {
+        format!("[{:12.6}]", seconds)
     }
 
-    pub fn hex_addr() -> String {
-        let mut rng = rand::thread_rng();
+    /// A random 64-bit address, `0x` and 16 hex digits
+    pub fn hex_addr(rng: &mut impl Rng) -> String {
         format!("0x{:016x}", rng.gen::<u64>())
     }
 
-    #[allow(dead_code)]
-    pub fn version() -> String {
-        let mut rng = rand::thread_rng();
+    /// A random version such as `v3.12.7`
+    pub fn version(rng: &mut impl Rng) -> String {
         format!(
             "v{}.{}.{}",
             rng.gen_range(1..10),
@@ -30,7 +29,7 @@ impl LogGenerator {
         )
     }
 
-    #[allow(dead_code)]
+    /// `42% (21/50)`
     pub fn progress(current: usize, total: usize) -> String {
         let percentage = (current as f64 / total as f64 * 100.0) as usize;
         format!("{}% ({}/{})", percentage, current, total)
@@ -1,55 +1,275 @@
-mod build_logs;
-mod cli;
-mod config;
-mod deno_logs;
-mod installer;
-mod kernel_logs;
-mod log_generator;
-mod messages;
-mod stages;
-mod ui;
-
+use chrono::Local;
 use clap::Parser;
-use cli::Cli;
-use colored::*;
-use installer::Installer;
-use rand::seq::SliceRandom;
-use std::io;
+use crossterm::terminal;
+use install_nothing::cli::{time_until, Cli, ColorChoice, Command, Format};
+use install_nothing::render::{
+    AnsiRenderer, CastWriter, HtmlRenderer, JsonRenderer, PlainRenderer, Renderer, Tee, Theme,
+    Tone, TuiRenderer,
+};
+use install_nothing::serve::{self, Hub};
+use install_nothing::stages::load_scripts;
+use install_nothing::telnet::{self, Session};
+use install_nothing::wrap::{self, Wrapped};
+use install_nothing::{Clock, Finish, Input, Installer, LogCorpus, SimulationConfig};
+use std::fs::File;
+use std::io::{self, BufWriter, IsTerminal, Write};
+use std::process::ExitStatus;
 
 fn main() {
     if let Err(e) = run_installer() {
-        handle_error(e);
+        handle_error(e, &Theme::from_env());
     }
 }
 
 fn run_installer() -> io::Result<()> {
     let cli = Cli::parse();
-    let mut stages = cli.get_stages();
+    match cli.color {
+        ColorChoice::Always => colored::control::set_override(true),
+        ColorChoice::Never => colored::control::set_override(false),
+        ColorChoice::Auto => {}
+    }
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    let mut config = SimulationConfig::load(cli.config.as_deref())?;
+    for (name, path) in &cli.corpus {
+        config.corpus.set(*name, path.clone());
+    }
+    if let Some(mode) = cli.log_mode {
+        config.set_log_mode(mode);
+    }
+    config.logs = LogCorpus::load(&config.corpus, config.synthesized())?;
+    if let Some(style) = cli.spinner {
+        config.spinner = Some(style);
+    }
+    config.scripts = load_scripts(cli.stages_dir.as_deref())?;
+    let scripts: Vec<String> = config
+        .scripts
+        .iter()
+        .map(|script| script.id.clone())
+        .collect();
+    let stages = cli
+        .get_stages(&scripts)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    let theme = Theme::select(
+        cli.theme.as_deref(),
+        &config.themes,
+        cli.color == ColorChoice::Auto,
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+    let mut clock = Clock::new(cli.speed);
+    if cli.instant {
+        clock = clock.without_waiting();
+    }
+
+    if cli.command.is_some() && (cli.tui || cli.record.is_some() || cli.plain) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--tui, --record and --plain draw in this terminal, they cannot be used with serve or telnet",
+        ));
+    }
+    if cli.command.is_some() && cli.wrapped().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "a wrapped command runs alongside this terminal, it cannot be used with serve or telnet",
+        ));
+    }
+    let json = cli.format == Format::Jsonl;
+    if json && (cli.tui || cli.record.is_some() || cli.plain || cli.command.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "--format jsonl cannot be used with --tui, --record, --plain, serve or telnet",
+        ));
+    }
+    let wrapped = match cli.wrapped() {
+        Some(command) => Some(Wrapped::spawn(command, &cli.wrap_log)?),
+        None => None,
+    };
+    // A file or pipe gets lines it can keep, unless it is a recording
+    let plain = cli.plain || (!io::stdout().is_terminal() && !cli.tui && cli.record.is_none());
+    let renderer: Box<dyn Renderer> = match cli.command {
+        Some(Command::Telnet { listen }) => {
+            color_elsewhere(&cli);
+            println!("Listening for telnet clients on {}", listen);
+            return telnet::listen(
+                listen,
+                Session {
+                    stages,
+                    order: cli.order,
+                    seed: cli.seed,
+                    speed: cli.speed,
+                    config,
+                    theme,
+                    boss_key: cli.boss_key,
+                    serious: cli.serious,
+                    finish: finish(&cli),
+                },
+            );
+        }
+        Some(Command::Serve { port }) => {
+            color_elsewhere(&cli);
+            let hub = Hub::new();
+            serve::listen(port, hub.clone())?;
+            println!("Serving the installer on http://localhost:{}", port);
+            Box::new(HtmlRenderer::new(hub, theme.clone()))
+        }
+        None if json => Box::new(JsonRenderer::new(io::stdout(), clock.clone())),
+        None if plain => Box::new(PlainRenderer::new(io::stdout(), theme.clone())),
+        None => {
+            let out = terminal_output(&cli, &clock)?;
+            if cli.tui {
+                Box::new(TuiRenderer::new(out, clock.clone(), theme.clone())?)
+            } else {
+                Box::new(AnsiRenderer::new(out, theme.clone()))
+            }
+        }
+    };
+
+    let mut installer = Installer::new(
+        stages,
+        cli.order,
+        seed,
+        clock,
+        config,
+        renderer,
+        // No hotkeys, raw mode would leave the lines without carriage returns on a terminal
+        if json || (plain && io::stdout().is_terminal()) {
+            Input::none()
+        } else {
+            Input::new()?.with_boss_key(cli.boss_key)
+        },
+    );
+    installer.serious(cli.serious);
+    installer.finish(finish(&cli));
+    if let Some(wrapped) = &wrapped {
+        installer.end_when(wrapped.ended());
+    }
+    let result = installer.run();
+    let quit = result.is_err();
+    // The run only ends before the command when the user quits, or on an
+    // error, and the command goes with it
+    let status = match wrapped {
+        Some(wrapped) if quit => Some(wrapped.kill()?),
+        Some(wrapped) => Some(wrapped.wait()?),
+        None => None,
+    };
+
+    // Dropping the installer gives the terminal back before the summary
+    let stats = installer.into_stats();
+    if let Some(path) = &cli.report {
+        stats.write_report(path)?;
+    }
+    if let Err(e) = result {
+        handle_error(e, &theme);
+    }
+    // A program reading the events only expects events
+    if !json {
+        println!();
+        stats.write_summary(&mut io::stdout(), &theme)?;
+    }
+    if let Some(status) = status {
+        // Quitting already said the installation was cancelled
+        if !json && !quit {
+            print_outcome(&cli, status, &theme);
+        }
+        let code = wrap::exit_code(status);
+        if code != 0 {
+            std::process::exit(code);
+        }
+    }
+    Ok(())
+}
 
-    let mut rng = rand::thread_rng();
-    stages.shuffle(&mut rng);
+/// Tells how the wrapped command ended, and where its output went
+fn print_outcome(cli: &Cli, status: ExitStatus, theme: &Theme) {
+    let command = match &cli.wrap {
+        Some(line) => line.clone(),
+        None => cli.command_line.join(" "),
+    };
+    let log = cli.wrap_log.display();
+    let (tone, title, detail) = if status.success() {
+        (
+            Tone::Success,
+            "Installation complete!",
+            format!("`{}` finished, its output is in {}", command, log),
+        )
+    } else {
+        (
+            Tone::Error,
+            "Installation failed!",
+            format!(
+                "`{}` exited with code {}, see {}",
+                command,
+                wrap::exit_code(status),
+                log
+            ),
+        )
+    };
+    let rule = "═══════════════════════════════════════";
+    println!("\n{}", theme.paint(rule, tone, false));
+    println!("{}", theme.paint(title, tone, true));
+    println!("{}", theme.paint(&detail, Tone::Bright, false));
+    println!("{}", theme.paint(rule, tone, false));
+}
 
-    let mut installer = Installer::new(stages);
-    installer.run()
+/// Colors output that goes somewhere else than stdout, such as a recording,
+/// whether stdout is a terminal or not, unless `--color` says otherwise
+fn color_elsewhere(cli: &Cli) {
+    if cli.color == ColorChoice::Auto {
+        colored::control::set_override(true);
+    }
 }
 
-fn handle_error(e: io::Error) {
+/// When the run ends, given the options that stop it
+fn finish(cli: &Cli) -> Finish {
+    if let Some(cycles) = cli.cycles {
+        Finish::Cycles(cycles)
+    } else if let Some(duration) = cli.duration {
+        Finish::Deadline(duration)
+    } else if let Some(time) = cli.until {
+        Finish::Deadline(time_until(time, Local::now().naive_local()))
+    } else if cli.instant {
+        Finish::Cycles(1)
+    } else {
+        Finish::Never
+    }
+}
+
+/// Stdout, along with the recording if one was asked for
+fn terminal_output(cli: &Cli, clock: &Clock) -> io::Result<Box<dyn Write>> {
+    let Some(path) = &cli.record else {
+        return Ok(Box::new(io::stdout()));
+    };
+    let file = File::create(path).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("cannot create recording {}: {}", path.display(), e),
+        )
+    })?;
+    color_elsewhere(cli);
+    let size = terminal::size().unwrap_or((80, 24));
+    let cast = CastWriter::new(BufWriter::new(file), clock.clone(), size)?;
+    Ok(Box::new(Tee::new(io::stdout(), cast)))
+}
+
+fn handle_error(e: io::Error, theme: &Theme) {
     if e.kind() == io::ErrorKind::Interrupted {
-        println!(
-            "\n\n{}",
-            "═══════════════════════════════════════".bright_cyan()
-        );
-        println!("{}", "Installation cancelled by user.".bright_white());
+        let rule = "═══════════════════════════════════════";
+        println!("\n\n{}", theme.paint(rule, Tone::Highlight, false));
         println!(
             "{}",
-            "Thank you for using Universal System Installer!".bright_white()
+            theme.paint("Installation cancelled by user.", Tone::Bright, false)
         );
         println!(
             "{}",
-            "═══════════════════════════════════════".bright_cyan()
+            theme.paint(
+                "Thank you for using Universal System Installer!",
+                Tone::Bright,
+                false
+            )
         );
+        println!("{}", theme.paint(rule, Tone::Highlight, false));
     } else {
-        eprintln!("\n{} {:?}", "Error:".bright_red(), e);
+        eprintln!("\n{} {}", theme.paint("Error:", Tone::Error, false), e);
         std::process::exit(1);
     }
 }
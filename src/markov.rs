@@ -0,0 +1,349 @@
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Longest line the model makes up, in tokens
+const MAX_TOKENS: usize = 48;
+/// Context marker before the first token and after the last one
+const EDGE: u32 = u32::MAX;
+
+/// How a stage turns a log into output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogMode {
+    /// The log as it is, from the top
+    #[default]
+    Replay,
+    /// Lines picked at random from the log
+    Sample,
+    /// New lines made up from the log, never the same twice
+    Synthesize,
+}
+
+/// Part of a token: literal text or a value that gets a fresh random value
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+enum Piece {
+    Text(String),
+    /// Hex digits, the `0x` in front of them is kept as text
+    Hex {
+        width: usize,
+    },
+    /// Decimal digits, zero-padded if the original was
+    Number {
+        width: usize,
+        padded: bool,
+    },
+    /// A PCI address such as `0000:00:1f.2`, with or without the domain
+    Pci {
+        domain: bool,
+    },
+}
+
+/// A word and the whitespace after it, with its values turned into slots
+type Token = Vec<Piece>;
+
+/// Token-level Markov chain over log lines
+///
+/// Each token is predicted from the two before it. Hex addresses, PCI
+/// addresses and numbers are typed slots, so `base 0x85600000` and
+/// `base 0x92a00000` are the same token and every generated line gets new
+/// values in them.
+#[derive(Debug)]
+pub struct Markov {
+    tokens: Vec<Token>,
+    next: HashMap<(u32, u32), Vec<u32>>,
+}
+
+impl Markov {
+    pub fn train(lines: &[String]) -> Self {
+        let mut ids: HashMap<Token, u32> = HashMap::new();
+        let mut tokens = Vec::new();
+        let mut next: HashMap<(u32, u32), Vec<u32>> = HashMap::new();
+
+        for line in lines {
+            let mut context = (EDGE, EDGE);
+            for token in tokenize(line) {
+                let id = *ids.entry(token.clone()).or_insert_with(|| {
+                    tokens.push(token);
+                    tokens.len() as u32 - 1
+                });
+                next.entry(context).or_default().push(id);
+                context = (context.1, id);
+            }
+            next.entry(context).or_default().push(EDGE);
+        }
+
+        Self { tokens, next }
+    }
+
+    /// Makes up a line
+    pub fn line(&self, rng: &mut impl Rng) -> String {
+        let mut line = String::new();
+        let mut context = (EDGE, EDGE);
+        for _ in 0..MAX_TOKENS {
+            let id = match self.next.get(&context).and_then(|ids| ids.choose(rng)) {
+                Some(&id) if id != EDGE => id,
+                _ => break,
+            };
+            for piece in &self.tokens[id as usize] {
+                fill(piece, &mut line, rng);
+            }
+            context = (context.1, id);
+        }
+        line.trim_end().to_string()
+    }
+}
+
+/// The lines of a log, along with a model of them once it is trained, shared
+/// by every copy
+#[derive(Clone, Debug)]
+pub struct LogLines {
+    lines: Arc<[String]>,
+    model: Option<Arc<Markov>>,
+}
+
+impl LogLines {
+    pub fn new(lines: Vec<String>) -> Self {
+        Self {
+            lines: lines.into(),
+            model: None,
+        }
+    }
+
+    /// Trains the model made-up lines come from, which takes a while for a
+    /// long log, so it is done when the log is loaded
+    pub fn trained(self) -> Self {
+        let model = Markov::train(&self.lines);
+        Self {
+            model: Some(Arc::new(model)),
+            ..self
+        }
+    }
+
+    pub fn all(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// `count` lines of output, in the way `mode` asks for
+    ///
+    /// Without a trained model, lines are sampled instead of made up.
+    pub fn take(&self, mode: LogMode, count: usize, rng: &mut impl Rng) -> Vec<String> {
+        if self.lines.is_empty() {
+            return Vec::new();
+        }
+        match (mode, &self.model) {
+            (LogMode::Replay, _) => self.lines.iter().cycle().take(count).cloned().collect(),
+            (LogMode::Synthesize, Some(model)) => (0..count).map(|_| model.line(rng)).collect(),
+            (LogMode::Sample | LogMode::Synthesize, _) => (0..count)
+                .filter_map(|_| self.lines.choose(rng).cloned())
+                .collect(),
+        }
+    }
+}
+
+/// Splits a line into words that keep the whitespace after them, so the
+/// columns of the original survive. Leading whitespace goes with the first word.
+fn tokenize(line: &str) -> Vec<Token> {
+    let mut words: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_space = true;
+    for c in line.chars() {
+        if !c.is_whitespace() && in_space && !current.trim().is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+        in_space = c.is_whitespace();
+        current.push(c);
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words.iter().map(|word| slots(word)).collect()
+}
+
+/// Turns the values in `word` into slots
+fn slots(word: &str) -> Token {
+    let chars: Vec<char> = word.chars().collect();
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut i = 0;
+
+    // Digits glued to letters are part of a name, like i915 or x86_64
+    let is_word_char = |c: Option<&char>| c.is_some_and(|c| c.is_alphanumeric() || *c == '_');
+
+    while i < chars.len() {
+        let at_boundary = i == 0 || !is_word_char(chars.get(i - 1));
+        let hex_run = |from: usize| {
+            chars[from..]
+                .iter()
+                .take_while(|c| c.is_ascii_hexdigit())
+                .count()
+        };
+
+        let slot = if !at_boundary {
+            None
+        } else if let Some((len, domain)) = pci_address(&chars[i..]) {
+            Some((len, Piece::Pci { domain }))
+        } else if chars[i] == '0' && chars.get(i + 1) == Some(&'x') && hex_run(i + 2) > 0 {
+            text.push_str("0x");
+            i += 2;
+            Some((hex_run(i), Piece::Hex { width: hex_run(i) }))
+        } else if hex_run(i) >= 8 && chars[i..i + hex_run(i)].iter().any(char::is_ascii_digit) {
+            Some((hex_run(i), Piece::Hex { width: hex_run(i) }))
+        } else {
+            let width = chars[i..].iter().take_while(|c| c.is_ascii_digit()).count();
+            (width > 0).then_some((
+                width,
+                Piece::Number {
+                    width,
+                    padded: width > 1 && chars[i] == '0',
+                },
+            ))
+        };
+
+        match slot {
+            Some((len, piece)) if !is_word_char(chars.get(i + len)) => {
+                if !text.is_empty() {
+                    pieces.push(Piece::Text(std::mem::take(&mut text)));
+                }
+                pieces.push(piece);
+                i += len;
+            }
+            _ => {
+                text.push(chars[i]);
+                i += 1;
+            }
+        }
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    pieces
+}
+
+/// Length of the PCI address at the start of `chars`, and whether it has a domain
+fn pci_address(chars: &[char]) -> Option<(usize, bool)> {
+    let matches = |pattern: &str| {
+        chars.len() >= pattern.len()
+            && pattern.chars().zip(chars).all(|(p, c)| match p {
+                'h' => c.is_ascii_hexdigit(),
+                'f' => ('0'..='7').contains(c),
+                _ => p == *c,
+            })
+    };
+    if matches("hhhh:hh:hh.f") {
+        Some((12, true))
+    } else if matches("hh:hh.f") {
+        Some((7, false))
+    } else {
+        None
+    }
+}
+
+/// Appends `piece` to `line`, with a fresh value if it is a slot
+fn fill(piece: &Piece, line: &mut String, rng: &mut impl Rng) {
+    const HEX: &[u8] = b"0123456789abcdef";
+    match piece {
+        Piece::Text(text) => line.push_str(text),
+        Piece::Hex { width } => {
+            line.extend((0..*width).map(|_| HEX[rng.gen_range(0..16)] as char));
+        }
+        Piece::Number { width, padded } => {
+            let max = 10u64.saturating_pow(*width as u32);
+            let min = if *padded || *width == 1 { 0 } else { max / 10 };
+            let value = rng.gen_range(min..max);
+            line.push_str(&format!("{:0width$}", value, width = width));
+        }
+        Piece::Pci { domain } => {
+            if *domain {
+                line.push_str("0000:");
+            }
+            line.push_str(&format!(
+                "{:02x}:{:02x}.{}",
+                rng.gen_range(0..16),
+                rng.gen_range(0..32),
+                rng.gen_range(0..8)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_values_become_slots() {
+        assert_eq!(
+            slots("0x0000000085600000,"),
+            vec![
+                Piece::Text("0x".to_string()),
+                Piece::Hex { width: 16 },
+                Piece::Text(",".to_string())
+            ]
+        );
+        assert_eq!(slots("0000:00:1f.2"), vec![Piece::Pci { domain: true }]);
+        assert_eq!(
+            slots("[42]"),
+            vec![
+                Piece::Text("[".to_string()),
+                Piece::Number {
+                    width: 2,
+                    padded: false
+                },
+                Piece::Text("]".to_string())
+            ]
+        );
+        // Names keep their digits
+        assert_eq!(slots("i915"), vec![Piece::Text("i915".to_string())]);
+        assert_eq!(slots("x86_64"), vec![Piece::Text("x86_64".to_string())]);
+    }
+
+    #[test]
+    fn test_tokens_keep_columns() {
+        let line = "  CC      init/main.o";
+        let tokens = tokenize(line);
+        assert_eq!(tokens.len(), 2);
+        let mut rng = StdRng::seed_from_u64(0);
+        let model = Markov::train(&[line.to_string()]);
+        assert_eq!(model.line(&mut rng), line);
+    }
+
+    #[test]
+    fn test_synthesized_values_are_fresh() {
+        let lines = vec!["Reserved memory: base 0x0000000085600000, size 2 MiB".to_string()];
+        let logs = LogLines::new(lines).trained();
+        let mut rng = StdRng::seed_from_u64(1);
+        let made_up = logs.take(LogMode::Synthesize, 20, &mut rng);
+        assert_eq!(made_up.len(), 20);
+        for line in &made_up {
+            assert!(line.starts_with("Reserved memory: base 0x"), "{}", line);
+            assert!(line.ends_with(" MiB"), "{}", line);
+        }
+        let mut unique = made_up.clone();
+        unique.sort();
+        unique.dedup();
+        assert!(unique.len() > 1);
+    }
+
+    #[test]
+    fn test_untrained_log_is_sampled() {
+        let lines = vec!["Reserved memory: base 0x0000000085600000, size 2 MiB".to_string()];
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(
+            LogLines::new(lines.clone()).take(LogMode::Synthesize, 2, &mut rng),
+            [lines[0].clone(), lines[0].clone()]
+        );
+    }
+
+    #[test]
+    fn test_replay_keeps_order() {
+        let logs = LogLines::new(vec!["a".to_string(), "b".to_string()]);
+        let mut rng = StdRng::seed_from_u64(0);
+        assert_eq!(logs.take(LogMode::Replay, 3, &mut rng), ["a", "b", "a"]);
+    }
+}
@@ -0,0 +1,162 @@
+use crate::cli::{Order, Stage, StageId};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+/// Stages that have to be installed before `stage` when the order is realistic
+fn dependencies(stage: Stage) -> &'static [Stage] {
+    match stage {
+        Stage::Bios => &[],
+        Stage::Boot => &[Stage::Bios],
+        Stage::Filesystem => &[Stage::Boot],
+        Stage::Bootloader => &[Stage::Filesystem],
+        Stage::System => &[Stage::Filesystem],
+        Stage::Drivers => &[Stage::Boot],
+        Stage::Network => &[Stage::Drivers],
+        Stage::Locale => &[Stage::System],
+        Stage::Kernel => &[Stage::System],
+        Stage::Initramfs => &[Stage::Bootloader, Stage::Kernel],
+        Stage::Services => &[Stage::Initramfs, Stage::Network],
+        Stage::Packages => &[Stage::System, Stage::Network],
+        Stage::Compilation => &[Stage::Packages],
+        Stage::Deno => &[Stage::Packages],
+        Stage::Database => &[Stage::Packages],
+        Stage::Xorg => &[Stage::Drivers, Stage::Packages],
+        Stage::Retro => &[Stage::Xorg],
+        Stage::Container => &[Stage::Services],
+        Stage::Ai => &[Stage::Drivers, Stage::Packages],
+        Stage::Cloud => &[Stage::Network],
+    }
+}
+
+/// Whether `stage` has to come after `other`, directly or through other stages
+fn depends_on(stage: Stage, other: Stage) -> bool {
+    dependencies(stage)
+        .iter()
+        .any(|&dependency| dependency == other || depends_on(dependency, other))
+}
+
+/// Whether `stage` has to come after `other`, scripted stages can go anywhere
+fn must_follow(stage: &StageId, other: &StageId) -> bool {
+    match (stage, other) {
+        (StageId::Builtin(stage), StageId::Builtin(other)) => depends_on(*stage, *other),
+        _ => false,
+    }
+}
+
+/// Puts the selected stages in the order they will run
+pub fn arrange(mut stages: Vec<StageId>, order: Order, rng: &mut impl Rng) -> Vec<StageId> {
+    match order {
+        // Installation order, then the scripted stages by name
+        Order::Fixed => {
+            stages.sort();
+            stages
+        }
+        Order::Shuffle => {
+            stages.shuffle(rng);
+            stages
+        }
+        Order::Realistic => {
+            // Pick at random among the stages whose dependencies are already
+            // placed. Unselected stages still link the ones around them, so
+            // Bios comes before Filesystem even when Boot is left out.
+            let mut ordered = Vec::with_capacity(stages.len());
+            while !stages.is_empty() {
+                let ready: Vec<usize> = (0..stages.len())
+                    .filter(|&i| {
+                        !stages
+                            .iter()
+                            .any(|other| *other != stages[i] && must_follow(&stages[i], other))
+                    })
+                    .collect();
+                let pick = ready[rng.gen_range(0..ready.len())];
+                ordered.push(stages.remove(pick));
+            }
+            ordered
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn ids(stages: Vec<Stage>) -> Vec<StageId> {
+        stages.into_iter().map(StageId::from).collect()
+    }
+
+    fn assert_respects_dependencies(order: &[StageId]) {
+        for (i, stage) in order.iter().enumerate() {
+            for later in &order[i + 1..] {
+                assert!(
+                    !must_follow(stage, later),
+                    "{:?} runs before {:?} in {:?}",
+                    stage,
+                    later,
+                    order
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fixed_uses_installation_order() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let stages = vec![
+            StageId::Script("vpn".to_string()),
+            Stage::Cloud.into(),
+            Stage::Bios.into(),
+            Stage::Kernel.into(),
+        ];
+        assert_eq!(
+            arrange(stages, Order::Fixed, &mut rng),
+            vec![
+                Stage::Bios.into(),
+                Stage::Kernel.into(),
+                Stage::Cloud.into(),
+                StageId::Script("vpn".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_installation_order_respects_dependencies() {
+        assert_respects_dependencies(&ids(Stage::all()));
+    }
+
+    #[test]
+    fn test_shuffle_keeps_every_stage() {
+        let mut rng = StdRng::seed_from_u64(0);
+        let mut result = arrange(ids(Stage::all()), Order::Shuffle, &mut rng);
+        assert_ne!(result, ids(Stage::all()));
+        result.sort();
+        assert_eq!(result, ids(Stage::all()));
+    }
+
+    #[test]
+    fn test_realistic_respects_dependencies() {
+        let mut orders = Vec::new();
+        for seed in 0..50 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let result = arrange(ids(Stage::all()), Order::Realistic, &mut rng);
+            assert_eq!(result.len(), Stage::all().len());
+            assert_respects_dependencies(&result);
+            orders.push(result);
+        }
+        orders.dedup();
+        assert!(orders.len() > 1, "realistic order is never shuffled");
+    }
+
+    #[test]
+    fn test_realistic_links_through_unselected_stages() {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let stages = ids(vec![Stage::Services, Stage::Filesystem, Stage::Bios]);
+            assert_eq!(
+                arrange(stages, Order::Realistic, &mut rng),
+                ids(vec![Stage::Bios, Stage::Filesystem, Stage::Services])
+            );
+        }
+    }
+}
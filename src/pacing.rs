@@ -0,0 +1,131 @@
+use crate::cli::{Stage, StageId};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Remaining time, in cycles, below which the next cycle is the last one
+///
+/// The last cycle is slowed down or sped up to fit, so anything between one
+/// and one and a half cycles is absorbed without running much off speed.
+const LAST_CYCLE_THRESHOLD: f64 = 1.5;
+
+/// When a run ends
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Finish {
+    /// Loop forever
+    Never,
+    /// Stop after this many cycles
+    Cycles(u32),
+    /// Stop as close as possible to this much playback time after the start
+    Deadline(Duration),
+}
+
+/// Typical simulated length of a stage at speed 1, including the messages
+/// and pause around it, measured over a few seeds with `--instant`
+fn typical_ms(stage: &StageId) -> u64 {
+    let stage = match stage {
+        StageId::Builtin(stage) => stage,
+        // Scripts are usually short, their real length is known after a cycle
+        StageId::Script(_) | StageId::Custom(_) => return 20_000,
+    };
+    match stage {
+        Stage::Bios => 31_000,
+        Stage::Boot => 3_000,
+        Stage::Filesystem => 12_000,
+        Stage::Bootloader => 22_000,
+        Stage::System => 6_500,
+        Stage::Drivers => 6_000,
+        Stage::Network => 7_000,
+        Stage::Kernel => 650_000,
+        Stage::Initramfs => 7_500,
+        Stage::Packages => 24_000,
+        Stage::Compilation => 150_000,
+        Stage::Deno => 700_000,
+        Stage::Database => 10_000,
+        Stage::Xorg => 30_000,
+        Stage::Services => 9_000,
+        Stage::Retro => 30_000,
+        Stage::Locale => 9_000,
+        Stage::Container => 58_000,
+        Stage::Ai => 18_000,
+        Stage::Cloud => 15_000,
+    }
+}
+
+/// Chooses the clock speed that makes a run end on its deadline
+///
+/// Stage lengths start from built-in estimates and are replaced by what each
+/// stage actually took once it has run.
+#[derive(Default)]
+pub struct Pacer {
+    measured: HashMap<StageId, u64>,
+}
+
+impl Pacer {
+    /// Expected simulated milliseconds for `stage`
+    pub fn estimate(&self, stage: &StageId) -> u64 {
+        self.measured
+            .get(stage)
+            .copied()
+            .unwrap_or_else(|| typical_ms(stage))
+    }
+
+    /// Remembers how many simulated milliseconds `stage` took
+    pub fn record(&mut self, stage: StageId, ms: u64) {
+        self.measured.insert(stage, ms);
+    }
+
+    /// Expected simulated milliseconds for `stages`
+    pub fn total(&self, stages: &[StageId]) -> u64 {
+        stages.iter().map(|stage| self.estimate(stage)).sum()
+    }
+
+    /// Whether a cycle starting with `remaining` time left should be the last
+    pub fn is_last_cycle(&self, stages: &[StageId], speed: f64, remaining: Duration) -> bool {
+        let cycle = Duration::from_secs_f64(self.total(stages) as f64 / 1000.0 / speed);
+        remaining.as_secs_f64() < cycle.as_secs_f64() * LAST_CYCLE_THRESHOLD
+    }
+
+    /// Speed at which `stages` take up the `remaining` time
+    pub fn speed_for(&self, stages: &[StageId], remaining: Duration) -> f64 {
+        let total = self.total(stages) as f64 / 1000.0;
+        if remaining.is_zero() {
+            f64::INFINITY
+        } else {
+            total / remaining.as_secs_f64()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_measurements_replace_estimates() {
+        let mut pacer = Pacer::default();
+        let boot = StageId::from(Stage::Boot);
+        assert_eq!(pacer.estimate(&boot), typical_ms(&boot));
+        pacer.record(boot.clone(), 1234);
+        assert_eq!(pacer.estimate(&boot), 1234);
+    }
+
+    #[test]
+    fn test_speed_fills_remaining_time() {
+        let mut pacer = Pacer::default();
+        pacer.record(Stage::Boot.into(), 30_000);
+        pacer.record(Stage::Bios.into(), 30_000);
+        let stages = [Stage::Bios.into(), Stage::Boot.into()];
+        assert_eq!(pacer.speed_for(&stages, Duration::from_secs(30)), 2.0);
+        assert_eq!(pacer.speed_for(&stages, Duration::from_secs(120)), 0.5);
+    }
+
+    #[test]
+    fn test_last_cycle() {
+        let mut pacer = Pacer::default();
+        pacer.record(Stage::Boot.into(), 60_000);
+        let stages = [Stage::Boot.into()];
+        assert!(!pacer.is_last_cycle(&stages, 1.0, Duration::from_secs(120)));
+        assert!(pacer.is_last_cycle(&stages, 1.0, Duration::from_secs(80)));
+        assert!(!pacer.is_last_cycle(&stages, 2.0, Duration::from_secs(80)));
+    }
+}
@@ -0,0 +1,218 @@
+use super::{Event, Line, Progress, Renderer, Theme, Tone};
+use crate::log_generator::LogGenerator;
+use crate::ui::layout::{terminal_width, truncate, width};
+use crate::ui::{ProgressBar, ProgressStyle};
+use crossterm::{
+    cursor, queue,
+    terminal::{self, ClearType},
+};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+/// Renders events as colored terminal output, redrawing bars and spinners in place
+///
+/// Lines that get redrawn are cut to the width of the terminal, which is read
+/// again for every frame, so that they never wrap and `\r` can always go back
+/// to their start, even after the terminal was resized.
+pub struct AnsiRenderer<W: Write> {
+    out: W,
+    theme: Theme,
+    transient: bool,
+    /// Style of the progress bar on the current line, until the bar ends
+    bar: Option<ProgressStyle>,
+    /// Lines of an unfinished block right above the cursor
+    block: u16,
+    /// Width kept up to date by someone else, instead of the terminal's
+    width: Option<Arc<AtomicUsize>>,
+}
+
+impl<W: Write> AnsiRenderer<W> {
+    pub fn new(out: W, theme: Theme) -> Self {
+        Self {
+            out,
+            theme,
+            transient: false,
+            bar: None,
+            block: 0,
+            width: None,
+        }
+    }
+
+    /// Fits the output to `width` columns, for a screen that is not this
+    /// terminal, such as a telnet client that reports its window size
+    pub fn with_width(mut self, width: Arc<AtomicUsize>) -> Self {
+        self.width = Some(width);
+        self
+    }
+
+    /// Ends the current line, with a carriage return since the terminal is in raw mode
+    fn end_line(&mut self) -> io::Result<()> {
+        write!(self.out, "\r\n")
+    }
+
+    fn write_line(&mut self, line: &Line) -> io::Result<()> {
+        if let Some(stamp) = line.stamp {
+            let stamp_text = LogGenerator::format_timestamp(stamp.seconds);
+            write!(
+                self.out,
+                "{} ",
+                self.theme.paint(&stamp_text, stamp.tone, false)
+            )?;
+        }
+        for span in &line.spans {
+            write!(
+                self.out,
+                "{}",
+                self.theme.paint(&span.text, span.tone, span.bold)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_progress(&mut self, progress: &Progress) -> io::Result<()> {
+        let line = ProgressBar::frame(progress, self.columns());
+        write!(self.out, "\r")?;
+        self.write_line(&line)?;
+        // Wipe what is left of a longer frame
+        queue!(self.out, terminal::Clear(ClearType::UntilNewLine))
+    }
+
+    /// Draws `lines` over the previous frame of the block
+    fn write_block(&mut self, lines: &[Line]) -> io::Result<()> {
+        if self.block > 0 {
+            queue!(self.out, cursor::MoveToPreviousLine(self.block))?;
+        }
+        let columns = self.columns().saturating_sub(1);
+        for line in lines {
+            self.write_line(&line.truncate(columns))?;
+            queue!(self.out, terminal::Clear(ClearType::UntilNewLine))?;
+            self.end_line()?;
+        }
+        // Wipe the rest of a taller frame
+        queue!(self.out, terminal::Clear(ClearType::FromCursorDown))?;
+        self.block = lines.len() as u16;
+        Ok(())
+    }
+}
+
+impl<W: Write> Renderer for AnsiRenderer<W> {
+    fn render(&mut self, event: &Event) -> io::Result<()> {
+        // Output in the middle of a bar takes its line, the next frame is drawn below
+        let interrupts_bar = self.bar.is_some()
+            && !matches!(
+                event,
+                Event::Progress(_) | Event::ProgressEnd(_) | Event::Paused(_)
+            );
+        if self.transient || interrupts_bar {
+            write!(self.out, "\r")?;
+            queue!(self.out, terminal::Clear(ClearType::CurrentLine))?;
+            self.transient = false;
+            self.bar = None;
+        }
+        // Likewise for a block, which is drawn again below the output by its next frame
+        let interrupts_block = self.block > 0
+            && !matches!(event, Event::Block(_) | Event::BlockEnd | Event::Paused(_));
+        if interrupts_block {
+            queue!(
+                self.out,
+                cursor::MoveToPreviousLine(self.block),
+                terminal::Clear(ClearType::FromCursorDown)
+            )?;
+            self.block = 0;
+        }
+
+        match event {
+            Event::Clear => {
+                queue!(
+                    self.out,
+                    terminal::Clear(ClearType::All),
+                    cursor::MoveTo(0, 0)
+                )?;
+            }
+            Event::StageStart { name, tone } => {
+                self.end_line()?;
+                write!(
+                    self.out,
+                    "{}\r\n",
+                    self.theme.paint(&format!("> {}", name), *tone, true)
+                )?;
+                self.end_line()?;
+            }
+            Event::CycleStart { .. } | Event::CycleEnd { .. } | Event::StageEnd { .. } => {}
+            Event::Line(line) | Event::Resolve(line) | Event::Retry(line) => {
+                self.write_line(line)?;
+                self.end_line()?;
+            }
+            Event::Blank => self.end_line()?,
+            Event::Pending(line) | Event::Prompt(line) => self.write_line(line)?,
+            Event::Transient(line) => {
+                let line = line.truncate(self.columns().saturating_sub(1));
+                self.write_line(&line)?;
+                self.transient = true;
+            }
+            Event::Progress(progress) => {
+                self.write_progress(progress)?;
+                self.bar = Some(progress.style);
+            }
+            Event::ProgressEnd(verdict) => {
+                let wiped = self.bar.take().is_some_and(ProgressStyle::is_wiped);
+                if wiped && verdict.is_none() {
+                    write!(self.out, "\r")?;
+                    queue!(self.out, terminal::Clear(ClearType::CurrentLine))?;
+                } else {
+                    if let Some(verdict) = verdict {
+                        write!(self.out, " ")?;
+                        self.write_line(verdict)?;
+                    }
+                    self.end_line()?;
+                }
+            }
+            Event::Spinner { frame, message } => {
+                let room = self.columns().saturating_sub(width(frame) + 2);
+                let message = truncate(message, room);
+                write!(
+                    self.out,
+                    "\r{} {}",
+                    frame,
+                    self.theme.paint(&message, Tone::Bright, false)
+                )?;
+                queue!(self.out, terminal::Clear(ClearType::UntilNewLine))?;
+            }
+            Event::Block(lines) => self.write_block(lines)?,
+            Event::BlockEnd => self.block = 0,
+            Event::Paused(_) | Event::Help(None) => {}
+            Event::Help(Some(hotkeys)) => {
+                self.end_line()?;
+                for (key, action) in hotkeys.iter() {
+                    write!(
+                        self.out,
+                        "  {}  {}\r\n",
+                        self.theme
+                            .paint(&format!("{:>5}", key), Tone::Highlight, true),
+                        action
+                    )?;
+                }
+            }
+            Event::SpinnerEnd { message } => {
+                write!(
+                    self.out,
+                    "\r  {}",
+                    self.theme.paint(message, Tone::Bright, false)
+                )?;
+                // Wipe the end of a frame wider than the indent
+                queue!(self.out, terminal::Clear(ClearType::UntilNewLine))?;
+                self.end_line()?;
+            }
+        }
+
+        self.out.flush()
+    }
+
+    fn columns(&self) -> usize {
+        match &self.width {
+            Some(width) => width.load(Ordering::Relaxed),
+            None => terminal_width(),
+        }
+    }
+}
@@ -0,0 +1,324 @@
+use super::{Event, Line, Renderer, Theme, Tone};
+use crate::log_generator::LogGenerator;
+use crate::serve::{Hub, Update};
+use crate::ui::{ProgressBar, ProgressStyle};
+use std::io;
+
+/// Width of the panel on the page, in columns
+const COLUMNS: usize = 100;
+
+/// What is drawn below the finished lines
+enum Live {
+    None,
+    /// A line waiting for its `Resolve`
+    Pending(Line),
+    /// A line that the next event replaces
+    Transient(Line),
+    /// Lines that stay below the log until the block ends
+    Block(Vec<Line>),
+}
+
+/// Renders events as HTML for the browsers watching a `Hub`
+///
+/// Lines are painted with the theme like on a terminal, and the escape codes
+/// that come out are turned into styled spans.
+pub struct HtmlRenderer {
+    hub: Hub,
+    theme: Theme,
+    live: Live,
+    /// Style of the current progress bar, until it ends
+    bar: Option<ProgressStyle>,
+    /// What the browsers were last sent as the live lines
+    shown: Vec<String>,
+}
+
+impl HtmlRenderer {
+    pub fn new(hub: Hub, theme: Theme) -> Self {
+        Self {
+            hub,
+            theme,
+            live: Live::None,
+            bar: None,
+            shown: Vec::new(),
+        }
+    }
+
+    fn html(&self, line: &Line) -> String {
+        let mut text = String::new();
+        if let Some(stamp) = line.stamp {
+            let stamp_text = LogGenerator::format_timestamp(stamp.seconds);
+            text += &format!("{} ", self.theme.paint(&stamp_text, stamp.tone, false));
+        }
+        for span in &line.spans {
+            text += &self
+                .theme
+                .paint(&span.text, span.tone, span.bold)
+                .to_string();
+        }
+        ansi_to_html(&text)
+    }
+
+    fn push(&self, line: &Line) {
+        self.hub.publish(Update::Line(self.html(line)));
+    }
+
+    fn update(&mut self, event: &Event) {
+        let replaced = match std::mem::replace(&mut self.live, Live::None) {
+            Live::Transient(line) => Some(line),
+            live => {
+                self.live = live;
+                None
+            }
+        };
+
+        match event {
+            Event::Clear => self.hub.publish(Update::Clear),
+            Event::StageStart { name, tone } => {
+                self.hub.publish(Update::Stage(name.to_string()));
+                self.push(&Line::new());
+                self.push(&Line::styled(*tone, format!("> {}", name)).bold());
+                self.push(&Line::new());
+            }
+            Event::CycleStart { .. }
+            | Event::CycleEnd { .. }
+            | Event::StageEnd { .. }
+            | Event::Help(_) => {}
+            Event::Line(line) | Event::Resolve(line) | Event::Retry(line) => {
+                match std::mem::replace(&mut self.live, Live::None) {
+                    Live::Pending(mut pending) => {
+                        pending.spans.extend(line.spans.iter().cloned());
+                        self.push(&pending);
+                    }
+                    live => {
+                        self.live = live;
+                        self.push(line);
+                    }
+                }
+            }
+            Event::Blank => self.push(&Line::new()),
+            Event::Pending(line) | Event::Prompt(line) => {
+                self.live = Live::Pending((*line).clone());
+            }
+            Event::Transient(line) => self.live = Live::Transient((*line).clone()),
+            Event::Progress(progress) => {
+                self.live = Live::Transient(ProgressBar::frame(progress, COLUMNS));
+                self.bar = Some(progress.style);
+            }
+            Event::ProgressEnd(verdict) => {
+                let wiped = self.bar.take().is_some_and(ProgressStyle::is_wiped);
+                if !wiped || verdict.is_some() {
+                    let mut line = replaced.unwrap_or_default();
+                    if let Some(verdict) = verdict {
+                        line = line.with(Tone::Plain, " ");
+                        line.spans.extend(verdict.spans.iter().cloned());
+                    }
+                    self.push(&line);
+                }
+            }
+            Event::Spinner { frame, message } => {
+                self.live = Live::Transient(
+                    Line::styled(Tone::Plain, format!("{} ", frame)).with(Tone::Bright, message),
+                );
+            }
+            Event::SpinnerEnd { message } => {
+                self.push(&Line::styled(Tone::Plain, "  ").with(Tone::Bright, message));
+            }
+            Event::Block(lines) => self.live = Live::Block(lines.to_vec()),
+            Event::BlockEnd => match std::mem::replace(&mut self.live, Live::None) {
+                Live::Block(lines) => lines.iter().for_each(|line| self.push(line)),
+                live => self.live = live,
+            },
+            Event::Paused(_) => {
+                // Keep whatever was on screen while frozen
+                if let Some(line) = replaced {
+                    self.live = Live::Transient(line);
+                }
+            }
+        }
+    }
+}
+
+impl Renderer for HtmlRenderer {
+    fn render(&mut self, event: &Event) -> io::Result<()> {
+        self.update(event);
+
+        let live: Vec<String> = match &self.live {
+            Live::None => Vec::new(),
+            Live::Pending(line) | Live::Transient(line) => vec![self.html(line)],
+            Live::Block(lines) => lines.iter().map(|line| self.html(line)).collect(),
+        };
+        if live != self.shown {
+            self.hub.publish(Update::Live(live.clone()));
+            self.shown = live;
+        }
+        Ok(())
+    }
+
+    fn columns(&self) -> usize {
+        COLUMNS
+    }
+}
+
+/// Text styles set by escape codes
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+struct Sgr {
+    color: Option<&'static str>,
+    rgb: Option<(u8, u8, u8)>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    reverse: bool,
+}
+
+impl Sgr {
+    /// Applies the parameters of one `ESC [ ... m` sequence
+    fn apply(&mut self, params: &str) {
+        let codes: Vec<u8> = params
+            .split(';')
+            .map(|code| code.parse().unwrap_or(0))
+            .collect();
+        let mut codes = codes.into_iter();
+        while let Some(code) = codes.next() {
+            match code {
+                0 => *self = Sgr::default(),
+                1 => self.bold = true,
+                2 => self.dim = true,
+                3 => self.italic = true,
+                4 => self.underline = true,
+                7 => self.reverse = true,
+                22 => (self.bold, self.dim) = (false, false),
+                23 => self.italic = false,
+                24 => self.underline = false,
+                27 => self.reverse = false,
+                30..=37 => self.set_color(PALETTE[(code - 30) as usize]),
+                90..=97 => self.set_color(PALETTE[(code - 90 + 8) as usize]),
+                39 => (self.color, self.rgb) = (None, None),
+                38 if codes.next() == Some(2) => {
+                    let mut channel = || codes.next().unwrap_or(0);
+                    self.rgb = Some((channel(), channel(), channel()));
+                    self.color = None;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn set_color(&mut self, color: &'static str) {
+        self.color = Some(color);
+        self.rgb = None;
+    }
+
+    /// The CSS for these styles, empty for plain text
+    fn css(&self) -> String {
+        let color = match (self.color, self.rgb) {
+            (Some(color), _) => Some(color.to_string()),
+            (None, Some((r, g, b))) => Some(format!("#{:02x}{:02x}{:02x}", r, g, b)),
+            (None, None) => None,
+        };
+        let mut css = String::new();
+        if self.reverse {
+            css += "color:var(--bg);";
+            css += &format!("background:{};", color.as_deref().unwrap_or("var(--fg)"));
+        } else if let Some(color) = color {
+            css += &format!("color:{};", color);
+        }
+        if self.bold {
+            css += "font-weight:bold;";
+        }
+        if self.dim {
+            css += "opacity:0.6;";
+        }
+        if self.italic {
+            css += "font-style:italic;";
+        }
+        if self.underline {
+            css += "text-decoration:underline;";
+        }
+        css
+    }
+}
+
+/// The 16 terminal colors, normal ones first, as xterm draws them
+const PALETTE: [&str; 16] = [
+    "#000000", "#cd0000", "#00cd00", "#cdcd00", "#0000ee", "#cd00cd", "#00cdcd", "#e5e5e5",
+    "#7f7f7f", "#ff0000", "#00ff00", "#ffff00", "#5c5cff", "#ff00ff", "#00ffff", "#ffffff",
+];
+
+/// Turns text colored with escape codes into HTML spans with inline styles
+pub fn ansi_to_html(text: &str) -> String {
+    let mut html = String::new();
+    let mut sgr = Sgr::default();
+    let mut run = String::new();
+    let mut chars = text.chars();
+
+    let flush = |html: &mut String, run: &mut String, sgr: &Sgr| {
+        if run.is_empty() {
+            return;
+        }
+        let css = sgr.css();
+        if css.is_empty() {
+            html.push_str(run);
+        } else {
+            html.push_str(&format!("<span style=\"{}\">{}</span>", css, run));
+        }
+        run.clear();
+    };
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => {
+                // Only colors make it into the page, other sequences are dropped
+                if chars.next() != Some('[') {
+                    continue;
+                }
+                let mut params = String::new();
+                let end = chars.by_ref().find(|&c| {
+                    let done = c.is_ascii_alphabetic();
+                    if !done {
+                        params.push(c);
+                    }
+                    done
+                });
+                if end == Some('m') {
+                    let mut next = sgr;
+                    next.apply(&params);
+                    if next != sgr {
+                        flush(&mut html, &mut run, &sgr);
+                        sgr = next;
+                    }
+                }
+            }
+            '&' => run.push_str("&amp;"),
+            '<' => run.push_str("&lt;"),
+            '>' => run.push_str("&gt;"),
+            '"' => run.push_str("&quot;"),
+            '\r' | '\n' => {}
+            c => run.push(c),
+        }
+    }
+    flush(&mut html, &mut run, &sgr);
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi_to_html() {
+        assert_eq!(ansi_to_html("a < b"), "a &lt; b");
+        assert_eq!(
+            ansi_to_html("\x1b[1;91mError:\x1b[0m disk <full>"),
+            "<span style=\"color:#ff0000;font-weight:bold;\">Error:</span> disk &lt;full&gt;"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[38;2;255;176;0mamber\x1b[0m"),
+            "<span style=\"color:#ffb000;\">amber</span>"
+        );
+        assert_eq!(
+            ansi_to_html("\x1b[7mOK\x1b[0m\x1b[2K"),
+            "<span style=\"color:var(--bg);background:var(--fg);\">OK</span>"
+        );
+    }
+}
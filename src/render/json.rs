@@ -0,0 +1,244 @@
+use super::{Event, Line, Progress, Renderer, Severity};
+use crate::clock::Clock;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+
+/// Width lines are laid out for, there is no terminal to fit
+const COLUMNS: usize = 80;
+
+/// One line of output
+#[derive(Serialize)]
+struct Record<'a> {
+    event: &'a str,
+    stage: Option<&'a str>,
+    severity: Severity,
+    timestamp: f64,
+    elapsed_ms: u64,
+    text: &'a str,
+    /// Fields of some events only, such as the percent of a progress bar
+    #[serde(flatten)]
+    extra: Value,
+}
+
+/// Writes every event as a line of JSON, for programs that watch the installer
+///
+/// Each object has the kind of `event`, the `stage` it happened in, its
+/// `severity`, the simulated `timestamp` and the `text`, plus `elapsed_ms` of
+/// simulated time. Frames of spinners and blocks are left out, and a progress
+/// bar is reported once per percent.
+pub struct JsonRenderer<W: Write> {
+    out: W,
+    clock: Clock,
+    /// Timestamp of the last stamped line, for events that have none
+    uptime: f64,
+    stage: Option<String>,
+    /// A line waiting for its `Resolve`
+    pending: Option<Line>,
+    /// The last percent reported for the current bar
+    percent: Option<u32>,
+    /// Label of the current bar
+    bar: String,
+    /// The last frame of the current block
+    block: Vec<Line>,
+}
+
+impl<W: Write> JsonRenderer<W> {
+    pub fn new(out: W, clock: Clock) -> Self {
+        Self {
+            out,
+            clock,
+            uptime: 0.0,
+            stage: None,
+            pending: None,
+            percent: None,
+            bar: String::new(),
+            block: Vec::new(),
+        }
+    }
+
+    /// Writes one event, with `extra` fields after the common ones
+    fn write(
+        &mut self,
+        event: &str,
+        severity: Severity,
+        timestamp: Option<f64>,
+        text: &str,
+        extra: Value,
+    ) -> io::Result<()> {
+        if let Some(timestamp) = timestamp {
+            self.uptime = timestamp;
+        }
+        let record = Record {
+            event,
+            stage: self.stage.as_deref(),
+            severity,
+            timestamp: self.uptime,
+            elapsed_ms: self.clock.simulated_ms(),
+            text,
+            extra,
+        };
+        serde_json::to_writer(&mut self.out, &record)?;
+        writeln!(self.out)
+    }
+
+    /// Writes a finished line as a log, warning or error event
+    fn write_line(&mut self, line: &Line) -> io::Result<()> {
+        let event = match line.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+            Severity::Info | Severity::Success => "log",
+        };
+        self.write_as(event, line)
+    }
+
+    fn write_as(&mut self, event: &str, line: &Line) -> io::Result<()> {
+        let timestamp = line.stamp.map(|stamp| stamp.seconds);
+        self.write(event, line.severity, timestamp, &text(line), json!({}))
+    }
+
+    fn write_progress(&mut self, progress: &Progress) -> io::Result<()> {
+        let percent = (progress.fraction.clamp(0.0, 1.0) * 100.0) as u32;
+        let label = text(progress.label);
+        // Some bars are wiped instead of ended, a new label is a new bar
+        if self.percent == Some(percent) && label == self.bar {
+            return Ok(());
+        }
+        self.percent = Some(percent);
+        self.bar = label.clone();
+        self.write(
+            "progress",
+            Severity::Info,
+            None,
+            &label,
+            json!({ "percent": percent }),
+        )
+    }
+}
+
+impl<W: Write> Renderer for JsonRenderer<W> {
+    fn render(&mut self, event: &Event) -> io::Result<()> {
+        match event {
+            Event::CycleStart { number, stages } => self.write(
+                "cycle_start",
+                Severity::Info,
+                None,
+                &format!("Cycle {}", number),
+                json!({ "cycle": number, "stages": stages }),
+            )?,
+            Event::CycleEnd { number } => self.write(
+                "cycle_end",
+                Severity::Success,
+                None,
+                &format!("Cycle {}", number),
+                json!({ "cycle": number }),
+            )?,
+            Event::StageStart { name, .. } => {
+                self.stage = Some(name.to_string());
+                self.write("stage_start", Severity::Info, None, name, json!({}))?;
+            }
+            Event::StageEnd { name } => {
+                self.write("stage_end", Severity::Success, None, name, json!({}))?;
+                self.stage = None;
+            }
+            Event::Line(line) | Event::Resolve(line) => match self.pending.take() {
+                Some(mut pending) => {
+                    pending.severity = pending.severity.max(line.severity);
+                    pending.spans.extend(line.spans.iter().cloned());
+                    self.write_line(&pending)?;
+                }
+                None => self.write_line(line)?,
+            },
+            Event::Retry(line) => self.write_as("retry", line)?,
+            Event::Pending(line) => self.pending = Some((*line).clone()),
+            Event::Prompt(line) => {
+                self.write_as("prompt", line)?;
+                self.pending = Some((*line).clone());
+            }
+            Event::Progress(progress) => self.write_progress(progress)?,
+            Event::ProgressEnd(verdict) => {
+                // Also sent when a stage is cut short, with or without a bar
+                if let Some(percent) = self.percent.take() {
+                    let label = std::mem::take(&mut self.bar);
+                    let verdict = verdict.map(text);
+                    self.write(
+                        "progress",
+                        Severity::Info,
+                        None,
+                        &label,
+                        json!({ "percent": percent, "done": true, "verdict": verdict }),
+                    )?;
+                }
+            }
+            Event::SpinnerEnd { message } => {
+                self.write("log", Severity::Info, None, message, json!({}))?;
+            }
+            Event::Block(lines) => self.block = lines.to_vec(),
+            Event::BlockEnd => {
+                for line in std::mem::take(&mut self.block) {
+                    self.write_line(&line)?;
+                }
+            }
+            Event::Clear
+            | Event::Blank
+            | Event::Transient(_)
+            | Event::Spinner { .. }
+            | Event::Paused(_)
+            | Event::Help(_) => {}
+        }
+        self.out.flush()
+    }
+
+    fn columns(&self) -> usize {
+        COLUMNS
+    }
+}
+
+/// The text of a line, without its timestamp
+fn text(line: &Line) -> String {
+    line.spans.iter().map(|span| span.text.as_str()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Tone;
+
+    #[test]
+    fn test_events_become_json_lines() {
+        let mut out = Vec::new();
+        let mut renderer = JsonRenderer::new(&mut out, Clock::instant());
+        let pending = Line::styled(Tone::Plain, "Mounting /boot... ");
+        let ok = Line::styled(Tone::Success, "OK");
+        let warning = Line::styled(Tone::Warning, "Disk almost full").stamped(1.5);
+        let events = [
+            Event::StageStart {
+                name: "Kernel",
+                tone: Tone::Header,
+            },
+            Event::Pending(&pending),
+            Event::Resolve(&ok),
+            Event::Line(&warning),
+            Event::Blank,
+            Event::StageEnd { name: "Kernel" },
+        ];
+        for event in &events {
+            renderer.render(event).unwrap();
+        }
+
+        let records: Vec<Value> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[0]["event"], "stage_start");
+        assert_eq!(records[1]["text"], "Mounting /boot... OK");
+        assert_eq!(records[1]["severity"], "success");
+        assert_eq!(records[1]["stage"], "Kernel");
+        assert_eq!(records[2]["event"], "warning");
+        assert_eq!(records[2]["timestamp"], 1.5);
+        assert_eq!(records[3]["event"], "stage_end");
+        assert_eq!(records[3]["timestamp"], 1.5);
+    }
+}
@@ -0,0 +1,307 @@
+mod ansi;
+mod html;
+mod json;
+mod plain;
+mod record;
+mod theme;
+mod tui;
+
+pub use ansi::AnsiRenderer;
+pub use html::{ansi_to_html, HtmlRenderer};
+pub use json::JsonRenderer;
+pub use plain::PlainRenderer;
+pub use record::{CastWriter, Tee};
+pub use theme::{Style, Theme};
+pub use tui::TuiRenderer;
+
+use crate::log_generator::LogGenerator;
+use crate::ui::layout::{self, terminal_width};
+use crate::ui::progress::Transfer;
+use crate::ui::ProgressStyle;
+use serde::{Deserialize, Serialize};
+use std::fmt::Display;
+use std::io;
+
+/// Semantic color of a piece of text, renderers decide how each one looks
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Tone {
+    /// Terminal default
+    Plain,
+    /// Background chatter such as kernel messages
+    Dim,
+    /// Emphasized text
+    Bright,
+    /// Stage titles and section headers
+    Header,
+    Accent,
+    Highlight,
+    Success,
+    Warning,
+    Error,
+    Special,
+    Info,
+    /// The done part of a progress bar
+    #[serde(skip)]
+    ProgressFill,
+    /// The part of a progress bar that is still to go
+    #[serde(skip)]
+    ProgressEmpty,
+}
+
+impl Tone {
+    /// Severity implied by text in this tone
+    fn severity(self) -> Severity {
+        match self {
+            Tone::Success => Severity::Success,
+            Tone::Warning => Severity::Warning,
+            Tone::Error => Severity::Error,
+            _ => Severity::Info,
+        }
+    }
+}
+
+/// How important a line is, independent of how it is colored
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Success,
+    Warning,
+    Error,
+}
+
+/// A run of text in a single tone
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub text: String,
+    pub tone: Tone,
+    pub bold: bool,
+}
+
+/// Simulated kernel timestamp printed in front of a log line
+#[derive(Clone, Copy, Debug)]
+pub struct Stamp {
+    pub seconds: f64,
+    pub tone: Tone,
+}
+
+/// One line of output, built from spans
+#[derive(Clone, Debug)]
+pub struct Line {
+    pub stamp: Option<Stamp>,
+    pub spans: Vec<Span>,
+    pub severity: Severity,
+    stamp_tone: Tone,
+}
+
+impl Line {
+    pub fn new() -> Self {
+        Self {
+            stamp: None,
+            spans: Vec::new(),
+            severity: Severity::Info,
+            stamp_tone: Tone::Dim,
+        }
+    }
+
+    /// Creates a line with a single span
+    pub fn styled(tone: Tone, text: impl Display) -> Self {
+        Self::new().with(tone, text)
+    }
+
+    /// Appends a span, raising the severity if the tone calls for it
+    pub fn with(mut self, tone: Tone, text: impl Display) -> Self {
+        self.severity = self.severity.max(tone.severity());
+        self.spans.push(Span {
+            text: text.to_string(),
+            tone,
+            bold: false,
+        });
+        self
+    }
+
+    /// Makes the last span bold
+    pub fn bold(mut self) -> Self {
+        if let Some(span) = self.spans.last_mut() {
+            span.bold = true;
+        }
+        self
+    }
+
+    /// Colors the timestamp this line gets when it is logged
+    pub fn stamp_tone(mut self, tone: Tone) -> Self {
+        self.stamp_tone = tone;
+        self.severity = self.severity.max(tone.severity());
+        self
+    }
+
+    pub fn stamped(mut self, seconds: f64) -> Self {
+        self.stamp = Some(Stamp {
+            seconds,
+            tone: self.stamp_tone,
+        });
+        self
+    }
+
+    /// Columns the line takes up, timestamp included
+    pub fn width(&self) -> usize {
+        let stamp = self.stamp.map_or(0, |stamp| {
+            layout::width(&LogGenerator::format_timestamp(stamp.seconds)) + 1
+        });
+        stamp
+            + self
+                .spans
+                .iter()
+                .map(|span| layout::width(&span.text))
+                .sum::<usize>()
+    }
+
+    /// The line cut down to `max` columns, ending in an ellipsis if anything was cut
+    pub fn truncate(&self, max: usize) -> Line {
+        if self.width() <= max {
+            return self.clone();
+        }
+        let mut line = Line {
+            spans: Vec::new(),
+            ..self.clone()
+        };
+        let mut remaining = max;
+        if let Some(stamp) = self.stamp {
+            let stamp_width = layout::width(&LogGenerator::format_timestamp(stamp.seconds)) + 1;
+            if stamp_width >= remaining {
+                // Too narrow for the timestamp, keep what fits of the text instead
+                line.stamp = None;
+            } else {
+                remaining -= stamp_width;
+            }
+        }
+        // Whatever fits before the ellipsis, which takes the tone of the span it cuts
+        let mut left = remaining.saturating_sub(1);
+        for span in &self.spans {
+            let text = layout::take_width(&span.text, left).to_string();
+            left -= layout::width(&text);
+            let whole = text.len() == span.text.len();
+            line.spans.push(Span {
+                text,
+                ..span.clone()
+            });
+            if !whole {
+                break;
+            }
+        }
+        if let Some(last) = line.spans.last_mut().filter(|_| remaining > 0) {
+            last.text.push('…');
+        }
+        line
+    }
+}
+
+impl Default for Line {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A frame of a progress bar
+pub struct Progress<'a> {
+    pub label: &'a Line,
+    pub fraction: f32,
+    pub style: ProgressStyle,
+    /// Width of the bar, or `None` to fit it to the line
+    pub width: Option<usize>,
+    /// Extra text drawn after the bar, such as a byte count
+    pub detail: Option<&'a str>,
+    /// Counts shown by the styles that copy a tool's output
+    pub transfer: Option<Transfer>,
+}
+
+/// Everything the installer shows, as structured events
+pub enum Event<'a> {
+    /// Wipe the screen before the installer starts
+    Clear,
+    /// A new pass over the selected stages, listed in the order they will run
+    CycleStart {
+        number: u32,
+        stages: &'a [&'a str],
+    },
+    /// Every selected stage has run
+    CycleEnd {
+        number: u32,
+    },
+    StageStart {
+        name: &'a str,
+        tone: Tone,
+    },
+    StageEnd {
+        name: &'a str,
+    },
+    /// A complete line
+    Line(&'a Line),
+    /// A line about trying again after something failed
+    Retry(&'a Line),
+    /// An empty line
+    Blank,
+    /// The start of a line whose outcome is printed later with `Resolve`
+    Pending(&'a Line),
+    /// The rest of the line started by `Pending`
+    Resolve(&'a Line),
+    /// A status line that is replaced by whatever comes next
+    Transient(&'a Line),
+    Progress(Progress<'a>),
+    /// The bar has finished, optionally followed by a verdict such as "OK"
+    ProgressEnd(Option<&'a Line>),
+    Spinner {
+        frame: &'a str,
+        message: &'a str,
+    },
+    SpinnerEnd {
+        message: &'a str,
+    },
+    /// Lines that are redrawn in place as a group, each `Block` replacing the
+    /// previous one; other output goes above them
+    Block(&'a [Line]),
+    /// The last `Block` stays on screen as it is
+    BlockEnd,
+    /// A question waiting for the user's answer
+    Prompt(&'a Line),
+    /// The run was paused or resumed, output should stay frozen in between
+    Paused(bool),
+    /// The list of hotkeys and what they do was opened, or closed with `None`
+    Help(Option<&'a [(String, &'static str)]>),
+}
+
+/// Presents installer events to the user
+pub trait Renderer {
+    fn render(&mut self, event: &Event) -> io::Result<()>;
+
+    /// Columns available for a line of output
+    fn columns(&self) -> usize {
+        terminal_width()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate_line() {
+        let line = Line::styled(Tone::Plain, "Unpacking ")
+            .with(Tone::Bright, "libgtk-2.0-0")
+            .stamped(0.5);
+        assert_eq!(line.width(), 15 + 22);
+        assert_eq!(line.truncate(40).spans.len(), 2);
+
+        let cut = line.truncate(30);
+        assert_eq!(cut.width(), 30);
+        assert!(cut.stamp.is_some());
+        assert_eq!(cut.spans[1].text, "libg…");
+        assert_eq!(cut.spans[1].tone, Tone::Bright);
+
+        // Too narrow for the timestamp
+        let cut = line.truncate(8);
+        assert!(cut.stamp.is_none());
+        assert_eq!(cut.spans[0].text, "Unpacki…");
+    }
+}
@@ -0,0 +1,186 @@
+use super::{Event, Line, Renderer, Theme, Tone};
+use crate::log_generator::LogGenerator;
+use crate::ui::{ProgressBar, ProgressStyle};
+use std::io::{self, Write};
+
+/// Width lines are laid out for, a file or pipe has no width of its own
+const COLUMNS: usize = 80;
+
+/// What is waiting for the event that decides how it ends
+enum Unfinished {
+    None,
+    /// The latest frame of a progress bar, in its style
+    Bar(Line, ProgressStyle),
+    /// The latest frame of a block
+    Block(Vec<Line>),
+}
+
+/// Renders events as lines that are never redrawn, for logs and pipes
+///
+/// Nothing goes back over what was written: a progress bar or block is
+/// written once, as its last frame, spinners only leave their message and
+/// status lines that a terminal would wipe are left out. Colors come from the
+/// theme, which paints nothing unless colors are on.
+pub struct PlainRenderer<W: Write> {
+    out: W,
+    theme: Theme,
+    unfinished: Unfinished,
+}
+
+impl<W: Write> PlainRenderer<W> {
+    pub fn new(out: W, theme: Theme) -> Self {
+        Self {
+            out,
+            theme,
+            unfinished: Unfinished::None,
+        }
+    }
+
+    fn write_line(&mut self, line: &Line) -> io::Result<()> {
+        if let Some(stamp) = line.stamp {
+            let stamp_text = LogGenerator::format_timestamp(stamp.seconds);
+            write!(
+                self.out,
+                "{} ",
+                self.theme.paint(&stamp_text, stamp.tone, false)
+            )?;
+        }
+        for span in &line.spans {
+            write!(
+                self.out,
+                "{}",
+                self.theme.paint(&span.text, span.tone, span.bold)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Renderer for PlainRenderer<W> {
+    fn render(&mut self, event: &Event) -> io::Result<()> {
+        // A bar that is not ended is wiped on a terminal, so it is never written
+        if !matches!(
+            event,
+            Event::Progress(_) | Event::ProgressEnd(_) | Event::Paused(_)
+        ) && matches!(self.unfinished, Unfinished::Bar(..))
+        {
+            self.unfinished = Unfinished::None;
+        }
+
+        match event {
+            Event::StageStart { name, tone } => {
+                writeln!(self.out)?;
+                writeln!(
+                    self.out,
+                    "{}",
+                    self.theme.paint(&format!("> {}", name), *tone, true)
+                )?;
+                writeln!(self.out)?;
+            }
+            Event::Line(line) | Event::Resolve(line) | Event::Retry(line) => {
+                self.write_line(line)?;
+                writeln!(self.out)?;
+            }
+            Event::Blank => writeln!(self.out)?,
+            Event::Pending(line) | Event::Prompt(line) => self.write_line(line)?,
+            Event::Progress(progress) => {
+                let frame = ProgressBar::frame(progress, self.columns());
+                self.unfinished = Unfinished::Bar(frame, progress.style);
+            }
+            Event::ProgressEnd(verdict) => {
+                if let Unfinished::Bar(frame, style) =
+                    std::mem::replace(&mut self.unfinished, Unfinished::None)
+                {
+                    // A terminal would be left with nothing
+                    if style.is_wiped() && verdict.is_none() {
+                        return self.out.flush();
+                    }
+                    self.write_line(&frame)?;
+                }
+                if let Some(verdict) = verdict {
+                    write!(self.out, " ")?;
+                    self.write_line(verdict)?;
+                }
+                writeln!(self.out)?;
+            }
+            Event::SpinnerEnd { message } => {
+                writeln!(
+                    self.out,
+                    "  {}",
+                    self.theme.paint(message, Tone::Bright, false)
+                )?;
+            }
+            Event::Block(lines) => self.unfinished = Unfinished::Block(lines.to_vec()),
+            Event::BlockEnd => {
+                if let Unfinished::Block(lines) =
+                    std::mem::replace(&mut self.unfinished, Unfinished::None)
+                {
+                    for line in &lines {
+                        self.write_line(line)?;
+                        writeln!(self.out)?;
+                    }
+                }
+            }
+            Event::Clear
+            | Event::CycleStart { .. }
+            | Event::CycleEnd { .. }
+            | Event::StageEnd { .. }
+            | Event::Transient(_)
+            | Event::Spinner { .. }
+            | Event::Paused(_)
+            | Event::Help(_) => {}
+        }
+
+        self.out.flush()
+    }
+
+    fn columns(&self) -> usize {
+        COLUMNS
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Progress;
+
+    #[test]
+    fn test_one_line_per_bar() {
+        let mut out = Vec::new();
+        let mut renderer = PlainRenderer::new(&mut out, Theme::preset("no-color").unwrap());
+        let label = Line::styled(Tone::Plain, "Flashing");
+        for frame in ["|", "/", "-"] {
+            renderer
+                .render(&Event::Spinner {
+                    frame,
+                    message: "Waiting",
+                })
+                .unwrap();
+        }
+        for i in 0..=10 {
+            renderer
+                .render(&Event::Progress(Progress {
+                    label: &label,
+                    fraction: i as f32 / 10.0,
+                    style: ProgressStyle::Hash,
+                    width: Some(10),
+                    detail: None,
+                    transfer: None,
+                }))
+                .unwrap();
+        }
+        renderer.render(&Event::ProgressEnd(None)).unwrap();
+        renderer
+            .render(&Event::Transient(&Line::styled(Tone::Plain, "Starting...")))
+            .unwrap();
+        renderer
+            .render(&Event::SpinnerEnd { message: "Waited" })
+            .unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert!(!text.contains('\r') && !text.contains('\x1b'), "{:?}", text);
+        assert_eq!(text.lines().count(), 2, "{:?}", text);
+        assert!(text.starts_with("Flashing [##########]"), "{:?}", text);
+        assert_eq!(text.lines().last(), Some("  Waited"));
+    }
+}
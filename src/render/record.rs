@@ -0,0 +1,121 @@
+use crate::clock::Clock;
+use serde_json::json;
+use std::io::{self, Write};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Writes terminal output as an asciicast v2 recording
+///
+/// Everything written between two flushes becomes one output event, stamped
+/// with the clock's elapsed time rather than the wall clock, so recordings
+/// made with an instant clock play back at the speed of a live run.
+pub struct CastWriter<W: Write> {
+    out: W,
+    clock: Clock,
+    pending: Vec<u8>,
+}
+
+impl<W: Write> CastWriter<W> {
+    /// Starts a recording of a terminal of the given size
+    pub fn new(mut out: W, clock: Clock, (width, height): (u16, u16)) -> io::Result<Self> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let header = json!({
+            "version": 2,
+            "width": width,
+            "height": height,
+            "timestamp": timestamp,
+            "env": { "TERM": "xterm-256color" },
+        });
+        writeln!(out, "{}", header)?;
+
+        Ok(Self {
+            out,
+            clock,
+            pending: Vec::new(),
+        })
+    }
+}
+
+impl<W: Write> Write for CastWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() {
+            let seconds = self.clock.elapsed().as_micros() as f64 / 1_000_000.0;
+            // The recording is replayed without a tty, so add the carriage
+            // returns the terminal driver would have added to each newline
+            let data = String::from_utf8_lossy(&self.pending)
+                .replace("\r\n", "\n")
+                .replace('\n', "\r\n");
+            writeln!(self.out, "{}", json!([seconds, "o", data]))?;
+            self.pending.clear();
+        }
+        self.out.flush()
+    }
+}
+
+impl<W: Write> Drop for CastWriter<W> {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+/// Sends everything written to two writers
+pub struct Tee<A: Write, B: Write> {
+    first: A,
+    second: B,
+}
+
+impl<A: Write, B: Write> Tee<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+impl<A: Write, B: Write> Write for Tee<A, B> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.first.write_all(buf)?;
+        self.second.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.first.flush()?;
+        self.second.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_use_clock_time() {
+        let clock = Clock::instant();
+        let mut buf = Vec::new();
+        {
+            let mut cast = CastWriter::new(&mut buf, clock.clone(), (80, 24)).unwrap();
+            write!(cast, "hello ").unwrap();
+            write!(cast, "world").unwrap();
+            cast.flush().unwrap();
+            clock.sleep(1500);
+            writeln!(cast).unwrap();
+        }
+
+        let text = String::from_utf8(buf).unwrap();
+        let lines: Vec<serde_json::Value> = text
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0]["version"], 2);
+        assert_eq!(lines[0]["width"], 80);
+        assert_eq!(lines[1], json!([0.0, "o", "hello world"]));
+        assert_eq!(lines[2], json!([1.5, "o", "\r\n"]));
+    }
+}
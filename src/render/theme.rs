@@ -0,0 +1,352 @@
+use super::Tone;
+use crate::config::ThemeConfig;
+use colored::*;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::str::FromStr;
+
+/// Names of the built-in themes, the first one is used unless another is picked
+pub const PRESETS: [&str; 6] = [
+    "default",
+    "amber",
+    "green",
+    "ibm",
+    "high-contrast",
+    "no-color",
+];
+
+/// Styles of a preset, one per tone in declaration order
+fn preset_styles(name: &str) -> Option<[&'static str; ROLES]> {
+    let styles = match name {
+        "default" => [
+            "",
+            "dim",
+            "bright_white",
+            "bright_yellow",
+            "cyan",
+            "bright_cyan",
+            "bright_green",
+            "yellow",
+            "bright_red",
+            "bright_magenta",
+            "bright_blue",
+            "bright_green",
+            "dim",
+        ],
+        "amber" => [
+            "#ffb000",
+            "#a87400",
+            "#ffd27a",
+            "#ffc640 bold",
+            "#e69a00",
+            "#ffd27a",
+            "#ffc640",
+            "#ffb000 bold",
+            "#ffe0a0 bold underline",
+            "#ffc640",
+            "#e69a00",
+            "#ffb000",
+            "#5c4000",
+        ],
+        "green" => [
+            "#33ff33",
+            "#1a9e1a",
+            "#b3ffb3",
+            "#66ff66 bold",
+            "#2bd92b",
+            "#99ff99",
+            "#66ff66",
+            "#33ff33 bold",
+            "#ccffcc bold underline",
+            "#80ff80",
+            "#2bd92b",
+            "#33ff33",
+            "#0f5f0f",
+        ],
+        "ibm" => [
+            "#6fa8ff",
+            "#3a5f9e",
+            "#d0e4ff",
+            "#a8ccff bold",
+            "#5a96f0",
+            "#c0d8ff",
+            "#a8ccff",
+            "#6fa8ff bold",
+            "#ffffff bold underline",
+            "#94bfff",
+            "#5a96f0",
+            "#6fa8ff",
+            "#2a4370",
+        ],
+        "high-contrast" => [
+            "bright_white",
+            "white",
+            "bright_white bold",
+            "bright_yellow bold",
+            "bright_cyan",
+            "bright_cyan bold",
+            "bright_green bold",
+            "bright_yellow bold",
+            "bright_red bold",
+            "bright_magenta bold",
+            "bright_cyan",
+            "bright_white",
+            "white",
+        ],
+        "no-color" => [""; ROLES],
+        _ => return None,
+    };
+    Some(styles)
+}
+
+/// Number of tones, each one gets a style
+const ROLES: usize = 13;
+
+/// How text in one role looks: a color and text attributes
+///
+/// Written as space-separated words, e.g. `bright_yellow bold` or
+/// `#ffb000 underline`. An empty string leaves the text as it is.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Style {
+    color: Option<Color>,
+    bold: bool,
+    dim: bool,
+    italic: bool,
+    underline: bool,
+    reverse: bool,
+}
+
+impl Style {
+    pub fn apply(&self, text: &str, bold: bool) -> ColoredString {
+        let mut styled = text.normal();
+        if let Some(color) = self.color {
+            styled = styled.color(color);
+        }
+        if self.bold || bold {
+            styled = styled.bold();
+        }
+        if self.dim {
+            styled = styled.dimmed();
+        }
+        if self.italic {
+            styled = styled.italic();
+        }
+        if self.underline {
+            styled = styled.underline();
+        }
+        if self.reverse {
+            styled = styled.reversed();
+        }
+        styled
+    }
+}
+
+impl FromStr for Style {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, String> {
+        let mut style = Style::default();
+        for word in value.split_whitespace() {
+            match word {
+                "bold" => style.bold = true,
+                "dim" => style.dim = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                "reverse" => style.reverse = true,
+                _ => style.color = Some(parse_color(word)?),
+            }
+        }
+        Ok(style)
+    }
+}
+
+impl TryFrom<String> for Style {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, String> {
+        value.parse()
+    }
+}
+
+/// Parses a color name such as `bright_cyan`, or a `#rrggbb` hex color
+fn parse_color(word: &str) -> Result<Color, String> {
+    let invalid = || {
+        format!(
+            "unknown style `{}`, expected a color such as `cyan`, `bright_red` or `#ffb000`, \
+             or one of bold, dim, italic, underline and reverse",
+            word
+        )
+    };
+
+    if let Some(hex) = word.strip_prefix('#') {
+        if hex.len() != 6 {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        return Ok(Color::TrueColor {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        });
+    }
+    word.replace('_', " ").parse().map_err(|_| invalid())
+}
+
+/// Maps every tone to a style
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Theme {
+    styles: [Style; ROLES],
+}
+
+impl Theme {
+    /// A built-in theme by name, see `PRESETS`
+    pub fn preset(name: &str) -> Option<Self> {
+        let styles = preset_styles(name)?;
+        Some(Self {
+            styles: styles.map(|style| style.parse().expect("preset styles are valid")),
+        })
+    }
+
+    /// The theme picked with `--theme`, looked up in the config file's
+    /// `[themes]` first and then in the presets
+    ///
+    /// Without a name, `NO_COLOR` selects `no-color` if `follow_no_color` is
+    /// set, and otherwise `default` is used, customized by `[themes.default]`
+    /// if the config file has it. Colors asked for explicitly, such as with
+    /// `--color always`, do not follow `NO_COLOR`.
+    pub fn select(
+        name: Option<&str>,
+        custom: &BTreeMap<String, ThemeConfig>,
+        follow_no_color: bool,
+    ) -> Result<Self, String> {
+        let name = match name {
+            Some(name) => name,
+            None if follow_no_color => Self::default_name(),
+            None => PRESETS[0],
+        };
+
+        let Some(config) = custom.get(name) else {
+            return Self::preset(name).ok_or_else(|| {
+                let mut names: Vec<&str> = PRESETS.to_vec();
+                names.extend(custom.keys().map(String::as_str));
+                format!(
+                    "unknown theme `{}`, expected one of {}",
+                    name,
+                    names.join(", ")
+                )
+            });
+        };
+
+        let base = config.base.as_deref().unwrap_or(match Self::preset(name) {
+            Some(_) => name,
+            None => PRESETS[0],
+        });
+        let mut theme = Self::preset(base).ok_or_else(|| {
+            format!(
+                "theme `{}` is based on `{}`, which is not a built-in theme ({})",
+                name,
+                base,
+                PRESETS.join(", ")
+            )
+        })?;
+        for (index, style) in config.styles().into_iter().enumerate() {
+            if let Some(style) = style {
+                theme.styles[index] = style;
+            }
+        }
+        Ok(theme)
+    }
+
+    /// The preset used when no theme is picked
+    pub fn from_env() -> Self {
+        Self::preset(Self::default_name()).expect("the default presets exist")
+    }
+
+    fn default_name() -> &'static str {
+        if no_color() {
+            "no-color"
+        } else {
+            PRESETS[0]
+        }
+    }
+
+    /// Applies the style of `tone` to `text`
+    pub fn paint(&self, text: &str, tone: Tone, bold: bool) -> ColoredString {
+        self.styles[tone as usize].apply(text, bold)
+    }
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::preset(PRESETS[0]).expect("the default preset exists")
+    }
+}
+
+/// Whether the user asked for no colors, see https://no-color.org
+fn no_color() -> bool {
+    env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_style() {
+        let style: Style = "#ffb000 bold underline".parse().unwrap();
+        assert_eq!(
+            style.color,
+            Some(Color::TrueColor {
+                r: 0xff,
+                g: 0xb0,
+                b: 0x00
+            })
+        );
+        assert!(style.bold && style.underline && !style.dim);
+        assert_eq!(
+            "bright_cyan".parse::<Style>().unwrap().color,
+            Some(Color::BrightCyan)
+        );
+        assert_eq!("".parse::<Style>().unwrap(), Style::default());
+        assert!("blinking".parse::<Style>().is_err());
+        assert!("#ffb0".parse::<Style>().is_err());
+    }
+
+    #[test]
+    fn test_presets_are_valid() {
+        for name in PRESETS {
+            assert!(Theme::preset(name).is_some(), "{}", name);
+        }
+        assert_eq!(
+            Theme::preset("no-color").unwrap().styles,
+            [Style::default(); ROLES]
+        );
+    }
+
+    #[test]
+    fn test_custom_theme() {
+        let config: ThemeConfig = toml::from_str(
+            r##"
+            base = "amber"
+            error = "bright_red bold"
+            "##,
+        )
+        .unwrap();
+        let custom = BTreeMap::from([("alarm".to_string(), config)]);
+
+        let theme = Theme::select(Some("alarm"), &custom, true).unwrap();
+        let amber = Theme::preset("amber").unwrap();
+        assert_eq!(
+            theme.styles[Tone::Header as usize],
+            amber.styles[Tone::Header as usize]
+        );
+        assert_eq!(
+            theme.styles[Tone::Error as usize],
+            "bright_red bold".parse().unwrap()
+        );
+
+        assert!(Theme::select(Some("sepia"), &custom, true).is_err());
+    }
+}
@@ -0,0 +1,390 @@
+use super::{Event, Line, Progress, Renderer, Theme, Tone};
+use crate::clock::{format_hms, Clock};
+use crate::log_generator::LogGenerator;
+use crate::ui::{ProgressBar, ProgressStyle};
+use crossterm::{
+    cursor, execute, queue,
+    terminal::{self, ClearType},
+};
+use std::collections::VecDeque;
+use std::io::{self, Write};
+
+/// Log lines kept for the log pane, older ones are dropped
+const LOG_CAPACITY: usize = 500;
+/// Width of the stage list, including its border
+const STAGE_PANEL_WIDTH: u16 = 34;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Pending,
+    Running,
+    Done,
+}
+
+/// What is being drawn on the last line of the log pane
+enum Live {
+    None,
+    /// A line waiting for its `Resolve`
+    Pending(Line),
+    /// A line that the next event replaces
+    Transient(Line),
+    /// Lines that stay below the log until the block ends
+    Block(Vec<Line>),
+}
+
+/// Full-screen dashboard on the terminal's alternate screen
+///
+/// Keeps its own copy of the stage list and recent log lines and redraws the
+/// whole layout after every event, sized to the terminal at that moment.
+pub struct TuiRenderer<W: Write> {
+    out: W,
+    clock: Clock,
+    theme: Theme,
+    cycle: u32,
+    stages: Vec<(String, Status)>,
+    log: VecDeque<Line>,
+    live: Live,
+    /// Style of the current progress bar, until it ends
+    bar: Option<ProgressStyle>,
+    paused: bool,
+    /// Hotkeys shown over everything else while the help is open
+    help: Option<Vec<(String, &'static str)>>,
+}
+
+impl<W: Write> TuiRenderer<W> {
+    /// Switches the terminal to the alternate screen, which is left again on drop
+    pub fn new(mut out: W, clock: Clock, theme: Theme) -> io::Result<Self> {
+        execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+        Ok(Self {
+            out,
+            clock,
+            theme,
+            cycle: 0,
+            stages: Vec::new(),
+            log: VecDeque::new(),
+            live: Live::None,
+            bar: None,
+            paused: false,
+            help: None,
+        })
+    }
+
+    fn push(&mut self, line: Line) {
+        if self.log.len() == LOG_CAPACITY {
+            self.log.pop_front();
+        }
+        self.log.push_back(line);
+    }
+
+    fn set_status(&mut self, name: &str, status: Status) {
+        if let Some(stage) = self.stages.iter_mut().find(|(stage, _)| stage == name) {
+            stage.1 = status;
+        }
+    }
+
+    fn update(&mut self, event: &Event) {
+        let replaced = match std::mem::replace(&mut self.live, Live::None) {
+            Live::Transient(line) => Some(line),
+            live => {
+                self.live = live;
+                None
+            }
+        };
+
+        match event {
+            Event::Clear => self.log.clear(),
+            Event::CycleStart { number, stages } => {
+                self.cycle = *number;
+                self.stages = stages
+                    .iter()
+                    .map(|name| (name.to_string(), Status::Pending))
+                    .collect();
+            }
+            Event::StageStart { name, tone } => {
+                self.set_status(name, Status::Running);
+                self.push(Line::new());
+                self.push(Line::styled(*tone, format!("> {}", name)).bold());
+                self.push(Line::new());
+            }
+            Event::StageEnd { name } => self.set_status(name, Status::Done),
+            Event::CycleEnd { .. } => {}
+            Event::Line(line) | Event::Resolve(line) | Event::Retry(line) => {
+                let line = match std::mem::replace(&mut self.live, Live::None) {
+                    Live::Pending(mut pending) => {
+                        pending.spans.extend(line.spans.iter().cloned());
+                        pending
+                    }
+                    live => {
+                        self.live = live;
+                        (*line).clone()
+                    }
+                };
+                self.push(line);
+            }
+            Event::Blank => self.push(Line::new()),
+            Event::Pending(line) | Event::Prompt(line) => {
+                self.live = Live::Pending((*line).clone());
+            }
+            Event::Transient(line) => self.live = Live::Transient((*line).clone()),
+            Event::Progress(progress) => {
+                self.live = Live::Transient(ProgressBar::frame(progress, self.columns()));
+                self.bar = Some(progress.style);
+            }
+            Event::ProgressEnd(verdict) => {
+                let wiped = self.bar.take().is_some_and(ProgressStyle::is_wiped);
+                if !wiped || verdict.is_some() {
+                    let mut line = replaced.unwrap_or_default();
+                    if let Some(verdict) = verdict {
+                        line = line.with(Tone::Plain, " ");
+                        line.spans.extend(verdict.spans.iter().cloned());
+                    }
+                    self.push(line);
+                }
+            }
+            Event::Spinner { frame, message } => {
+                self.live = Live::Transient(
+                    Line::styled(Tone::Plain, format!("{} ", frame)).with(Tone::Bright, message),
+                );
+            }
+            Event::SpinnerEnd { message } => {
+                self.push(Line::styled(Tone::Plain, "  ").with(Tone::Bright, message));
+            }
+            Event::Block(lines) => self.live = Live::Block(lines.to_vec()),
+            Event::BlockEnd => match std::mem::replace(&mut self.live, Live::None) {
+                Live::Block(lines) => lines.into_iter().for_each(|line| self.push(line)),
+                live => self.live = live,
+            },
+            Event::Paused(paused) => {
+                self.paused = *paused;
+                // Keep whatever was on screen while frozen
+                if let Some(line) = replaced {
+                    self.live = Live::Transient(line);
+                }
+            }
+            Event::Help(hotkeys) => {
+                self.help = hotkeys.map(<[_]>::to_vec);
+                if let Some(line) = replaced {
+                    self.live = Live::Transient(line);
+                }
+            }
+        }
+    }
+
+    /// Writes `line` at the cursor, cut off with an ellipsis after `width` columns
+    fn write_clipped(&mut self, line: &Line, width: usize) -> io::Result<()> {
+        let line = line.truncate(width);
+        if let Some(stamp) = line.stamp {
+            let text = format!("{} ", LogGenerator::format_timestamp(stamp.seconds));
+            write!(self.out, "{}", self.theme.paint(&text, stamp.tone, false))?;
+        }
+        for span in &line.spans {
+            write!(
+                self.out,
+                "{}",
+                self.theme.paint(&span.text, span.tone, span.bold)
+            )?;
+        }
+        Ok(())
+    }
+
+    fn draw(&mut self) -> io::Result<()> {
+        let (width, height) = terminal::size()?;
+        if width < STAGE_PANEL_WIDTH + 10 || height < 8 {
+            queue!(
+                self.out,
+                cursor::MoveTo(0, 0),
+                terminal::Clear(ClearType::All)
+            )?;
+            write!(self.out, "Terminal too small")?;
+            return self.out.flush();
+        }
+
+        let body_top = 2;
+        let body_height = height - 4;
+        let log_left = STAGE_PANEL_WIDTH + 1;
+        let log_width = (width - log_left) as usize;
+
+        // Header
+        let title = "UNIVERSAL SYSTEM INSTALLER v3.2.1 (Build 1999)";
+        let pad = (width as usize).saturating_sub(title.len()) / 2;
+        queue!(
+            self.out,
+            cursor::MoveTo(0, 0),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+        write!(
+            self.out,
+            "{}{}",
+            " ".repeat(pad),
+            self.theme.paint(title, Tone::Bright, true)
+        )?;
+        queue!(self.out, cursor::MoveTo(0, 1))?;
+        write!(
+            self.out,
+            "{}",
+            self.theme
+                .paint(&"═".repeat(width as usize), Tone::Highlight, false)
+        )?;
+
+        // Stage list and log pane
+        let mut tail: Vec<&Line> = self.log.iter().collect();
+        let live = match &self.live {
+            Live::None => Vec::new(),
+            Live::Pending(line) | Live::Transient(line) => vec![line.clone()],
+            Live::Block(lines) => lines.clone(),
+        };
+        tail.extend(&live);
+        let skip = tail.len().saturating_sub(body_height as usize);
+        let visible: Vec<Line> = tail[skip..].iter().map(|line| (*line).clone()).collect();
+
+        for row in 0..body_height {
+            queue!(
+                self.out,
+                cursor::MoveTo(0, body_top + row),
+                terminal::Clear(ClearType::CurrentLine)
+            )?;
+
+            if let Some((name, status)) = self.stages.get(row as usize) {
+                let (marker, tone) = match status {
+                    Status::Done => ("[✓]", Tone::Success),
+                    Status::Running => ("[>]", Tone::Header),
+                    Status::Pending => ("[ ]", Tone::Dim),
+                };
+                let label: String = name.chars().take(STAGE_PANEL_WIDTH as usize - 6).collect();
+                write!(
+                    self.out,
+                    " {} {}",
+                    self.theme.paint(marker, tone, false),
+                    self.theme.paint(&label, tone, *status == Status::Running)
+                )?;
+            }
+
+            queue!(
+                self.out,
+                cursor::MoveTo(STAGE_PANEL_WIDTH - 1, body_top + row)
+            )?;
+            write!(self.out, "{}", self.theme.paint("│", Tone::Dim, false))?;
+
+            if let Some(line) = visible.get(row as usize) {
+                queue!(self.out, cursor::MoveTo(log_left, body_top + row))?;
+                self.write_clipped(line, log_width)?;
+            }
+        }
+
+        // Cycle gauge
+        let done = self
+            .stages
+            .iter()
+            .filter(|(_, status)| *status == Status::Done)
+            .count();
+        let total = self.stages.len().max(1);
+        let fraction = done as f32 / total as f32;
+        let gauge_label = Line::styled(Tone::Bright, " Cycle progress");
+        let detail = format!("({}/{} stages)", done, self.stages.len());
+        let bar_width = (width as usize).saturating_sub(42).min(60);
+        let gauge = ProgressBar::frame(
+            &Progress {
+                label: &gauge_label,
+                fraction,
+                style: ProgressStyle::Block,
+                width: Some(bar_width),
+                detail: Some(&detail),
+                transfer: None,
+            },
+            width as usize,
+        );
+        queue!(
+            self.out,
+            cursor::MoveTo(0, height - 2),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+        self.write_clipped(&gauge, width as usize)?;
+
+        // Footer
+        let footer = format!(
+            " Elapsed {} │ Cycle #{} │ Speed x{} │ {}",
+            format_hms(self.clock.elapsed()),
+            self.cycle,
+            self.clock.speed(),
+            if self.paused {
+                "PAUSED, space to resume"
+            } else {
+                "? for help"
+            }
+        );
+        let footer: String = footer.chars().take(width as usize).collect();
+        queue!(
+            self.out,
+            cursor::MoveTo(0, height - 1),
+            terminal::Clear(ClearType::CurrentLine)
+        )?;
+        write!(self.out, "{}", self.theme.paint(&footer, Tone::Dim, false))?;
+
+        if let Some(hotkeys) = self.help.clone() {
+            self.draw_help(&hotkeys, width, height)?;
+        }
+
+        self.out.flush()
+    }
+
+    /// Draws the hotkey list in a box in the middle of the screen
+    fn draw_help(&mut self, hotkeys: &[(String, &str)], width: u16, height: u16) -> io::Result<()> {
+        let inner = 36;
+        let left = width.saturating_sub(inner as u16 + 2) / 2;
+        let top = height.saturating_sub(hotkeys.len() as u16 + 4) / 2;
+
+        let mut rows = vec![format!("{:^inner$}", "Hotkeys"), " ".repeat(inner)];
+        for (key, action) in hotkeys {
+            rows.push(format!(" {:>5}  {:<w$}", key, action, w = inner - 8));
+        }
+
+        queue!(self.out, cursor::MoveTo(left, top))?;
+        write!(
+            self.out,
+            "{}",
+            self.theme
+                .paint(&format!("┌{}┐", "─".repeat(inner)), Tone::Highlight, false)
+        )?;
+        for (i, row) in rows.iter().enumerate() {
+            queue!(self.out, cursor::MoveTo(left, top + 1 + i as u16))?;
+            write!(
+                self.out,
+                "{}{}{}",
+                self.theme.paint("│", Tone::Highlight, false),
+                self.theme.paint(row, Tone::Bright, i == 0),
+                self.theme.paint("│", Tone::Highlight, false)
+            )?;
+        }
+        queue!(self.out, cursor::MoveTo(left, top + 1 + rows.len() as u16))?;
+        write!(
+            self.out,
+            "{}",
+            self.theme
+                .paint(&format!("└{}┘", "─".repeat(inner)), Tone::Highlight, false)
+        )?;
+        Ok(())
+    }
+}
+
+impl<W: Write> Renderer for TuiRenderer<W> {
+    fn render(&mut self, event: &Event) -> io::Result<()> {
+        if let Event::Clear = event {
+            queue!(self.out, terminal::Clear(ClearType::All))?;
+        }
+        self.update(event);
+        self.draw()
+    }
+
+    /// Width of the log pane
+    fn columns(&self) -> usize {
+        let (width, _) = terminal::size().unwrap_or((80, 24));
+        width.saturating_sub(STAGE_PANEL_WIDTH + 1) as usize
+    }
+}
+
+impl<W: Write> Drop for TuiRenderer<W> {
+    fn drop(&mut self) {
+        let _ = execute!(self.out, cursor::Show, terminal::LeaveAlternateScreen);
+    }
+}
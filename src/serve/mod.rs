@@ -0,0 +1,225 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::Duration;
+
+/// The page that shows the installer, with its script and styles inline
+const PAGE: &str = include_str!("index.html");
+/// Lines a browser that joins late gets to see, older ones are dropped
+const BACKLOG: usize = 500;
+/// How often an idle stream sends a comment, which finds browsers that left
+const KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// A change to what the page shows
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Update {
+    /// Wipe the log
+    Clear,
+    /// A stage has started
+    Stage(String),
+    /// A finished line, as HTML
+    Line(String),
+    /// The lines being redrawn below the log, such as a progress bar,
+    /// replacing the previous ones
+    Live(Vec<String>),
+}
+
+impl Update {
+    /// The update as a Server-Sent Event
+    fn to_sse(&self) -> String {
+        let (name, data) = match self {
+            Update::Clear => ("clear", String::new()),
+            Update::Stage(name) => ("stage", name.clone()),
+            Update::Line(html) => ("line", html.clone()),
+            Update::Live(lines) => ("live", lines.join("\n")),
+        };
+        let mut event = format!("event: {}\n", name);
+        // Every line of the data gets its own field, the browser joins them again
+        for line in data.split('\n') {
+            event.push_str("data: ");
+            event.push_str(line);
+            event.push('\n');
+        }
+        event.push('\n');
+        event
+    }
+}
+
+#[derive(Default)]
+struct Shared {
+    stage: Option<String>,
+    log: VecDeque<String>,
+    live: Vec<String>,
+    browsers: Vec<Sender<Update>>,
+}
+
+/// The session every browser watches
+///
+/// Keeps the recent output so that a browser joining late starts with the
+/// same screen as the others.
+#[derive(Clone, Default)]
+pub struct Hub {
+    shared: Arc<Mutex<Shared>>,
+}
+
+impl Hub {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Shared> {
+        // A browser thread that panicked leaves nothing half-written behind
+        self.shared.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Sends `update` to every browser
+    pub fn publish(&self, update: Update) {
+        let mut shared = self.lock();
+        match &update {
+            Update::Clear => shared.log.clear(),
+            Update::Stage(name) => shared.stage = Some(name.clone()),
+            Update::Line(html) => {
+                if shared.log.len() == BACKLOG {
+                    shared.log.pop_front();
+                }
+                shared.log.push_back(html.clone());
+            }
+            Update::Live(lines) => shared.live = lines.clone(),
+        }
+        // Browsers that left have dropped their end of the channel
+        shared
+            .browsers
+            .retain(|browser| browser.send(update.clone()).is_ok());
+    }
+
+    /// Starts a stream of updates, beginning with what is on screen now
+    pub fn subscribe(&self) -> Receiver<Update> {
+        let (sender, receiver) = mpsc::channel();
+        let mut shared = self.lock();
+        let mut updates = vec![Update::Clear];
+        updates.extend(shared.stage.clone().map(Update::Stage));
+        updates.extend(shared.log.iter().cloned().map(Update::Line));
+        updates.push(Update::Live(shared.live.clone()));
+        for update in updates {
+            let _ = sender.send(update);
+        }
+        shared.browsers.push(sender);
+        receiver
+    }
+}
+
+/// Serves the page and the stream of `hub` on localhost, in the background
+pub fn listen(port: u16, hub: Hub) -> io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", port))
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot listen on port {}: {}", port, e)))?;
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let hub = hub.clone();
+            thread::spawn(move || {
+                // The browser went away, there is nobody to tell
+                let _ = handle(stream, &hub);
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Answers one request
+fn handle(mut stream: TcpStream, hub: &Hub) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // The headers are not needed, but have to be read before answering
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut words = request.split_whitespace();
+    match (words.next(), words.next()) {
+        (Some("GET"), Some("/" | "/index.html")) => respond(
+            &mut stream,
+            "200 OK",
+            "text/html; charset=utf-8",
+            PAGE.as_bytes(),
+        ),
+        (Some("GET"), Some("/events")) => stream_updates(&mut stream, hub),
+        (Some("GET"), Some(_)) => {
+            respond(&mut stream, "404 Not Found", "text/plain", b"Not found\n")
+        }
+        _ => respond(
+            &mut stream,
+            "405 Method Not Allowed",
+            "text/plain",
+            b"Method not allowed\n",
+        ),
+    }
+}
+
+fn respond(
+    stream: &mut TcpStream,
+    status: &str,
+    content_type: &str,
+    body: &[u8],
+) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        content_type,
+        body.len()
+    )?;
+    stream.write_all(body)?;
+    stream.flush()
+}
+
+/// Sends updates to a browser until it goes away
+fn stream_updates(stream: &mut TcpStream, hub: &Hub) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\n\
+         Connection: keep-alive\r\n\r\n"
+    )?;
+    let updates = hub.subscribe();
+    loop {
+        match updates.recv_timeout(KEEP_ALIVE) {
+            Ok(update) => stream.write_all(update.to_sse().as_bytes())?,
+            Err(RecvTimeoutError::Timeout) => stream.write_all(b": keep-alive\n\n")?,
+            Err(RecvTimeoutError::Disconnected) => return Ok(()),
+        }
+        stream.flush()?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_late_browser_gets_the_screen() {
+        let hub = Hub::new();
+        hub.publish(Update::Line("gone".to_string()));
+        hub.publish(Update::Clear);
+        hub.publish(Update::Stage("Kernel".to_string()));
+        hub.publish(Update::Line("one".to_string()));
+        hub.publish(Update::Live(vec!["[###   ]".to_string()]));
+
+        let updates: Vec<Update> = hub.subscribe().try_iter().collect();
+        assert_eq!(
+            updates,
+            vec![
+                Update::Clear,
+                Update::Stage("Kernel".to_string()),
+                Update::Line("one".to_string()),
+                Update::Live(vec!["[###   ]".to_string()]),
+            ]
+        );
+        assert_eq!(
+            Update::Live(vec!["a".to_string(), "b".to_string()]).to_sse(),
+            "event: live\ndata: a\ndata: b\n\n"
+        );
+    }
+}
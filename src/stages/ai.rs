@@ -1,13 +1,23 @@
 use super::InstallationStage;
 use crate::config::AiConfig;
-use crate::log_generator::LogGenerator;
-use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
+use crate::context::Context;
+use crate::render::{Line, Tone};
+use crate::ui::progress::Transfer;
+use crate::ui::{MultiProgress, ProgressBar, ProgressStyle, Spinner, SpinnerStyle};
 use rand::Rng;
 use std::io;
-use std::thread;
 use std::time::Duration;
 
+/// Size of the model weights, as shown in the log
+const MODEL_SIZE: u64 = 140_000_000_000;
+/// Files the weights are split into
+const SHARDS: u64 = 15;
+/// Files downloaded at the same time
+const DOWNLOAD_WORKERS: usize = 4;
+/// Simulated milliseconds between two frames of the download
+const DOWNLOAD_TICK_MS: u64 = 100;
+
+/// Downloads a 140 GB model and loads it onto a GPU
 pub struct AiStage {
     config: AiConfig,
 }
@@ -18,125 +28,179 @@ impl AiStage {
     }
 }
 
+impl AiStage {
+    /// Downloads the weight files a few at a time, each finished file
+    /// leaving its bar above the ones still going
+    fn download_shards(&self, ctx: &mut Context) -> io::Result<()> {
+        let duration = ctx
+            .rng
+            .gen_range(self.config.model_download_speed_range.clone());
+        // Per file, so that all of them are in after about `duration`
+        let speed = MODEL_SIZE as f64 * 1000.0 / duration as f64 / DOWNLOAD_WORKERS as f64;
+        let shard_size = MODEL_SIZE / SHARDS;
+
+        let mut multi = MultiProgress::new(ProgressStyle::Pip);
+        let mut active: Vec<(usize, Line, Transfer)> = Vec::new();
+        let mut next = 1;
+        loop {
+            while active.len() < DOWNLOAD_WORKERS && next <= SHARDS {
+                let label = Line::styled(
+                    Tone::Plain,
+                    format!("model-{:05}-of-{:05}.safetensors", next, SHARDS),
+                );
+                let task = multi.add(label.clone());
+                active.push((task, label, Transfer::items(0, shard_size)));
+                next += 1;
+            }
+            if active.is_empty() {
+                break;
+            }
+            for (task, label, transfer) in &active {
+                multi.set(*task, label.clone(), Some(*transfer));
+            }
+            multi.draw(ctx);
+
+            if ctx.check_exit() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
+            }
+            ctx.sleep(DOWNLOAD_TICK_MS);
+
+            for (task, label, transfer) in &mut active {
+                let rate = speed * ctx.rng.gen_range(0.6..1.4);
+                transfer.rate = if transfer.rate == 0.0 {
+                    rate
+                } else {
+                    transfer.rate * 0.7 + rate * 0.3
+                };
+                transfer.done = (transfer.done + (rate * DOWNLOAD_TICK_MS as f64 / 1000.0) as u64)
+                    .min(transfer.total);
+                transfer.elapsed += Duration::from_millis(DOWNLOAD_TICK_MS);
+                if transfer.is_done() {
+                    multi.set(*task, label.clone(), Some(*transfer));
+                    multi.complete(ctx, *task);
+                }
+            }
+            active.retain(|(_, _, transfer)| !transfer.is_done());
+        }
+
+        multi.finish(ctx);
+        Ok(())
+    }
+}
+
 impl InstallationStage for AiStage {
     fn name(&self) -> &'static str {
         "AI Model Loading"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_magenta().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
+    fn tone(&self) -> Tone {
+        Tone::Special
+    }
 
-        println!(
-            "{} Initializing HuggingFace Hub client...",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(600));
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        Spinner::with_style(SpinnerStyle::Dots).animate(
+            "Initializing HuggingFace Hub client...",
+            600,
+            ctx,
+        )?;
 
         let model_name = "Llama-3-70B-Instruct-v1";
-        println!(
-            "{} Found model {} (size: 140GB)",
-            LogGenerator::timestamp().dimmed(),
-            model_name.cyan()
+        ctx.log_line(
+            Line::styled(Tone::Plain, "Found model ")
+                .with(Tone::Accent, model_name)
+                .with(Tone::Plain, " (size: 140GB)"),
         );
 
-        if rng.gen_bool(self.config.failure_rate_network) {
-            println!(
-                "{} Error: HuggingFace Hub: 502 Bad Gateway",
-                LogGenerator::timestamp().red()
+        if ctx.rng.gen_bool(self.config.failure_rate_network) {
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Error: HuggingFace Hub: 502 Bad Gateway")
+                    .stamp_tone(Tone::Error),
             );
-            println!(
-                "{} Retrying connection in 3s...",
-                LogGenerator::timestamp().yellow()
-            );
-            thread::sleep(Duration::from_millis(3000));
-            println!(
-                "{} Connection established.",
-                LogGenerator::timestamp().dimmed()
+            ctx.log_retry(
+                Line::styled(Tone::Plain, "Retrying connection in 3s...").stamp_tone(Tone::Warning),
             );
+            ctx.sleep(3000);
+            ctx.log(Tone::Plain, "Connection established.");
+            ctx.stats.recovered_failures += 1;
         }
 
-        println!(
-            "{} Downloading model weights...",
-            LogGenerator::timestamp().dimmed()
+        ctx.log(
+            Tone::Plain,
+            format!("Downloading model weights ({} files)...", SHARDS),
         );
-        let progress = ProgressBar::new(ProgressStyle::Block);
-        progress.animate(
-            "Downloading",
-            rng.gen_range(self.config.model_download_speed_range.clone()),
-            exit_check,
-        )?;
-
-        println!(
-            "{} Verifying SHA256 checksums...",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(
-            rng.gen_range(self.config.checksum_delay_range.clone()),
-        ));
-        if rng.gen_bool(self.config.failure_rate_checksum) {
-            println!(
-                "{} Warning: Checksum mismatch for shard 03, re-downloading...",
-                LogGenerator::timestamp().yellow()
+        self.download_shards(ctx)?;
+        ctx.stats.downloaded_bytes += MODEL_SIZE;
+
+        ctx.log(Tone::Plain, "Verifying SHA256 checksums...");
+        ctx.sleep_range(self.config.checksum_delay_range.clone());
+        if ctx.rng.gen_bool(self.config.failure_rate_checksum) {
+            ctx.log_retry(
+                Line::styled(
+                    Tone::Plain,
+                    "Warning: Checksum mismatch for shard 03, re-downloading...",
+                )
+                .stamp_tone(Tone::Warning),
             );
-            thread::sleep(Duration::from_millis(1000));
+            ctx.sleep(1000);
+            ctx.stats.warnings += 1;
         }
-        println!(
-            "{} Integrity check passed.",
-            LogGenerator::timestamp().dimmed()
-        );
+        ctx.log(Tone::Plain, "Integrity check passed.");
 
-        println!(
-            "{} Initializing CUDA context...",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(500));
+        ctx.log(Tone::Plain, "Initializing CUDA context...");
+        ctx.sleep(500);
 
-        println!(
-            "{} Compiling custom CUDA kernels (FlashAttention-v2)...",
-            LogGenerator::timestamp().dimmed()
+        ctx.log(
+            Tone::Plain,
+            "Compiling custom CUDA kernels (FlashAttention-v2)...",
         );
         let progress = ProgressBar::new(ProgressStyle::Block);
         progress.animate(
             "Compiling",
-            rng.gen_range(self.config.compilation_speed_range.clone()),
-            exit_check,
+            ctx.rng
+                .gen_range(self.config.compilation_speed_range.clone()),
+            ctx,
         )?;
 
-        if rng.gen_bool(self.config.failure_rate_kernel_panic) {
-            println!(
-                "{} Error: illegal memory access in kernel 'fused_rotary_embedding'",
-                LogGenerator::timestamp().red()
+        if ctx.rng.gen_bool(self.config.failure_rate_kernel_panic) {
+            ctx.log_line(
+                Line::styled(
+                    Tone::Plain,
+                    "Error: illegal memory access in kernel 'fused_rotary_embedding'",
+                )
+                .stamp_tone(Tone::Error),
             );
-            println!(
-                "{} Resetting CUDA context and recompiling...",
-                LogGenerator::timestamp().yellow()
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Resetting CUDA context and recompiling...")
+                    .stamp_tone(Tone::Warning),
             );
-            thread::sleep(Duration::from_millis(2000));
+            ctx.sleep(2000);
+            ctx.stats.recovered_failures += 1;
         }
 
-        println!(
-            "{} Allocating tensors...",
-            LogGenerator::timestamp().dimmed()
-        );
+        ctx.log(Tone::Plain, "Allocating tensors...");
 
-        if rng.gen_bool(self.config.failure_rate_oom) {
-            println!(
-                "{} Error: CUDA out of memory. Tried to allocate 24.5GB",
-                LogGenerator::timestamp().red()
+        if ctx.rng.gen_bool(self.config.failure_rate_oom) {
+            ctx.log_line(
+                Line::styled(
+                    Tone::Plain,
+                    "Error: CUDA out of memory. Tried to allocate 24.5GB",
+                )
+                .stamp_tone(Tone::Error),
             );
-            println!(
-                "{} Reducing batch size to 1 and offloading optimizer state...",
-                LogGenerator::timestamp().yellow()
+            ctx.log_line(
+                Line::styled(
+                    Tone::Plain,
+                    "Reducing batch size to 1 and offloading optimizer state...",
+                )
+                .stamp_tone(Tone::Warning),
             );
-            thread::sleep(Duration::from_millis(1500));
+            ctx.sleep(1500);
+            ctx.stats.recovered_failures += 1;
         }
 
         let layers = 12;
         for i in 1..=layers {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
@@ -145,30 +209,20 @@ impl InstallationStage for AiStage {
             } else {
                 "Attention"
             };
-            println!(
-                "{} Loading layer {}/{} ({})...",
-                LogGenerator::timestamp().dimmed(),
-                i,
-                layers,
-                layer_type.cyan()
+            ctx.log_line(
+                Line::styled(Tone::Plain, format!("Loading layer {}/{} (", i, layers))
+                    .with(Tone::Accent, layer_type)
+                    .with(Tone::Plain, ")..."),
             );
 
-            thread::sleep(Duration::from_millis(
-                rng.gen_range(self.config.layer_load_delay_range.clone()),
-            ));
+            ctx.sleep_range(self.config.layer_load_delay_range.clone());
         }
 
-        println!(
-            "{} Model loaded successfully.",
-            LogGenerator::timestamp().dimmed()
-        );
-        println!("{} Quantization: INT8", LogGenerator::timestamp().dimmed());
-        println!(
-            "{} Inference engine ready.",
-            LogGenerator::timestamp().dimmed()
-        );
+        ctx.log(Tone::Plain, "Model loaded successfully.");
+        ctx.log(Tone::Plain, "Quantization: INT8");
+        ctx.log(Tone::Plain, "Inference engine ready.");
 
-        thread::sleep(Duration::from_millis(500));
+        ctx.sleep(500);
         Ok(())
     }
 }
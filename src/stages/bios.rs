@@ -1,14 +1,15 @@
 use super::InstallationStage;
 use crate::config::BiosConfig;
-use crate::ui::{ProgressBar, ProgressStyle, Spinner};
-use chrono::Local;
-use colored::*;
+use crate::context::Context;
+use crate::render::{Event, Line, Tone};
+use crate::ui::layout::{fit_width, framed};
+use crate::ui::{ProgressBar, ProgressStyle, Spinner, SpinnerStyle};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 use rand::Rng;
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use std::io;
 use sysinfo::System;
 
+/// Flashes the BIOS, with a POST screen and hardware detection
 pub struct BiosStage {
     config: BiosConfig,
 }
@@ -67,6 +68,15 @@ impl BiosStage {
             os_name,
         }
     }
+
+    /// A date and time within the year after the default BIOS date, picked
+    /// by the session seed so that a replay shows the same clock
+    fn system_date(ctx: &mut Context) -> NaiveDateTime {
+        let epoch = NaiveDate::from_ymd_opt(2025, 11, 15)
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .unwrap_or_default();
+        epoch + Duration::seconds(ctx.rng.gen_range(0..365 * 24 * 3600))
+    }
 }
 
 impl InstallationStage for BiosStage {
@@ -74,73 +84,53 @@ impl InstallationStage for BiosStage {
         "BIOS/Firmware Update Sequence"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
         let sys_info = Self::get_system_info();
 
-        let now = Local::now();
+        let now = Self::system_date(ctx);
         let bios_serial = format!(
             "{:04X}-{:04X}-{:04X}-{:04X}",
-            rng.gen::<u16>(),
-            rng.gen::<u16>(),
-            rng.gen::<u16>(),
-            rng.gen::<u16>()
+            ctx.rng.gen::<u16>(),
+            ctx.rng.gen::<u16>(),
+            ctx.rng.gen::<u16>(),
+            ctx.rng.gen::<u16>()
         );
 
-        println!(
-            "{}",
-            "╔═══════════════════════════════════════════════════════════════╗".bright_cyan()
-        );
-        println!(
-            "{}",
-            format!("║  {:<61}║", self.config.vendor).bright_cyan()
-        );
-        println!(
-            "{}",
-            format!("║  {:<61}║", self.config.version).bright_cyan()
-        );
-        println!(
-            "{}",
-            "╚═══════════════════════════════════════════════════════════════╝".bright_cyan()
-        );
-        println!();
-        println!(
-            "{}",
-            format!("BIOS Date: {}  S/N: {}", self.config.bios_date, bios_serial).dimmed()
+        let width = fit_width(65, ctx.columns());
+        for line in framed(&[&self.config.vendor, &self.config.version], width) {
+            ctx.print(Tone::Highlight, line);
+        }
+        ctx.blank();
+        ctx.print(
+            Tone::Dim,
+            format!("BIOS Date: {}  S/N: {}", self.config.bios_date, bios_serial),
         );
-        println!(
-            "{}",
+        ctx.print(
+            Tone::Dim,
             format!(
                 "System Date: {}  Time: {}",
                 now.format("%m/%d/%Y"),
                 now.format("%H:%M:%S")
-            )
-            .dimmed()
+            ),
         );
-        println!("{}", format!("System Name: {}", sys_info.hostname).dimmed());
-        thread::sleep(Duration::from_millis(self.config.header_delay));
+        ctx.print(Tone::Dim, format!("System Name: {}", sys_info.hostname));
+        ctx.sleep(self.config.header_delay);
 
-        println!();
-        println!(
-            "{}",
-            "Performing POST (Power-On Self Test)...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(self.config.post_start_delay));
+        ctx.blank();
+        ctx.print(Tone::Bright, "Performing POST (Power-On Self Test)...");
+        ctx.sleep(self.config.post_start_delay);
 
-        let mut spinner = Spinner::new();
+        let mut spinner = Spinner::with_style(SpinnerStyle::Dos);
 
         spinner.animate(
             &format!("CPU: {}", sys_info.cpu_brand),
             self.config.cpu_detect_time,
-            exit_check,
+            ctx,
         )?;
         spinner.animate(
             &format!("CPU Cores: {} physical", sys_info.cpu_count),
             self.config.cpu_cores_time,
-            exit_check,
+            ctx,
         )?;
 
         if sys_info.cpu_freq > 0 {
@@ -148,37 +138,33 @@ impl InstallationStage for BiosStage {
             spinner.animate(
                 &format!("CPU Speed: {:.2} GHz", freq_ghz),
                 self.config.cpu_freq_time,
-                exit_check,
+                ctx,
             )?;
         }
 
-        println!();
+        ctx.blank();
         let memory_mb = sys_info.total_memory_kb / 1024;
         let memory_gb = memory_mb as f64 / 1024.0;
 
-        print!("{}", "Testing Memory: ".bright_white());
-        io::stdout().flush()?;
-
         let mem_progress = ProgressBar::new(ProgressStyle::Hash);
+        let mem_label = Line::styled(Tone::Plain, "Testing Memory:");
         let steps = 40;
         let delay = self.config.memory_test_time / steps;
 
         for i in 0..=steps {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
             let progress = i as f32 / steps as f32;
             let tested_kb = (sys_info.total_memory_kb as f32 * progress) as u64;
-            print!(
-                "\rTesting Memory: {} {}/{} KB",
-                mem_progress.render(progress),
-                tested_kb,
-                sys_info.total_memory_kb
-            );
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(delay));
+            let detail = format!("{}/{} KB", tested_kb, sys_info.total_memory_kb);
+            mem_progress.draw(ctx, &mem_label, progress, Some(&detail));
+            ctx.sleep(delay);
         }
-        println!(" {}", "OK".bright_green());
+        ctx.emit(&Event::ProgressEnd(Some(&Line::styled(
+            Tone::Success,
+            "OK",
+        ))));
 
         spinner.animate(
             &format!(
@@ -186,218 +172,217 @@ impl InstallationStage for BiosStage {
                 memory_gb, memory_mb
             ),
             self.config.memory_details_time,
-            exit_check,
+            ctx,
         )?;
 
-        if rng.gen_bool(self.config.cmos_error_chance) {
-            println!(
-                "{}",
-                "WARNING: CMOS checksum invalid, loading defaults".yellow()
+        if ctx.rng.gen_bool(self.config.cmos_error_chance) {
+            ctx.print(
+                Tone::Warning,
+                "WARNING: CMOS checksum invalid, loading defaults",
             );
-            thread::sleep(Duration::from_millis(self.config.cmos_warning_time));
+            ctx.stats.warnings += 1;
+            ctx.sleep(self.config.cmos_warning_time);
         }
 
-        println!();
-        println!("{}", "Detecting IDE Devices...".bright_white());
-
-        print!("  Primary Master   [0x1F0-0x1F7]: ");
-        io::stdout().flush()?;
-        thread::sleep(Duration::from_millis(self.config.ide_master_time));
-        println!("{}", "WDC WD2000JB-00GVC0".bright_green());
-
-        print!("  Primary Slave    [0x1F0-0x1F7]: ");
-        io::stdout().flush()?;
-        thread::sleep(Duration::from_millis(self.config.ide_slave_time));
-        println!("{}", "None".dimmed());
-
-        print!("  Secondary Master [0x170-0x177]: ");
-        io::stdout().flush()?;
-        thread::sleep(Duration::from_millis(self.config.ide_master_time));
-        println!("{}", "ATAPI CD-ROM".bright_green());
-
-        print!("  Secondary Slave  [0x170-0x177]: ");
-        io::stdout().flush()?;
-        thread::sleep(Duration::from_millis(self.config.ide_slave_time));
-        println!("{}", "None".dimmed());
-
-        println!();
-        println!("{}", "Scanning PCI bus...".bright_white());
+        ctx.blank();
+        ctx.print(Tone::Bright, "Detecting IDE Devices...");
+
+        ctx.pending(Line::styled(
+            Tone::Plain,
+            "  Primary Master   [0x1F0-0x1F7]: ",
+        ));
+        ctx.sleep(self.config.ide_master_time);
+        ctx.resolve(Line::styled(Tone::Success, "WDC WD2000JB-00GVC0"));
+
+        ctx.pending(Line::styled(
+            Tone::Plain,
+            "  Primary Slave    [0x1F0-0x1F7]: ",
+        ));
+        ctx.sleep(self.config.ide_slave_time);
+        ctx.resolve(Line::styled(Tone::Dim, "None"));
+
+        ctx.pending(Line::styled(
+            Tone::Plain,
+            "  Secondary Master [0x170-0x177]: ",
+        ));
+        ctx.sleep(self.config.ide_master_time);
+        ctx.resolve(Line::styled(Tone::Success, "ATAPI CD-ROM"));
+
+        ctx.pending(Line::styled(
+            Tone::Plain,
+            "  Secondary Slave  [0x170-0x177]: ",
+        ));
+        ctx.sleep(self.config.ide_slave_time);
+        ctx.resolve(Line::styled(Tone::Dim, "None"));
+
+        ctx.blank();
+        ctx.print(Tone::Bright, "Scanning PCI bus...");
 
         let pci_progress = ProgressBar::new(ProgressStyle::Block);
-        print!("  Probing 00:00.0 - 00:1F.7: ");
-        io::stdout().flush()?;
+        let pci_label = Line::styled(Tone::Plain, "  Probing 00:00.0 - 00:1F.7:");
 
         let steps = 30;
         let delay = self.config.pci_scan_time / steps;
         for i in 0..=steps {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
             let progress = i as f32 / steps as f32;
-            print!(
-                "\r  Probing 00:00.0 - 00:1F.7: {}",
-                pci_progress.render(progress)
-            );
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(delay));
+            pci_progress.draw(ctx, &pci_label, progress, None);
+            ctx.sleep(delay);
         }
-        println!();
-
-        thread::sleep(Duration::from_millis(200));
-        let pci_addr1 = format!("00:{:02X}.0", rng.gen_range(0x02..0x10));
-        let pci_addr2 = format!("00:{:02X}.0", rng.gen_range(0x10..0x1F));
-        println!(
-            "  Found {} - VGA Compatible Controller",
-            pci_addr1.bright_cyan()
+        ctx.emit(&Event::ProgressEnd(None));
+
+        ctx.sleep(200);
+        let pci_addr1 = format!("00:{:02X}.0", ctx.rng.gen_range(0x02..0x10));
+        let pci_addr2 = format!("00:{:02X}.0", ctx.rng.gen_range(0x10..0x1F));
+        ctx.print_line(
+            Line::styled(Tone::Plain, "  Found ")
+                .with(Tone::Highlight, pci_addr1)
+                .with(Tone::Plain, " - VGA Compatible Controller"),
+        );
+        ctx.sleep(self.config.pci_device_time);
+        ctx.print_line(
+            Line::styled(Tone::Plain, "  Found ")
+                .with(Tone::Highlight, pci_addr2)
+                .with(Tone::Plain, " - Ethernet Controller"),
         );
-        thread::sleep(Duration::from_millis(self.config.pci_device_time));
-        println!("  Found {} - Ethernet Controller", pci_addr2.bright_cyan());
-        thread::sleep(Duration::from_millis(self.config.pci_device_time));
-        println!("  Found {} - SMBus Controller", "00:1F.3".bright_cyan());
-        thread::sleep(Duration::from_millis(self.config.pci_device_time));
+        ctx.sleep(self.config.pci_device_time);
+        ctx.print_line(
+            Line::styled(Tone::Plain, "  Found ")
+                .with(Tone::Highlight, "00:1F.3")
+                .with(Tone::Plain, " - SMBus Controller"),
+        );
+        ctx.sleep(self.config.pci_device_time);
 
-        println!();
+        ctx.blank();
         spinner.animate(
             &format!("Network Adapters: {} detected", sys_info.network_count),
             self.config.network_detect_time,
-            exit_check,
+            ctx,
         )?;
         spinner.animate(
             "USB Controller: UHCI/EHCI Compatible",
             self.config.usb_detect_time,
-            exit_check,
+            ctx,
         )?;
         spinner.animate(
             "USB Device(s): 0 connected",
             self.config.usb_detect_time,
-            exit_check,
+            ctx,
         )?;
 
-        println!();
+        ctx.blank();
         spinner.animate(
             &format!("Host OS: {}", sys_info.os_name),
             self.config.system_info_time,
-            exit_check,
+            ctx,
         )?;
         spinner.animate(
             &format!("Storage Devices: {} disk(s) found", sys_info.disk_count),
             self.config.system_info_time,
-            exit_check,
+            ctx,
         )?;
 
         let system_uuid = format!(
             "{:08X}-{:04X}-{:04X}-{:04X}-{:012X}",
-            rng.gen::<u32>(),
-            rng.gen::<u16>(),
-            rng.gen::<u16>(),
-            rng.gen::<u16>(),
-            rng.gen::<u64>() & 0xFFFFFFFFFFFF
+            ctx.rng.gen::<u32>(),
+            ctx.rng.gen::<u16>(),
+            ctx.rng.gen::<u16>(),
+            ctx.rng.gen::<u16>(),
+            ctx.rng.gen::<u64>() & 0xFFFFFFFFFFFF
         );
         spinner.animate(
             &format!("System UUID: {}", system_uuid),
             self.config.uuid_time,
-            exit_check,
+            ctx,
         )?;
 
-        println!();
-        spinner.animate(
-            "Boot Device Priority:",
-            self.config.boot_priority_time,
-            exit_check,
-        )?;
-        println!("  1st: {}", "Hard Disk Drive".bright_green());
-        println!("  2nd: {}", "CD-ROM Drive".dimmed());
-        println!("  3rd: {}", "Network Boot".dimmed());
-        thread::sleep(Duration::from_millis(self.config.boot_display_time));
-
-        println!();
-        println!(
-            "{}",
-            "═══════════════════════════════════════════════════════════════".bright_yellow()
-        );
-        println!(
-            "{}",
-            "  CRITICAL: Firmware Update Sequence Initiated"
-                .bright_yellow()
-                .bold()
-        );
-        println!(
-            "{}",
-            "═══════════════════════════════════════════════════════════════".bright_yellow()
+        ctx.blank();
+        spinner.animate("Boot Device Priority:", self.config.boot_priority_time, ctx)?;
+        ctx.print_line(Line::styled(Tone::Plain, "  1st: ").with(Tone::Success, "Hard Disk Drive"));
+        ctx.print_line(Line::styled(Tone::Plain, "  2nd: ").with(Tone::Dim, "CD-ROM Drive"));
+        ctx.print_line(Line::styled(Tone::Plain, "  3rd: ").with(Tone::Dim, "Network Boot"));
+        ctx.sleep(self.config.boot_display_time);
+
+        let rule = "═".repeat(fit_width(63, ctx.columns()));
+        ctx.blank();
+        ctx.print(Tone::Header, &rule);
+        ctx.print_line(
+            Line::styled(
+                Tone::Header,
+                "  CRITICAL: Firmware Update Sequence Initiated",
+            )
+            .bold(),
         );
-        thread::sleep(Duration::from_millis(self.config.firmware_header_delay));
+        ctx.print(Tone::Header, &rule);
+        ctx.sleep(self.config.firmware_header_delay);
 
         spinner.animate(
             "Backing up current BIOS to NVRAM...",
             self.config.backup_time,
-            exit_check,
+            ctx,
         )?;
         spinner.animate(
             "Verifying backup integrity... CRC32 OK",
             self.config.verify_time,
-            exit_check,
+            ctx,
         )?;
 
-        println!();
-        println!(
-            "{}",
-            "  WARNING: Do NOT power off or restart during this process!"
-                .yellow()
-                .bold()
+        ctx.blank();
+        ctx.print_line(
+            Line::styled(
+                Tone::Warning,
+                "  WARNING: Do NOT power off or restart during this process!",
+            )
+            .bold(),
         );
-        println!(
-            "{}",
-            "  System damage may occur if interrupted!".yellow().bold()
+        ctx.print_line(
+            Line::styled(Tone::Warning, "  System damage may occur if interrupted!").bold(),
         );
-        println!();
-        thread::sleep(Duration::from_millis(self.config.warning_delay));
+        ctx.blank();
+        ctx.sleep(self.config.warning_delay);
 
         let progress = ProgressBar::new(ProgressStyle::Block);
         progress.animate(
             "Erasing flash sectors:",
-            rng.gen_range(self.config.erase_min..self.config.erase_max),
-            exit_check,
+            ctx.rng.gen_range(self.config.erase_time_range.clone()),
+            ctx,
         )?;
 
         progress.animate(
             "Writing new firmware:",
-            rng.gen_range(self.config.write_min..self.config.write_max),
-            exit_check,
+            ctx.rng.gen_range(self.config.write_time_range.clone()),
+            ctx,
         )?;
 
         progress.animate(
             "Verifying firmware:",
-            rng.gen_range(self.config.verify_min..self.config.verify_max),
-            exit_check,
+            ctx.rng.gen_range(self.config.verify_time_range.clone()),
+            ctx,
         )?;
 
-        println!();
-        spinner.animate(
-            "Firmware update complete!",
-            self.config.complete_time,
-            exit_check,
-        )?;
+        ctx.blank();
+        spinner.animate("Firmware update complete!", self.config.complete_time, ctx)?;
         spinner.animate(
             "Updating ESCD (Extended System Configuration Data)...",
             self.config.escd_time,
-            exit_check,
+            ctx,
         )?;
 
-        println!();
-        println!(
-            "{}",
-            format!(
-                "BIOS update successful - {} -> {}",
-                self.config.version, self.config.new_version
+        ctx.blank();
+        ctx.print_line(
+            Line::styled(
+                Tone::Success,
+                format!(
+                    "BIOS update successful - {} -> {}",
+                    self.config.version, self.config.new_version
+                ),
             )
-            .bright_green()
-            .bold()
-        );
-        println!(
-            "{}",
-            "System will initialize with new firmware".bright_green()
+            .bold(),
         );
-        thread::sleep(Duration::from_millis(self.config.success_delay));
+        ctx.print(Tone::Success, "System will initialize with new firmware");
+        ctx.sleep(self.config.success_delay);
 
         Ok(())
     }
@@ -1,22 +1,22 @@
 use super::InstallationStage;
 use crate::config::BootConfig;
+use crate::context::Context;
 use crate::kernel_logs::KernelLogs;
-use colored::*;
+use crate::render::Tone;
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
+/// Boots the kernel, printing its log
 pub struct BootStage {
     config: BootConfig,
     kernel_logs: KernelLogs,
 }
 
 impl BootStage {
-    pub fn new(config: BootConfig) -> Self {
+    pub fn new(config: BootConfig, kernel_logs: KernelLogs) -> Self {
         Self {
             config,
-            kernel_logs: KernelLogs::load(),
+            kernel_logs,
         }
     }
 }
@@ -26,28 +26,23 @@ impl InstallationStage for BootStage {
         "Kernel Boot Sequence"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
-        let log_count = rng.gen_range(self.config.log_count_range.clone());
-        let logs = self.kernel_logs.random_batch(log_count);
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        let log_count = ctx.rng.gen_range(self.config.log_count_range.clone());
+        let logs = self
+            .kernel_logs
+            .lines(self.config.log_mode, log_count, &mut ctx.rng);
 
         for log in logs {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            println!("{}", log.dimmed());
-            thread::sleep(Duration::from_millis(
-                rng.gen_range(self.config.log_delay_range.clone()),
-            ));
+            ctx.print(Tone::Dim, log);
+            ctx.sleep_range(self.config.log_delay_range.clone());
         }
 
-        println!();
-        thread::sleep(Duration::from_millis(self.config.final_delay));
+        ctx.blank();
+        ctx.sleep(self.config.final_delay);
 
         Ok(())
     }
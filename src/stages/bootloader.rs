@@ -1,14 +1,13 @@
 use super::InstallationStage;
 use crate::config::BootloaderConfig;
-use crate::log_generator::LogGenerator;
+use crate::context::Context;
+use crate::render::{Line, Tone};
 use crate::ui::{ProgressBar, ProgressStyle, Spinner};
-use colored::*;
 use rand::Rng;
 use std::env;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
+/// Installs GRUB on the boot disk
 pub struct BootloaderStage {
     config: BootloaderConfig,
 }
@@ -24,53 +23,31 @@ impl InstallationStage for BootloaderStage {
         "Bootloader Installation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.log(Tone::Bright, "Installing GRUB2 bootloader...");
+        ctx.sleep(self.config.install_delay);
 
-        let mut rng = rand::thread_rng();
+        ctx.log(Tone::Dim, "Running pre-installation checks...");
+        ctx.sleep(500);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Installing GRUB2 bootloader...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(self.config.install_delay));
-
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Running pre-installation checks...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(500));
-
-        let is_efi = rng.gen_bool(0.7);
+        let is_efi = ctx.rng.gen_bool(0.7);
         if is_efi {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "EFI variables detected, installing for UEFI mode".dimmed()
-            );
-            thread::sleep(Duration::from_millis(300));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "EFI System Partition found at /boot/efi".dimmed()
+            ctx.log(
+                Tone::Dim,
+                "EFI variables detected, installing for UEFI mode",
             );
+            ctx.sleep(300);
+            ctx.log(Tone::Dim, "EFI System Partition found at /boot/efi");
         } else {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Legacy BIOS mode detected".dimmed()
-            );
+            ctx.log(Tone::Dim, "Legacy BIOS mode detected");
         }
-        thread::sleep(Duration::from_millis(400));
+        ctx.sleep(400);
 
         let mut spinner = Spinner::new();
         spinner.animate(
             "Probing devices for bootloader installation...",
             self.config.probe_delay,
-            exit_check,
+            ctx,
         )?;
 
         let devices = [
@@ -79,41 +56,28 @@ impl InstallationStage for BootloaderStage {
             ("/dev/vda", "VirtIO", "QEMU HARDDISK", 64),
             ("/dev/sdb", "SATA", "Crucial MX500", 1000),
         ];
-        let (device, dev_type, dev_name, capacity_gb) = devices[rng.gen_range(0..devices.len())];
+        let (device, dev_type, dev_name, capacity_gb) =
+            devices[ctx.rng.gen_range(0..devices.len())];
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("Detected {} device: {} ({})", dev_type, dev_name, device).dimmed()
-        );
-        thread::sleep(Duration::from_millis(250));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Capacity: {} GB", capacity_gb).dimmed()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Block size: 512 bytes").dimmed()
+        ctx.log(
+            Tone::Dim,
+            format!("Detected {} device: {} ({})", dev_type, dev_name, device),
         );
-        thread::sleep(Duration::from_millis(300));
+        ctx.sleep(250);
+        ctx.log(Tone::Dim, format!("  Capacity: {} GB", capacity_gb));
+        ctx.sleep(200);
+        ctx.log(Tone::Dim, "  Block size: 512 bytes");
+        ctx.sleep(300);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Analyzing partition table...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(600));
+        ctx.log(Tone::Dim, "Analyzing partition table...");
+        ctx.sleep(600);
 
         let partition_table = if is_efi { "GPT" } else { "MBR" };
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Partition table type: {}", partition_table).dimmed()
+        ctx.log(
+            Tone::Dim,
+            format!("  Partition table type: {}", partition_table),
         );
-        thread::sleep(Duration::from_millis(250));
+        ctx.sleep(250);
 
         let boot_partition = if device == "/dev/nvme0n1" {
             format!("{}p2", device)
@@ -121,12 +85,8 @@ impl InstallationStage for BootloaderStage {
             format!("{}2", device)
         };
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Boot partition: {}", boot_partition).dimmed()
-        );
-        thread::sleep(Duration::from_millis(250));
+        ctx.log(Tone::Dim, format!("  Boot partition: {}", boot_partition));
+        ctx.sleep(250);
 
         if is_efi {
             let efi_partition = if device == "/dev/nvme0n1" {
@@ -134,36 +94,23 @@ impl InstallationStage for BootloaderStage {
             } else {
                 format!("{}1", device)
             };
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("  EFI partition: {} (FAT32, 512 MB)", efi_partition).dimmed()
+            ctx.log(
+                Tone::Dim,
+                format!("  EFI partition: {} (FAT32, 512 MB)", efi_partition),
             );
-            thread::sleep(Duration::from_millis(250));
+            ctx.sleep(250);
         }
-        thread::sleep(Duration::from_millis(400));
+        ctx.sleep(400);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Checking filesystems...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(500));
+        ctx.log(Tone::Dim, "Checking filesystems...");
+        ctx.sleep(500);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  {} is mounted on /", boot_partition).dimmed()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Filesystem: ext4".dimmed()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.log(Tone::Dim, format!("  {} is mounted on /", boot_partition));
+        ctx.sleep(200);
+        ctx.log(Tone::Dim, "  Filesystem: ext4");
+        ctx.sleep(400);
 
-        if exit_check() {
+        if ctx.check_exit() {
             return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
         }
 
@@ -194,12 +141,12 @@ impl InstallationStage for BootloaderStage {
             }
         };
 
-        println!(
-            "\n{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("Installing for {} platform...", platform).bright_white()
+        ctx.blank();
+        ctx.log(
+            Tone::Bright,
+            format!("Installing for {} platform...", platform),
         );
-        thread::sleep(Duration::from_millis(self.config.device_install_delay));
+        ctx.sleep(self.config.device_install_delay);
 
         let modules = if is_efi {
             vec![
@@ -242,58 +189,38 @@ impl InstallationStage for BootloaderStage {
             ]
         };
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("Installing GRUB modules ({} modules)...", modules.len()).dimmed()
+        ctx.log(
+            Tone::Dim,
+            format!("Installing GRUB modules ({} modules)...", modules.len()),
         );
-        thread::sleep(Duration::from_millis(300));
+        ctx.sleep(300);
 
         for (idx, module) in modules.iter().enumerate() {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
             if idx % 3 == 0 {
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    format!("  Installing {}.mod", module).dimmed()
-                );
-                thread::sleep(Duration::from_millis(rng.gen_range(150..300)));
+                ctx.log(Tone::Dim, format!("  Installing {}.mod", module));
+                ctx.sleep_range(150..300);
             }
         }
-        thread::sleep(Duration::from_millis(400));
+        ctx.sleep(400);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Installing bootloader fonts...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(300));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Converting DejaVu Sans Regular to PFF2 format".dimmed()
-        );
-        thread::sleep(Duration::from_millis(600));
+        ctx.log(Tone::Dim, "Installing bootloader fonts...");
+        ctx.sleep(300);
+        ctx.log(Tone::Dim, "  Converting DejaVu Sans Regular to PFF2 format");
+        ctx.sleep(600);
 
-        if exit_check() {
+        if ctx.check_exit() {
             return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
         }
 
-        println!(
-            "\n{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Generating grub configuration file...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(self.config.config_gen_delay));
+        ctx.blank();
+        ctx.log(Tone::Bright, "Generating grub configuration file...");
+        ctx.sleep(self.config.config_gen_delay);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Probing system configuration...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(500));
+        ctx.log(Tone::Dim, "Probing system configuration...");
+        ctx.sleep(500);
 
         let kernel_sets = [
             [
@@ -312,202 +239,115 @@ impl InstallationStage for BootloaderStage {
                 "vmlinuz-6.1.0-25-amd64",
             ],
         ];
-        let kernels = &kernel_sets[rng.gen_range(0..kernel_sets.len())];
+        let kernels = &kernel_sets[ctx.rng.gen_range(0..kernel_sets.len())];
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Searching for linux images...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.log(Tone::Dim, "Searching for linux images...");
+        ctx.sleep(400);
 
         for kernel in kernels {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("Found linux image: /boot/{}", kernel).dimmed()
-            );
+            ctx.log(Tone::Dim, format!("Found linux image: /boot/{}", kernel));
 
-            let initrd_suffix = if kernel.contains("generic") {
-                &kernel[8..]
-            } else if kernel.contains("amd64") {
-                &kernel[8..]
-            } else {
-                &kernel[8..]
-            };
+            let initrd_suffix = &kernel[8..];
 
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("Found initrd image: /boot/initrd.img-{}", initrd_suffix).dimmed()
+            ctx.log(
+                Tone::Dim,
+                format!("Found initrd image: /boot/initrd.img-{}", initrd_suffix),
             );
-            thread::sleep(Duration::from_millis(
-                rng.gen_range(self.config.kernel_scan_delay_range.clone()),
-            ));
+            ctx.sleep_range(self.config.kernel_scan_delay_range.clone());
         }
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Found memtest86+ image: /boot/memtest86+.bin".dimmed()
-        );
-        thread::sleep(Duration::from_millis(300));
+        ctx.log(Tone::Dim, "Found memtest86+ image: /boot/memtest86+.bin");
+        ctx.sleep(300);
 
-        if rng.gen_bool(self.config.windows_found_chance) {
+        if ctx.rng.gen_bool(self.config.windows_found_chance) {
             let partition = if device == "/dev/nvme0n1" {
                 format!("{}p3", device)
             } else {
                 format!("{}3", device)
             };
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("Found Windows Boot Manager on {}", partition).dimmed()
-            );
-            thread::sleep(Duration::from_millis(250));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "  Windows 11 (loader) (on {})"
-                    .replace("{}", &partition)
-                    .dimmed()
+            ctx.log(
+                Tone::Dim,
+                format!("Found Windows Boot Manager on {}", partition),
             );
-            thread::sleep(Duration::from_millis(200));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Adding boot menu entry for Windows".dimmed()
+            ctx.sleep(250);
+            ctx.log(
+                Tone::Dim,
+                "  Windows 11 (loader) (on {})".replace("{}", &partition),
             );
-            thread::sleep(Duration::from_millis(self.config.windows_delay));
+            ctx.sleep(200);
+            ctx.log(Tone::Dim, "Adding boot menu entry for Windows");
+            ctx.sleep(self.config.windows_delay);
         }
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Writing configuration to /boot/grub/grub.cfg...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(500));
-
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Setting default boot entry: 0".dimmed()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Setting timeout: 5 seconds".dimmed()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Enabling submenu for older kernels".dimmed()
-        );
-        thread::sleep(Duration::from_millis(500));
-
-        if rng.gen_bool(0.6) {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Installing GRUB theme...".dimmed()
-            );
-            thread::sleep(Duration::from_millis(300));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "  Theme: starfield".dimmed()
-            );
-            thread::sleep(Duration::from_millis(400));
+        ctx.log(Tone::Dim, "Writing configuration to /boot/grub/grub.cfg...");
+        ctx.sleep(500);
+
+        ctx.log(Tone::Dim, "  Setting default boot entry: 0");
+        ctx.sleep(200);
+        ctx.log(Tone::Dim, "  Setting timeout: 5 seconds");
+        ctx.sleep(200);
+        ctx.log(Tone::Dim, "  Enabling submenu for older kernels");
+        ctx.sleep(500);
+
+        if ctx.rng.gen_bool(0.6) {
+            ctx.log(Tone::Dim, "Installing GRUB theme...");
+            ctx.sleep(300);
+            ctx.log(Tone::Dim, "  Theme: starfield");
+            ctx.sleep(400);
         }
 
-        println!(
-            "\n{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Installing bootloader to disk...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(300));
+        ctx.blank();
+        ctx.log(Tone::Bright, "Installing bootloader to disk...");
+        ctx.sleep(300);
 
         if is_efi {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Creating EFI boot entry...".dimmed()
-            );
-            thread::sleep(Duration::from_millis(300));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "  EFI application: \\EFI\\ubuntu\\shimx64.efi".dimmed()
-            );
-            thread::sleep(Duration::from_millis(400));
+            ctx.log(Tone::Dim, "Creating EFI boot entry...");
+            ctx.sleep(300);
+            ctx.log(Tone::Dim, "  EFI application: \\EFI\\ubuntu\\shimx64.efi");
+            ctx.sleep(400);
         }
 
         for i in 0..5 {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
             let progress = ProgressBar::new(ProgressStyle::Block);
-            progress.animate(
-                &format!(
-                    "{} Writing stage {} image...",
-                    LogGenerator::timestamp().dimmed(),
-                    i + 1
-                ),
-                rng.gen_range(self.config.write_stage_delay_range.clone()),
-                exit_check,
+            progress.animate_line(
+                ctx.stamp(Line::styled(
+                    Tone::Bright,
+                    format!("Writing stage {} image...", i + 1),
+                )),
+                ctx.rng
+                    .gen_range(self.config.write_stage_delay_range.clone()),
+                ctx,
             )?;
         }
 
-        println!(
-            "\n{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Verifying installation...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(600));
+        ctx.blank();
+        ctx.log(Tone::Dim, "Verifying installation...");
+        ctx.sleep(600);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Checking boot sector... OK".dimmed()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.log(Tone::Dim, "  Checking boot sector... OK");
+        ctx.sleep(400);
 
         if is_efi {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "  Checking EFI boot variables... OK".dimmed()
-            );
-            thread::sleep(Duration::from_millis(400));
+            ctx.log(Tone::Dim, "  Checking EFI boot variables... OK");
+            ctx.sleep(400);
         }
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Verifying GRUB modules... OK".dimmed()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.log(Tone::Dim, "  Verifying GRUB modules... OK");
+        ctx.sleep(400);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "  Checking configuration file... OK".dimmed()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.log(Tone::Dim, "  Checking configuration file... OK");
+        ctx.sleep(400);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Installation finished. No error reported.".bright_green()
-        );
+        ctx.log(Tone::Success, "Installation finished. No error reported.");
 
-        thread::sleep(Duration::from_millis(self.config.finish_delay));
+        ctx.sleep(self.config.finish_delay);
 
         Ok(())
     }
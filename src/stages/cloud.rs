@@ -1,13 +1,12 @@
 use super::InstallationStage;
 use crate::config::CloudConfig;
-use crate::log_generator::LogGenerator;
-use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
+use crate::context::Context;
+use crate::render::{Line, Tone};
+use crate::ui::{ProgressBar, ProgressStyle, Spinner, SpinnerStyle};
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
+/// Provisions cloud resources with Terraform
 pub struct CloudStage {
     config: CloudConfig,
 }
@@ -23,17 +22,16 @@ impl InstallationStage for CloudStage {
         "Cloud Infrastructure Provisioning"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_cyan().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
+    fn tone(&self) -> Tone {
+        Tone::Highlight
+    }
 
-        println!(
-            "{} Initializing Terraform backend...",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(600));
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        Spinner::with_style(SpinnerStyle::Dots).animate(
+            "Initializing Terraform backend...",
+            600,
+            ctx,
+        )?;
 
         let resources = [
             ("aws_vpc.main", "VPC"),
@@ -53,87 +51,102 @@ impl InstallationStage for CloudStage {
         ];
 
         for (resource, r_type) in resources {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            let colored_resource = match r_type {
-                "IAM Role" => resource.yellow(),
-                "EC2 Instance" | "Lambda" => resource.green(),
-                "RDS Instance" | "DynamoDB" => resource.blue(),
-                "S3 Bucket" | "CloudFront" => resource.magenta(),
-                _ => resource.cyan(),
+            let resource_tone = match r_type {
+                "IAM Role" => Tone::Warning,
+                "EC2 Instance" | "Lambda" => Tone::Success,
+                "RDS Instance" | "DynamoDB" => Tone::Info,
+                "S3 Bucket" | "CloudFront" => Tone::Special,
+                _ => Tone::Accent,
             };
 
-            println!(
-                "{} Creating {} ({})",
-                LogGenerator::timestamp().dimmed(),
-                colored_resource,
-                r_type.dimmed()
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Creating ")
+                    .with(resource_tone, resource)
+                    .with(Tone::Plain, " (")
+                    .with(Tone::Dim, r_type)
+                    .with(Tone::Plain, ")"),
             );
 
-            if rng.gen_bool(self.config.failure_rate_rate_limit) {
-                thread::sleep(Duration::from_millis(rng.gen_range(200..500)));
-                println!(
-                    "{} Error: 429 Too Many Requests (RequestLimitExceeded)",
-                    LogGenerator::timestamp().red()
-                );
-                println!("{} Throttling...", LogGenerator::timestamp().yellow());
-                thread::sleep(Duration::from_millis(2000));
-                println!(
-                    "{} Resuming operation...",
-                    LogGenerator::timestamp().dimmed()
+            if ctx.rng.gen_bool(self.config.failure_rate_rate_limit) {
+                ctx.sleep_range(200..500);
+                ctx.log_line(
+                    Line::styled(
+                        Tone::Plain,
+                        "Error: 429 Too Many Requests (RequestLimitExceeded)",
+                    )
+                    .stamp_tone(Tone::Error),
                 );
+                ctx.log_line(Line::styled(Tone::Plain, "Throttling...").stamp_tone(Tone::Warning));
+                ctx.sleep(2000);
+                ctx.log(Tone::Plain, "Resuming operation...");
+                ctx.stats.recovered_failures += 1;
             }
 
             if r_type == "EC2 Instance"
-                && rng.gen_bool(self.config.failure_rate_insufficient_capacity)
+                && ctx
+                    .rng
+                    .gen_bool(self.config.failure_rate_insufficient_capacity)
             {
-                thread::sleep(Duration::from_millis(1000));
-                println!("{} Error: InsufficientInstanceCapacity: We currently do not have sufficient capacity in the Availability Zone you requested.", LogGenerator::timestamp().red());
-                println!(
-                    "{} Retrying in different Availability Zone (us-east-1b)...",
-                    LogGenerator::timestamp().yellow()
+                ctx.sleep(1000);
+                ctx.log_line(Line::styled(Tone::Plain, "Error: InsufficientInstanceCapacity: We currently do not have sufficient capacity in the Availability Zone you requested.").stamp_tone(Tone::Error));
+                ctx.log_retry(
+                    Line::styled(
+                        Tone::Plain,
+                        "Retrying in different Availability Zone (us-east-1b)...",
+                    )
+                    .stamp_tone(Tone::Warning),
                 );
-                thread::sleep(Duration::from_millis(1500));
+                ctx.sleep(1500);
+                ctx.stats.recovered_failures += 1;
             }
 
-            if r_type == "Lambda" && rng.gen_bool(self.config.failure_rate_dependency_violation) {
-                println!("{} Error: The role defined for the function cannot be assumed by the function.", LogGenerator::timestamp().red());
-                println!(
-                    "{} Waiting for IAM propagation...",
-                    LogGenerator::timestamp().yellow()
+            if r_type == "Lambda"
+                && ctx
+                    .rng
+                    .gen_bool(self.config.failure_rate_dependency_violation)
+            {
+                ctx.log_line(Line::styled(Tone::Plain, "Error: The role defined for the function cannot be assumed by the function.").stamp_tone(Tone::Error));
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "Waiting for IAM propagation...")
+                        .stamp_tone(Tone::Warning),
                 );
-                thread::sleep(Duration::from_millis(2500));
+                ctx.sleep(2500);
+                ctx.stats.recovered_failures += 1;
             }
 
-            if r_type == "S3 Bucket" && rng.gen_bool(self.config.failure_rate_checksum_mismatch) {
-                println!(
-                    "{} Error: Checksum mismatch during upload.",
-                    LogGenerator::timestamp().red()
+            if r_type == "S3 Bucket" && ctx.rng.gen_bool(self.config.failure_rate_checksum_mismatch)
+            {
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "Error: Checksum mismatch during upload.")
+                        .stamp_tone(Tone::Error),
                 );
-                println!(
-                    "{} Re-calculating hashes and retrying...",
-                    LogGenerator::timestamp().yellow()
+                ctx.log_retry(
+                    Line::styled(Tone::Plain, "Re-calculating hashes and retrying...")
+                        .stamp_tone(Tone::Warning),
                 );
-                thread::sleep(Duration::from_millis(1200));
+                ctx.sleep(1200);
+                ctx.stats.recovered_failures += 1;
             }
 
-            let duration = rng.gen_range(self.config.provision_speed_range.clone());
+            let duration = ctx.rng.gen_range(self.config.provision_speed_range.clone());
             let progress = ProgressBar::new(ProgressStyle::Block);
-            progress.animate("Provisioning", duration, exit_check)?;
+            progress.animate("Provisioning", duration, ctx)?;
 
-            println!(
-                "{} Resource {} is Available",
-                LogGenerator::timestamp().dimmed(),
-                colored_resource
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Resource ")
+                    .with(resource_tone, resource)
+                    .with(Tone::Plain, " is Available"),
             );
         }
 
-        println!();
-        println!("{}", "Infrastructure provisioning complete.".bright_green());
+        ctx.blank();
+        ctx.print(Tone::Success, "Infrastructure provisioning complete.");
 
-        thread::sleep(Duration::from_millis(500));
+        ctx.sleep(500);
         Ok(())
     }
 }
@@ -1,22 +1,25 @@
 use super::InstallationStage;
 use crate::build_logs::BuildLogs;
-use crate::log_generator::LogGenerator;
-use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
+use crate::config::CompilationConfig;
+use crate::context::Context;
+use crate::render::{Line, Tone};
+use crate::ui::progress::Transfer;
+use crate::ui::{MultiProgress, ProgressStyle};
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
+/// Simulated milliseconds between two frames while files compile
+const TICK_MS: u64 = 50;
+
+/// Builds kernel modules, a few files at a time
 pub struct CompilationStage {
+    config: CompilationConfig,
     build_logs: BuildLogs,
 }
 
 impl CompilationStage {
-    pub fn new() -> Self {
-        Self {
-            build_logs: BuildLogs::load(),
-        }
+    pub fn new(config: CompilationConfig, build_logs: BuildLogs) -> Self {
+        Self { config, build_logs }
     }
 }
 
@@ -25,64 +28,86 @@ impl InstallationStage for CompilationStage {
         "Kernel Module Compilation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "make[1]: Entering directory '/usr/src/linux-headers-5.4.0'".dimmed()
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.log(
+            Tone::Dim,
+            "make[1]: Entering directory '/usr/src/linux-headers-5.4.0'",
         );
-        println!();
+        ctx.blank();
 
-        let mut rng = rand::thread_rng();
-        let logs = self.build_logs.all_logs();
+        let logs = self.build_logs.lines(self.config.log_mode, &mut ctx.rng);
+        let mut logs = logs.iter().peekable();
 
-        for log in logs {
-            if exit_check() {
+        // Files being compiled, each with its line and the milliseconds it has taken so far
+        let mut multi = MultiProgress::new(ProgressStyle::Block);
+        let mut jobs: Vec<(usize, Line, Transfer)> = Vec::new();
+        loop {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            let should_show_progress = log.trim().starts_with("CC")
-                || log.trim().starts_with("LD")
-                || log.trim().starts_with("AR");
-
-            if should_show_progress {
-                let speed_category = rng.gen_range(0..10);
-                let duration = if speed_category < 3 {
-                    rng.gen_range(50..200)
-                } else if speed_category < 7 {
-                    rng.gen_range(200..600)
-                } else {
-                    rng.gen_range(600..1500)
+            // Start files until every job slot is busy, other lines come out as make gets to them
+            let mut wait = TICK_MS;
+            while jobs.len() < self.config.jobs.get() {
+                let Some(log) = logs.next() else {
+                    break;
                 };
 
-                let progress = ProgressBar::new(ProgressStyle::Block);
-                progress.animate(
-                    &format!("{} {}", LogGenerator::timestamp().dimmed(), log.cyan()),
-                    duration,
-                    exit_check,
-                )?;
-            } else {
-                println!("{} {}", LogGenerator::timestamp().dimmed(), log.cyan());
-                let speed_category = rng.gen_range(0..10);
-                let delay = if speed_category < 4 {
-                    rng.gen_range(10..30)
-                } else if speed_category < 8 {
-                    rng.gen_range(30..80)
+                let should_show_progress = log.trim().starts_with("CC")
+                    || log.trim().starts_with("LD")
+                    || log.trim().starts_with("AR");
+
+                if should_show_progress {
+                    let speed_category = ctx.rng.gen_range(0..10);
+                    let duration = if speed_category < 3 {
+                        ctx.rng.gen_range(50..200)
+                    } else if speed_category < 7 {
+                        ctx.rng.gen_range(200..600)
+                    } else {
+                        ctx.rng.gen_range(600..1500)
+                    };
+
+                    let label = ctx.stamp(Line::styled(Tone::Accent, log));
+                    let task = multi.add(label.clone());
+                    jobs.push((task, label, Transfer::items(0, duration)));
                 } else {
-                    rng.gen_range(80..200)
-                };
-                thread::sleep(Duration::from_millis(delay));
+                    ctx.log(Tone::Accent, log);
+                    let speed_category = ctx.rng.gen_range(0..10);
+                    wait = if speed_category < 4 {
+                        ctx.rng.gen_range(10..30)
+                    } else if speed_category < 8 {
+                        ctx.rng.gen_range(30..80)
+                    } else {
+                        ctx.rng.gen_range(80..200)
+                    };
+                    break;
+                }
+            }
+            if jobs.is_empty() && logs.peek().is_none() {
+                break;
+            }
+
+            for (task, label, elapsed) in &jobs {
+                multi.set(*task, label.clone(), Some(*elapsed));
+            }
+            multi.draw(ctx);
+            ctx.sleep(wait);
+
+            for (task, label, elapsed) in &mut jobs {
+                elapsed.done = (elapsed.done + wait).min(elapsed.total);
+                if elapsed.is_done() {
+                    multi.set(*task, label.clone(), Some(*elapsed));
+                    multi.complete(ctx, *task);
+                }
             }
+            jobs.retain(|(_, _, elapsed)| !elapsed.is_done());
         }
+        multi.finish(ctx);
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "make[1]: Leaving directory '/usr/src/linux-headers-5.4.0'".dimmed()
+        ctx.blank();
+        ctx.log(
+            Tone::Dim,
+            "make[1]: Leaving directory '/usr/src/linux-headers-5.4.0'",
         );
 
         Ok(())
@@ -91,6 +116,6 @@ impl InstallationStage for CompilationStage {
 
 impl Default for CompilationStage {
     fn default() -> Self {
-        Self::new()
+        Self::new(CompilationConfig::default(), BuildLogs::load())
     }
 }
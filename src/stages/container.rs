@@ -1,13 +1,56 @@
 use super::InstallationStage;
 use crate::config::ContainerConfig;
+use crate::context::Context;
 use crate::log_generator::LogGenerator;
-use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
+use crate::render::{Line, Tone};
+use crate::ui::progress::Transfer;
+use crate::ui::{MultiProgress, ProgressBar, ProgressStyle};
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
+/// Size of the kubectl binary that gets downloaded
+const KUBECTL_SIZE: u64 = 49_704_960;
+/// Layers docker downloads at the same time
+const CONCURRENT_DOWNLOADS: usize = 3;
+/// Simulated milliseconds between two frames of a pull
+const PULL_TICK_MS: u64 = 100;
+
+/// Where a layer is in a `docker pull`
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum LayerState {
+    Waiting,
+    Downloading,
+    Downloaded,
+    Extracting,
+    Complete,
+}
+
+struct Layer {
+    id: String,
+    task: usize,
+    size: u64,
+    /// Bytes downloaded, then bytes extracted, in fractions so that slow
+    /// layers move at all
+    done: f64,
+    /// Download speed in bytes per second, extraction goes faster
+    rate: f64,
+    state: LayerState,
+}
+
+impl Layer {
+    fn label(&self) -> Line {
+        let (status, tone) = match self.state {
+            LayerState::Waiting => ("Waiting", Tone::Plain),
+            LayerState::Downloading => ("Downloading", Tone::Plain),
+            LayerState::Downloaded => ("Download complete", Tone::Plain),
+            LayerState::Extracting => ("Extracting", Tone::Plain),
+            LayerState::Complete => ("Pull complete", Tone::Success),
+        };
+        Line::styled(Tone::Dim, format!("{}: ", self.id)).with(tone, status)
+    }
+}
+
+/// Pulls container images and starts pods on Kubernetes
 pub struct ContainerStage {
     config: ContainerConfig,
 }
@@ -18,85 +61,181 @@ impl ContainerStage {
     }
 }
 
+impl ContainerStage {
+    /// Pulls the layers of an image, like docker: a few downloads at a time,
+    /// each layer extracted in order once it is in
+    fn pull_layers(&self, image_size: u64, ctx: &mut Context) -> io::Result<()> {
+        let count = ctx.rng.gen_range(3..8);
+        let shares: Vec<f64> = (0..count).map(|_| ctx.rng.gen_range(0.05..1.0)).collect();
+        let sum: f64 = shares.iter().sum();
+
+        let mut multi = MultiProgress::new(ProgressStyle::Docker);
+        let mut layers = Vec::new();
+        for share in shares {
+            let id = LogGenerator::hex_addr(&mut ctx.rng)[2..14].to_string();
+            let size = ((image_size as f64 * share / sum) as u64).max(32);
+            let duration = ctx
+                .rng
+                .gen_range(self.config.layer_pull_speed_range.clone());
+            let task = multi.add(
+                Line::styled(Tone::Dim, format!("{}: ", id)).with(Tone::Plain, "Pulling fs layer"),
+            );
+            layers.push(Layer {
+                id,
+                task,
+                size,
+                done: 0.0,
+                rate: size as f64 * 1000.0 / duration as f64,
+                state: LayerState::Waiting,
+            });
+        }
+        multi.draw(ctx);
+
+        while layers
+            .iter()
+            .any(|layer| layer.state != LayerState::Complete)
+        {
+            if ctx.check_exit() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
+            }
+            ctx.sleep(PULL_TICK_MS);
+
+            let downloading = layers
+                .iter()
+                .filter(|layer| layer.state == LayerState::Downloading)
+                .count();
+            for layer in layers
+                .iter_mut()
+                .filter(|layer| layer.state == LayerState::Waiting)
+                .take(CONCURRENT_DOWNLOADS.saturating_sub(downloading))
+            {
+                layer.state = LayerState::Downloading;
+            }
+
+            for layer in &mut layers {
+                if layer.state == LayerState::Downloading {
+                    layer.done += layer.rate * PULL_TICK_MS as f64 / 1000.0;
+                    if layer.done >= layer.size as f64 {
+                        layer.state = LayerState::Downloaded;
+                    }
+                }
+            }
+
+            if let Some(layer) = layers
+                .iter_mut()
+                .find(|layer| layer.state != LayerState::Complete)
+            {
+                match layer.state {
+                    LayerState::Downloaded => {
+                        layer.state = LayerState::Extracting;
+                        layer.done = 0.0;
+                    }
+                    LayerState::Extracting => {
+                        layer.done += layer.rate * 3.0 * PULL_TICK_MS as f64 / 1000.0;
+                        if layer.done >= layer.size as f64 {
+                            layer.state = LayerState::Complete;
+                        }
+                    }
+                    _ => {}
+                }
+            }
+
+            for layer in &layers {
+                let bar = matches!(
+                    layer.state,
+                    LayerState::Downloading | LayerState::Extracting
+                )
+                .then(|| Transfer::items((layer.done as u64).min(layer.size), layer.size));
+                multi.set(layer.task, layer.label(), bar);
+            }
+            multi.draw(ctx);
+        }
+
+        multi.finish(ctx);
+        Ok(())
+    }
+}
+
 impl InstallationStage for ContainerStage {
     fn name(&self) -> &'static str {
         "Container Orchestration"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_blue().bold());
-        println!();
+    fn tone(&self) -> Tone {
+        Tone::Info
+    }
 
-        let mut rng = rand::thread_rng();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        // Image and its compressed size in MiB
         let images = [
-            "alpine:latest",
-            "nginx:1.21-alpine",
-            "postgres:14",
-            "redis:6.2",
-            "node:16-slim",
-            "python:3.9-slim",
+            ("alpine:latest", 3),
+            ("nginx:1.21-alpine", 9),
+            ("postgres:14", 135),
+            ("redis:6.2", 40),
+            ("node:16-slim", 67),
+            ("python:3.9-slim", 45),
         ];
 
-        for image in images {
-            if exit_check() {
+        for (image, size_mib) in images {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            println!(
-                "{} Pulling {}",
-                LogGenerator::timestamp().dimmed(),
-                image.cyan()
-            );
+            ctx.log_line(Line::styled(Tone::Plain, "Pulling ").with(Tone::Accent, image));
 
-            if rng.gen_bool(self.config.failure_rate_image_pull) {
-                thread::sleep(Duration::from_millis(rng.gen_range(500..1500)));
-                println!(
-                    "{} Error: Connection timed out while pulling {}",
-                    LogGenerator::timestamp().red(),
-                    image
+            if ctx.rng.gen_bool(self.config.failure_rate_image_pull) {
+                ctx.sleep_range(500..1500);
+                ctx.log_line(
+                    Line::styled(
+                        Tone::Plain,
+                        format!("Error: Connection timed out while pulling {}", image),
+                    )
+                    .stamp_tone(Tone::Error),
                 );
-                thread::sleep(Duration::from_millis(1000));
-                println!("{} Retrying in 3s...", LogGenerator::timestamp().yellow());
-                thread::sleep(Duration::from_millis(3000));
-                println!(
-                    "{} Retrying pull for {}",
-                    LogGenerator::timestamp().dimmed(),
-                    image.cyan()
+                ctx.sleep(1000);
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "Retrying in 3s...").stamp_tone(Tone::Warning),
                 );
+                ctx.sleep(3000);
+                ctx.log_retry(
+                    Line::styled(Tone::Plain, "Retrying pull for ").with(Tone::Accent, image),
+                );
+                ctx.stats.recovered_failures += 1;
             }
 
-            let layers = rng.gen_range(3..8);
-            for _i in 0..layers {
-                let layer_id = LogGenerator::hex_addr();
-                let short_id = &layer_id[2..14];
-                let progress = ProgressBar::new(ProgressStyle::Block);
-                progress.animate(
-                    &format!("  {} Pulling fs layer", short_id)
-                        .dimmed()
-                        .to_string(),
-                    rng.gen_range(self.config.layer_pull_speed_range.clone()),
-                    exit_check,
-                )?;
-            }
-
-            println!(
-                "{} Digest: sha256:{}",
-                LogGenerator::timestamp().dimmed(),
-                LogGenerator::hex_addr()
+            let (name, tag) = image.split_once(':').unwrap_or((image, "latest"));
+            ctx.log(
+                Tone::Plain,
+                format!("{}: Pulling from library/{}", tag, name),
             );
-            println!(
-                "{} Status: Downloaded newer image for {}",
-                LogGenerator::timestamp().dimmed(),
-                image
+            self.pull_layers(size_mib * 1024 * 1024, ctx)?;
+            ctx.stats.downloaded_bytes += size_mib * 1024 * 1024;
+
+            let digest = LogGenerator::hex_addr(&mut ctx.rng);
+            ctx.log(Tone::Plain, format!("Digest: sha256:{}", digest));
+            ctx.log(
+                Tone::Plain,
+                format!("Status: Downloaded newer image for {}", image),
             );
-            thread::sleep(Duration::from_millis(300));
+            ctx.sleep(300);
         }
 
-        println!();
-        println!(
-            "{} Initializing Kubernetes cluster...",
-            LogGenerator::timestamp().dimmed()
+        ctx.blank();
+        ctx.log(
+            Tone::Plain,
+            "curl -LO https://dl.k8s.io/release/v1.29.2/bin/linux/amd64/kubectl",
         );
+        ProgressBar::new(ProgressStyle::Curl).animate_transfer(
+            Line::new(),
+            KUBECTL_SIZE,
+            ctx.rng
+                .gen_range(self.config.kubectl_download_range.clone()),
+            ctx,
+        )?;
+        ctx.stats.downloaded_bytes += KUBECTL_SIZE;
+
+        ctx.blank();
+        ctx.log(Tone::Plain, "Initializing Kubernetes cluster...");
 
         let pods = [
             "api-gateway",
@@ -107,101 +246,139 @@ impl InstallationStage for ContainerStage {
         ];
 
         for pod in pods {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            println!(
-                "{} Scaled up replica set {} to 1",
-                LogGenerator::timestamp().dimmed(),
-                format!("{}-rs", pod).cyan()
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Scaled up replica set ")
+                    .with(Tone::Accent, format!("{}-rs", pod))
+                    .with(Tone::Plain, " to 1"),
             );
-            thread::sleep(Duration::from_millis(rng.gen_range(100..300)));
+            ctx.sleep_range(100..300);
 
-            println!(
-                "{} Pod {} Status: {}",
-                LogGenerator::timestamp().dimmed(),
-                pod.yellow(),
-                "Pending".yellow()
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Pod ")
+                    .with(Tone::Warning, pod)
+                    .with(Tone::Plain, " Status: ")
+                    .with(Tone::Warning, "Pending"),
             );
-            thread::sleep(Duration::from_millis(rng.gen_range(100..300)));
-            println!(
-                "{} Pod {} Status: {}",
-                LogGenerator::timestamp().dimmed(),
-                pod.yellow(),
-                "ContainerCreating".blue()
+            ctx.sleep_range(100..300);
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Pod ")
+                    .with(Tone::Warning, pod)
+                    .with(Tone::Plain, " Status: ")
+                    .with(Tone::Info, "ContainerCreating"),
             );
 
-            if rng.gen_bool(self.config.probability_volume_mount) {
-                let pvc_id = format!("pvc-{}", &LogGenerator::hex_addr()[2..10]);
-                println!(
-                    "{} Mounting volume {} to {}",
-                    LogGenerator::timestamp().dimmed(),
-                    pvc_id.magenta(),
-                    pod
+            if ctx.rng.gen_bool(self.config.probability_volume_mount) {
+                let pvc_id = format!("pvc-{}", &LogGenerator::hex_addr(&mut ctx.rng)[2..10]);
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "Mounting volume ")
+                        .with(Tone::Special, pvc_id)
+                        .with(Tone::Plain, format!(" to {}", pod)),
                 );
-                thread::sleep(Duration::from_millis(rng.gen_range(200..500)));
+                ctx.sleep_range(200..500);
             }
 
-            if rng.gen_bool(self.config.probability_secret_mount) {
-                println!(
-                    "{} Mounting secret {} to {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "vault-token".magenta(),
-                    pod
+            if ctx.rng.gen_bool(self.config.probability_secret_mount) {
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "Mounting secret ")
+                        .with(Tone::Special, "vault-token")
+                        .with(Tone::Plain, format!(" to {}", pod)),
                 );
             }
 
-            if rng.gen_bool(self.config.probability_sidecar_injection) {
-                println!(
-                    "{} Injecting sidecar {} to {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "istio-proxy".cyan(),
-                    pod
+            if ctx.rng.gen_bool(self.config.probability_sidecar_injection) {
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "Injecting sidecar ")
+                        .with(Tone::Accent, "istio-proxy")
+                        .with(Tone::Plain, format!(" to {}", pod)),
                 );
-                thread::sleep(Duration::from_millis(rng.gen_range(100..300)));
+                ctx.sleep_range(100..300);
             }
 
-            thread::sleep(Duration::from_millis(rng.gen_range(200..500)));
-            if rng.gen_bool(self.config.failure_rate_readiness_probe) {
-                println!(
-                    "{} Warning: Readiness probe failed for {}: Connection refused",
-                    LogGenerator::timestamp().yellow(),
-                    pod
+            ctx.sleep_range(200..500);
+            if ctx.rng.gen_bool(self.config.failure_rate_readiness_probe) {
+                ctx.log_line(
+                    Line::styled(
+                        Tone::Plain,
+                        format!(
+                            "Warning: Readiness probe failed for {}: Connection refused",
+                            pod
+                        ),
+                    )
+                    .stamp_tone(Tone::Warning),
                 );
-                thread::sleep(Duration::from_millis(800));
+                ctx.sleep(800);
+                ctx.stats.warnings += 1;
             }
-            println!(
-                "{} Readiness probe passed for {}",
-                LogGenerator::timestamp().dimmed(),
-                pod
-            );
+            ctx.log(Tone::Plain, format!("Readiness probe passed for {}", pod));
 
-            if rng.gen_bool(self.config.failure_rate_crash_loop) {
-                println!(
-                    "{} Warning: CrashLoopBackOff detected for {}, restarting...",
-                    LogGenerator::timestamp().yellow(),
-                    pod
+            if ctx.rng.gen_bool(self.config.failure_rate_crash_loop) {
+                ctx.log_line(
+                    Line::styled(
+                        Tone::Plain,
+                        format!(
+                            "Warning: CrashLoopBackOff detected for {}, restarting...",
+                            pod
+                        ),
+                    )
+                    .stamp_tone(Tone::Warning),
                 );
-                thread::sleep(Duration::from_millis(800));
+                ctx.sleep(800);
+                ctx.stats.warnings += 1;
+                ctx.stats.recovered_failures += 1;
             }
 
-            println!(
-                "{} Pod {} Status: {}",
-                LogGenerator::timestamp().dimmed(),
-                pod.yellow(),
-                "Running".green()
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Pod ")
+                    .with(Tone::Warning, pod)
+                    .with(Tone::Plain, " Status: ")
+                    .with(Tone::Success, "Running"),
             );
-            println!(
-                "{} Pod {} IP: 10.244.{}.{}",
-                LogGenerator::timestamp().dimmed(),
-                pod,
-                rng.gen_range(0..255),
-                rng.gen_range(0..255)
+            let subnet = ctx.rng.gen_range(0..255);
+            let host = ctx.rng.gen_range(0..255);
+            ctx.log(
+                Tone::Plain,
+                format!("Pod {} IP: 10.244.{}.{}", pod, subnet, host),
             );
         }
 
-        thread::sleep(Duration::from_millis(500));
+        ctx.sleep(500);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::input::Input;
+    use crate::render::{PlainRenderer, Theme};
+    use std::sync::mpsc;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_slow_layers_finish() {
+        let (done, finished) = mpsc::channel();
+        thread::spawn(move || {
+            // A few bytes a second, less than one per frame
+            let stage = ContainerStage::new(ContainerConfig {
+                layer_pull_speed_range: 10_000..10_001,
+                ..ContainerConfig::default()
+            });
+            let (_keys, chars) = mpsc::channel();
+            let renderer = PlainRenderer::new(io::sink(), Theme::default());
+            let mut ctx = Context::new(
+                3,
+                Clock::instant(),
+                Box::new(renderer),
+                Input::remote(chars),
+            );
+            done.send(stage.pull_layers(0, &mut ctx).is_ok()).unwrap();
+        });
+        assert_eq!(finished.recv_timeout(Duration::from_secs(10)), Ok(true));
+    }
+}
@@ -1,63 +1,60 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
-use crate::ui::{ProgressBar, ProgressStyle, Spinner};
-use colored::*;
+use crate::config::DatabaseConfig;
+use crate::context::Context;
+use crate::render::Tone;
+use crate::ui::{ProgressBar, ProgressStyle, Spinner, SpinnerStyle};
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
-pub struct DatabaseStage;
+/// Installs and initializes a MySQL or PostgreSQL server
+pub struct DatabaseStage {
+    config: DatabaseConfig,
+}
+
+impl DatabaseStage {
+    pub fn new(config: DatabaseConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for DatabaseStage {
     fn name(&self) -> &'static str {
         "Database Server Installation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-        let mut spinner = Spinner::new();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        let mut spinner = Spinner::with_style(SpinnerStyle::Bounce);
 
-        let db_type = if rng.gen_bool(0.5) {
+        let db_type = if ctx.rng.gen_bool(self.config.mysql_chance) {
             "MySQL"
         } else {
             "PostgreSQL"
         };
         let version = if db_type == "MySQL" { "8.0.28" } else { "14.2" };
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("Installing {} Server {}...", db_type, version).bright_white()
+        ctx.log(
+            Tone::Bright,
+            format!("Installing {} Server {}...", db_type, version),
         );
-        thread::sleep(Duration::from_millis(800));
+        ctx.sleep(800);
 
-        spinner.animate("Initializing database cluster...", 2000, exit_check)?;
+        spinner.animate(
+            "Initializing database cluster...",
+            self.config.cluster_init_time,
+            ctx,
+        )?;
 
         if db_type == "PostgreSQL" {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "The files belonging to this database system will be owned by user \"postgres\"."
-                    .dimmed()
-            );
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "This user must also own the server process.".dimmed()
+            ctx.log(
+                Tone::Dim,
+                "The files belonging to this database system will be owned by user \"postgres\".",
             );
-            thread::sleep(Duration::from_millis(500));
+            ctx.log(Tone::Dim, "This user must also own the server process.");
+            ctx.sleep(500);
         }
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Creating database files...".bright_white()
-        );
+        ctx.blank();
+        ctx.log(Tone::Bright, "Creating database files...");
 
         let files = [
             "global/pg_control",
@@ -68,36 +65,35 @@ impl InstallationStage for DatabaseStage {
         ];
 
         for file in &files {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("  creating {}", file).dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(150..300)));
+            ctx.log(Tone::Dim, format!("  creating {}", file));
+            ctx.sleep_range(self.config.file_delay_range.clone());
         }
 
-        println!();
+        ctx.blank();
         let progress = ProgressBar::new(ProgressStyle::Equals);
         progress.animate(
             "Initializing system tables:",
-            rng.gen_range(2000..3500),
-            exit_check,
+            ctx.rng
+                .gen_range(self.config.system_tables_time_range.clone()),
+            ctx,
         )?;
 
-        println!();
-        spinner.animate("Creating template databases...", 1500, exit_check)?;
+        ctx.blank();
+        spinner.animate(
+            "Creating template databases...",
+            self.config.template_time,
+            ctx,
+        )?;
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Success. You can now start the database server using:".bright_green()
+        ctx.log(
+            Tone::Success,
+            "Success. You can now start the database server using:",
         );
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
+        ctx.log(
+            Tone::Dim,
             format!(
                 "    {} -D /var/lib/{}/data",
                 if db_type == "PostgreSQL" {
@@ -106,8 +102,7 @@ impl InstallationStage for DatabaseStage {
                     "mysqld"
                 },
                 db_type.to_lowercase()
-            )
-            .dimmed()
+            ),
         );
 
         Ok(())
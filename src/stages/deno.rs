@@ -1,71 +1,72 @@
 use super::InstallationStage;
+use crate::config::DenoConfig;
+use crate::context::Context;
 use crate::deno_logs::DenoLogs;
-use crate::log_generator::LogGenerator;
+use crate::render::{Line, Tone};
+use crate::ui::progress::Transfer;
 use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
-use crossterm::event::{self, Event, KeyCode};
 use rand::Rng;
-use std::io::{self, Write};
-use std::thread;
+use std::io;
 use std::time::Duration;
 
+/// Compiles the Deno runtime with cargo
 pub struct DenoStage {
+    config: DenoConfig,
     deno_logs: DenoLogs,
 }
 
 impl DenoStage {
-    pub fn new() -> Self {
-        Self {
-            deno_logs: DenoLogs::load(),
-        }
+    pub fn new(config: DenoConfig, deno_logs: DenoLogs) -> Self {
+        Self { config, deno_logs }
     }
 
     /// Display logs line by line with realistic delays and progress bars
-    fn display_logs(&self, logs: &[String], exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        let mut rng = rand::thread_rng();
+    fn display_logs(&self, logs: &[String], ctx: &mut Context) -> io::Result<()> {
+        // Cargo's bar under the build output, counting the crates compiled so far
+        let build = ProgressBar::new(ProgressStyle::Cargo);
+        let building = Line::styled(Tone::Highlight, "    Building").bold();
+        let crates = logs.iter().filter(|log| is_compiling(log)).count() as u64;
+        let mut compiled = 0;
 
         for log in logs {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
             if log.contains("error:") || log.contains("Error") {
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    log.bright_red()
-                );
+                ctx.log(Tone::Error, log);
             } else if log.contains("warning:") {
-                println!("{} {}", LogGenerator::timestamp().dimmed(), log.yellow());
-            } else if log.contains("Compiling") {
-                let speed_category = rng.gen_range(0..10);
+                ctx.log(Tone::Warning, log);
+                ctx.stats.warnings += 1;
+            } else if is_compiling(log) {
+                let speed_category = ctx.rng.gen_range(0..10);
                 let duration = if speed_category < 3 {
-                    rng.gen_range(100..400)
+                    ctx.rng.gen_range(100..400)
                 } else if speed_category < 7 {
-                    rng.gen_range(400..1000)
+                    ctx.rng.gen_range(400..1000)
                 } else {
-                    rng.gen_range(1000..2500)
+                    ctx.rng.gen_range(1000..2500)
                 };
 
-                let progress = ProgressBar::new(ProgressStyle::Block);
-                progress.animate(
-                    &format!("{} {}", LogGenerator::timestamp().dimmed(), log.green()),
-                    duration,
-                    exit_check,
-                )?;
+                ctx.log(Tone::Success, log);
+                let name = log.split_whitespace().nth(1).unwrap_or_default();
+                build.draw_transfer(
+                    ctx,
+                    &building,
+                    Transfer::items(compiled, crates),
+                    Some(name),
+                );
+                ctx.sleep(duration);
+                compiled += 1;
             } else if log.contains("Downloading") || log.contains("Downloaded") {
-                println!("{} {}", LogGenerator::timestamp().dimmed(), log.cyan());
-                thread::sleep(Duration::from_millis(rng.gen_range(10..40)));
+                ctx.log(Tone::Accent, log);
+                ctx.sleep_range(10..40);
             } else if log.contains("Finished") {
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    log.bright_green().bold()
-                );
-                thread::sleep(Duration::from_millis(300));
+                ctx.log_line(Line::styled(Tone::Success, log).bold());
+                ctx.sleep(300);
             } else {
-                println!("{} {}", LogGenerator::timestamp().dimmed(), log);
-                thread::sleep(Duration::from_millis(rng.gen_range(20..80)));
+                ctx.log(Tone::Plain, log);
+                ctx.sleep_range(20..80);
             }
         }
 
@@ -73,124 +74,106 @@ impl DenoStage {
     }
 
     /// Prompt user to retry or abort
-    fn prompt_retry(&self) -> io::Result<bool> {
-        println!();
-        print!("{}", "Try again or abort? [1-2]: ".bright_yellow().bold());
-        io::stdout().flush()?;
+    fn prompt_retry(&self, ctx: &mut Context) -> io::Result<bool> {
+        ctx.blank();
+        ctx.prompt(Line::styled(Tone::Header, "Try again or abort? [1-2]: ").bold());
 
         loop {
-            if let Ok(Event::Key(key_event)) = event::read() {
-                match key_event.code {
-                    KeyCode::Char('1') => {
-                        println!("1");
-                        println!(
-                            "{} {}",
-                            LogGenerator::timestamp().dimmed(),
-                            "Retrying compilation...".bright_cyan()
-                        );
-                        thread::sleep(Duration::from_millis(1000));
-                        return Ok(true);
-                    }
-                    KeyCode::Char('2') => {
-                        println!("2");
-                        println!(
-                            "{} {}",
-                            LogGenerator::timestamp().dimmed(),
-                            "Aborting...".bright_red()
-                        );
-                        thread::sleep(Duration::from_millis(500));
-                        return Ok(false);
-                    }
-                    _ => {
-                        continue;
-                    }
+            if ctx.check_exit() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
+            }
+
+            // Nobody can answer an instant run or one without a keyboard, so it retries
+            let answer = if ctx.clock.is_instant() || !ctx.is_interactive() {
+                Some('1')
+            } else {
+                ctx.read_char(Duration::from_millis(100))
+            };
+
+            match answer {
+                Some('1') => {
+                    ctx.resolve(Line::styled(Tone::Plain, "1"));
+                    ctx.log_retry(Line::styled(Tone::Highlight, "Retrying compilation..."));
+                    ctx.sleep(1000);
+                    return Ok(true);
+                }
+                Some('2') => {
+                    ctx.resolve(Line::styled(Tone::Plain, "2"));
+                    ctx.log(Tone::Error, "Aborting...");
+                    ctx.sleep(500);
+                    return Ok(false);
                 }
+                _ => {}
             }
         }
     }
 }
 
+fn is_compiling(log: &str) -> bool {
+    log.contains("Compiling")
+}
+
 impl InstallationStage for DenoStage {
     fn name(&self) -> &'static str {
         "Deno Runtime Compilation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
-        let should_fail = rng.gen_bool(0.3);
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        let should_fail = ctx.rng.gen_bool(self.config.failure_chance);
 
         if should_fail {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Building Deno from source...".bright_white()
-            );
-            println!();
-
-            self.display_logs(self.deno_logs.error_logs(), exit_check)?;
-
-            println!();
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Build failed! The installation encountered errors.".bright_red()
+            ctx.log(Tone::Bright, "Building Deno from source...");
+            ctx.blank();
+
+            let logs = self
+                .deno_logs
+                .error_lines(self.config.log_mode, &mut ctx.rng);
+            self.display_logs(&logs, ctx)?;
+
+            ctx.blank();
+            ctx.log(
+                Tone::Error,
+                "Build failed! The installation encountered errors.",
             );
 
-            let retry = self.prompt_retry()?;
+            let retry = self.prompt_retry(ctx)?;
 
             if retry {
-                println!();
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "Rebuilding Deno from source...".bright_white()
-                );
-                println!();
-
-                self.display_logs(self.deno_logs.success_logs(), exit_check)?;
-
-                println!();
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "Build completed successfully!".bright_green().bold()
-                );
+                ctx.blank();
+                ctx.log(Tone::Bright, "Rebuilding Deno from source...");
+                ctx.blank();
+
+                let logs = self
+                    .deno_logs
+                    .success_lines(self.config.log_mode, &mut ctx.rng);
+                self.display_logs(&logs, ctx)?;
+
+                ctx.blank();
+                ctx.log_line(Line::styled(Tone::Success, "Build completed successfully!").bold());
+                ctx.stats.recovered_failures += 1;
             } else {
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "Skipping Deno installation...".dimmed()
-                );
+                ctx.log(Tone::Dim, "Skipping Deno installation...");
             }
         } else {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Building Deno from source...".bright_white()
-            );
-            println!();
+            ctx.log(Tone::Bright, "Building Deno from source...");
+            ctx.blank();
 
-            self.display_logs(self.deno_logs.success_logs(), exit_check)?;
+            let logs = self
+                .deno_logs
+                .success_lines(self.config.log_mode, &mut ctx.rng);
+            self.display_logs(&logs, ctx)?;
 
-            println!();
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Build completed successfully!".bright_green().bold()
-            );
+            ctx.blank();
+            ctx.log_line(Line::styled(Tone::Success, "Build completed successfully!").bold());
         }
 
-        thread::sleep(Duration::from_millis(500));
+        ctx.sleep(500);
         Ok(())
     }
 }
 
 impl Default for DenoStage {
     fn default() -> Self {
-        Self::new()
+        Self::new(DenoConfig::default(), DenoLogs::load())
     }
 }
@@ -1,48 +1,47 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::config::DriversConfig;
+use crate::context::Context;
 use crate::messages::DRIVERS;
-use colored::*;
-use rand::Rng;
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use crate::render::{Line, Tone};
+use std::io;
 
-pub struct DriversStage;
+/// Loads hardware drivers
+pub struct DriversStage {
+    config: DriversConfig,
+}
+
+impl DriversStage {
+    pub fn new(config: DriversConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for DriversStage {
     fn name(&self) -> &'static str {
         "Hardware Driver Installation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
         for (device, driver) in DRIVERS {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            print!(
-                "{} Detecting: {} ",
-                LogGenerator::timestamp().dimmed(),
-                device.bright_cyan()
+            ctx.log_pending(
+                Line::styled(Tone::Plain, "Detecting: ")
+                    .with(Tone::Highlight, device)
+                    .with(Tone::Plain, " "),
             );
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(rng.gen_range(400..900)));
+            ctx.sleep_range(self.config.detect_delay_range.clone());
 
-            println!("{}", "[FOUND]".bright_green());
-            println!(
-                "{}   └─ Loading driver: {}",
-                LogGenerator::timestamp().dimmed(),
-                driver.dimmed()
+            ctx.resolve(Line::styled(Tone::Success, "[FOUND]"));
+            ctx.log_line(
+                Line::styled(Tone::Plain, "  └─ Loading driver: ").with(Tone::Dim, driver),
             );
-            thread::sleep(Duration::from_millis(300));
+            ctx.sleep(self.config.load_delay);
         }
 
-        println!();
+        ctx.blank();
         Ok(())
     }
 }
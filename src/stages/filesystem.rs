@@ -1,164 +1,107 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::config::FilesystemConfig;
+use crate::context::Context;
+use crate::render::Tone;
 use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
-pub struct FilesystemStage;
+/// Creates an ext4 filesystem and checks it
+pub struct FilesystemStage {
+    config: FilesystemConfig,
+}
+
+impl FilesystemStage {
+    pub fn new(config: FilesystemConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for FilesystemStage {
     fn name(&self) -> &'static str {
         "Filesystem Operations"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.log(Tone::Bright, "Creating ext4 filesystem on /dev/sda2...");
+        ctx.sleep(600);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Creating ext4 filesystem on /dev/sda2...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(600));
-
-        let blocks = rng.gen_range(50000000..100000000);
+        let blocks = ctx.rng.gen_range(50000000..100000000);
         let inodes = blocks / 4;
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("mke2fs 1.45.5 (07-Jan-2020)").dimmed()
-        );
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
+        ctx.log(Tone::Dim, "mke2fs 1.45.5 (07-Jan-2020)");
+        ctx.log(
+            Tone::Dim,
             format!(
                 "Creating filesystem with {} 4k blocks and {} inodes",
                 blocks, inodes
-            )
-            .dimmed()
+            ),
         );
-        thread::sleep(Duration::from_millis(400));
+        ctx.sleep(400);
 
-        if exit_check() {
+        if ctx.check_exit() {
             return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
         }
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Filesystem UUID: 8f3e1a2b-4c5d-6e7f-8a9b-0c1d2e3f4a5b".dimmed()
-        );
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("Superblock backups stored on blocks:").dimmed()
+        ctx.log(
+            Tone::Dim,
+            "Filesystem UUID: 8f3e1a2b-4c5d-6e7f-8a9b-0c1d2e3f4a5b",
         );
+        ctx.log(Tone::Dim, "Superblock backups stored on blocks:");
 
         let backup_blocks = [32768, 98304, 163840, 229376, 294912];
         for block in &backup_blocks {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("        {}", block).dimmed()
-            );
-            thread::sleep(Duration::from_millis(100));
+            ctx.log(Tone::Dim, format!("        {}", block));
+            ctx.sleep(100);
         }
 
-        println!();
+        ctx.blank();
         let progress = ProgressBar::new(ProgressStyle::Equals);
         progress.animate(
             "Allocating group tables:",
-            rng.gen_range(2000..3000),
-            exit_check,
+            ctx.rng
+                .gen_range(self.config.group_tables_time_range.clone()),
+            ctx,
         )?;
 
         let progress = ProgressBar::new(ProgressStyle::Equals);
         progress.animate(
             "Writing inode tables:",
-            rng.gen_range(2500..4000),
-            exit_check,
+            ctx.rng
+                .gen_range(self.config.inode_tables_time_range.clone()),
+            ctx,
         )?;
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Creating journal (32768 blocks): ".dimmed()
-        );
-        thread::sleep(Duration::from_millis(rng.gen_range(800..1200)));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "done".bright_green()
-        );
+        ctx.log(Tone::Dim, "Creating journal (32768 blocks): ");
+        ctx.sleep_range(800..1200);
+        ctx.log(Tone::Success, "done");
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Writing superblocks and filesystem accounting information: ".dimmed()
-        );
-        thread::sleep(Duration::from_millis(rng.gen_range(600..1000)));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "done".bright_green()
+        ctx.log(
+            Tone::Dim,
+            "Writing superblocks and filesystem accounting information: ",
         );
-
-        println!();
-
-        if rng.gen_bool(0.4) {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Running filesystem check...".bright_white()
-            );
-            thread::sleep(Duration::from_millis(500));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "e2fsck 1.45.5 (07-Jan-2020)".dimmed()
-            );
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Pass 1: Checking inodes, blocks, and sizes".dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(800..1500)));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Pass 2: Checking directory structure".dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(600..1000)));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Pass 3: Checking directory connectivity".dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(400..800)));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Pass 4: Checking reference counts".dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(400..700)));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Pass 5: Checking group summary information".dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(300..600)));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "/dev/sda2: 11/2048000 files (0.0% non-contiguous), 200000/8192000 blocks"
-                    .bright_green()
+        ctx.sleep_range(600..1000);
+        ctx.log(Tone::Success, "done");
+
+        ctx.blank();
+
+        if ctx.rng.gen_bool(self.config.fsck_chance) {
+            ctx.log(Tone::Bright, "Running filesystem check...");
+            ctx.sleep(500);
+            ctx.log(Tone::Dim, "e2fsck 1.45.5 (07-Jan-2020)");
+            ctx.log(Tone::Dim, "Pass 1: Checking inodes, blocks, and sizes");
+            ctx.sleep_range(800..1500);
+            ctx.log(Tone::Dim, "Pass 2: Checking directory structure");
+            ctx.sleep_range(600..1000);
+            ctx.log(Tone::Dim, "Pass 3: Checking directory connectivity");
+            ctx.sleep_range(400..800);
+            ctx.log(Tone::Dim, "Pass 4: Checking reference counts");
+            ctx.sleep_range(400..700);
+            ctx.log(Tone::Dim, "Pass 5: Checking group summary information");
+            ctx.sleep_range(300..600);
+            ctx.log(
+                Tone::Success,
+                "/dev/sda2: 11/2048000 files (0.0% non-contiguous), 200000/8192000 blocks",
             );
         }
 
@@ -1,36 +1,37 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::config::InitramfsConfig;
+use crate::context::Context;
+use crate::render::Tone;
 use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
-pub struct InitramfsStage;
+/// Generates the initial RAM filesystem
+pub struct InitramfsStage {
+    config: InitramfsConfig,
+}
+
+impl InitramfsStage {
+    pub fn new(config: InitramfsConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for InitramfsStage {
     fn name(&self) -> &'static str {
         "Initial RAM Filesystem"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
-        let kernel_version = "5.4.0-42-generic";
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        let kernel_version = &self.config.kernel_version;
+        ctx.log(
+            Tone::Bright,
             format!(
                 "update-initramfs: Generating /boot/initrd.img-{}",
                 kernel_version
-            )
-            .bright_white()
+            ),
         );
-        thread::sleep(Duration::from_millis(800));
+        ctx.sleep(800);
 
         let modules = [
             "kernel/drivers/ata/libata.ko",
@@ -44,41 +45,29 @@ impl InstallationStage for InitramfsStage {
             "kernel/drivers/usb/core/usbcore.ko",
         ];
 
-        if exit_check() {
+        if ctx.check_exit() {
             return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
         }
 
         for module in &modules {
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("Adding module: {}", module).dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(100..250)));
+            ctx.log(Tone::Dim, format!("Adding module: {}", module));
+            ctx.sleep_range(self.config.module_delay_range.clone());
         }
 
-        println!();
+        ctx.blank();
         let progress = ProgressBar::new(ProgressStyle::Block);
         progress.animate(
             "Copying binaries and libraries:",
-            rng.gen_range(2000..3500),
-            exit_check,
+            ctx.rng.gen_range(self.config.copy_time_range.clone()),
+            ctx,
         )?;
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Creating initramfs image...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(rng.gen_range(1000..2000)));
+        ctx.blank();
+        ctx.log(Tone::Bright, "Creating initramfs image...");
+        ctx.sleep_range(self.config.image_time_range.clone());
 
-        let size_mb = rng.gen_range(25..45);
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("Image size: {}MB", size_mb).bright_green()
-        );
+        let size_mb = ctx.rng.gen_range(25..45);
+        ctx.log(Tone::Success, format!("Image size: {}MB", size_mb));
 
         Ok(())
     }
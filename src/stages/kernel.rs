@@ -1,30 +1,30 @@
 use super::InstallationStage;
+use crate::config::KernelConfig;
+use crate::context::Context;
 use crate::kernel_logs::KernelLogs;
-use crate::log_generator::LogGenerator;
+use crate::render::{Line, Tone};
 use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
+/// Compiles the Linux kernel
 pub struct KernelStage {
+    config: KernelConfig,
     kernel_logs: KernelLogs,
 }
 
 impl KernelStage {
-    pub fn new() -> Self {
+    pub fn new(config: KernelConfig, kernel_logs: KernelLogs) -> Self {
         Self {
-            kernel_logs: KernelLogs::load(),
+            config,
+            kernel_logs,
         }
     }
 
     /// Display all kernel logs with progress bars for initialization steps
-    fn display_logs(&self, logs: &[String], exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        let mut rng = rand::thread_rng();
-
+    fn display_logs(&self, logs: &[String], ctx: &mut Context) -> io::Result<()> {
         for log in logs {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
@@ -35,37 +35,33 @@ impl KernelStage {
                 || log.contains("Probing");
 
             if should_show_progress {
-                let speed_category = rng.gen_range(0..10);
+                let speed_category = ctx.rng.gen_range(0..10);
                 let duration = if speed_category < 3 {
-                    rng.gen_range(600..900)
+                    ctx.rng.gen_range(600..900)
                 } else if speed_category < 7 {
-                    rng.gen_range(900..1200)
+                    ctx.rng.gen_range(900..1200)
                 } else {
-                    rng.gen_range(1200..3500)
+                    ctx.rng.gen_range(1200..3500)
                 };
 
                 let progress = ProgressBar::new(ProgressStyle::Block);
-                progress.animate(
-                    &format!(
-                        "{} {}",
-                        LogGenerator::timestamp().dimmed(),
-                        log.bright_cyan()
-                    ),
+                progress.animate_line(
+                    ctx.stamp(Line::styled(Tone::Highlight, log)),
                     duration,
-                    exit_check,
+                    ctx,
                 )?;
             } else {
-                let speed_category = rng.gen_range(0..10);
+                let speed_category = ctx.rng.gen_range(0..10);
                 let delay = if speed_category < 4 {
-                    rng.gen_range(25..50)
+                    ctx.rng.gen_range(25..50)
                 } else if speed_category < 8 {
-                    rng.gen_range(50..100)
+                    ctx.rng.gen_range(50..100)
                 } else {
-                    rng.gen_range(100..400)
+                    ctx.rng.gen_range(100..400)
                 };
 
-                println!("{} {}", LogGenerator::timestamp().dimmed(), log.dimmed());
-                thread::sleep(Duration::from_millis(delay));
+                ctx.log(Tone::Dim, log);
+                ctx.sleep(delay);
             }
         }
 
@@ -78,33 +74,26 @@ impl InstallationStage for KernelStage {
         "Linux Kernel Compilation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Building Linux kernel from source...".bright_white()
-        );
-        println!();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.log(Tone::Bright, "Building Linux kernel from source...");
+        ctx.blank();
 
-        self.display_logs(self.kernel_logs.all_logs(), exit_check)?;
+        let count = self.kernel_logs.all_logs().len();
+        let logs = self
+            .kernel_logs
+            .lines(self.config.log_mode, count, &mut ctx.rng);
+        self.display_logs(&logs, ctx)?;
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Kernel build completed successfully!".bright_green().bold()
-        );
+        ctx.blank();
+        ctx.log_line(Line::styled(Tone::Success, "Kernel build completed successfully!").bold());
 
-        thread::sleep(Duration::from_millis(500));
+        ctx.sleep(500);
         Ok(())
     }
 }
 
 impl Default for KernelStage {
     fn default() -> Self {
-        Self::new()
+        Self::new(KernelConfig::default(), KernelLogs::load())
     }
 }
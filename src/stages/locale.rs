@@ -1,87 +1,54 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::config::LocaleConfig;
+use crate::context::Context;
+use crate::render::Tone;
 use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
-pub struct LocaleStage;
+/// Generates locales
+pub struct LocaleStage {
+    config: LocaleConfig,
+}
+
+impl LocaleStage {
+    pub fn new(config: LocaleConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for LocaleStage {
     fn name(&self) -> &'static str {
         "Localization Configuration"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Generating locales...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(500));
-
-        let locales = [
-            "en_US.UTF-8",
-            "en_GB.UTF-8",
-            "de_DE.UTF-8",
-            "fr_FR.UTF-8",
-            "es_ES.UTF-8",
-            "ja_JP.UTF-8",
-            "zh_CN.UTF-8",
-        ];
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.log(Tone::Bright, "Generating locales...");
+        ctx.sleep(500);
 
-        for locale in &locales {
-            if exit_check() {
+        for locale in &self.config.locales {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("Generating locale {}... ", locale).dimmed()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(300..700)));
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "done".bright_green()
-            );
+            ctx.log(Tone::Dim, format!("Generating locale {}... ", locale));
+            ctx.sleep_range(self.config.locale_delay_range.clone());
+            ctx.log(Tone::Success, "done");
         }
 
-        println!();
+        ctx.blank();
         let progress = ProgressBar::new(ProgressStyle::Hash);
         progress.animate(
             "Building locale archive:",
-            rng.gen_range(2000..3000),
-            exit_check,
+            ctx.rng.gen_range(self.config.archive_time_range.clone()),
+            ctx,
         )?;
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Configuring timezone...".bright_white()
-        );
-        let timezones = [
-            "America/New_York",
-            "America/Los_Angeles",
-            "Europe/London",
-            "Europe/Berlin",
-            "Asia/Tokyo",
-        ];
-        let timezone = timezones[rng.gen_range(0..timezones.len())];
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Timezone set to: {}", timezone).dimmed()
-        );
-        thread::sleep(Duration::from_millis(600));
+        ctx.blank();
+        ctx.log(Tone::Bright, "Configuring timezone...");
+        let timezones = &self.config.timezones;
+        let timezone = &timezones[ctx.rng.gen_range(0..timezones.len())];
+        ctx.log(Tone::Dim, format!("  Timezone set to: {}", timezone));
+        ctx.sleep(600);
 
         Ok(())
     }
@@ -15,11 +15,14 @@ mod locale;
 mod network;
 mod packages;
 mod retro;
+mod script;
 mod services;
 mod system;
 mod xorg;
 
-use crate::cli::Stage;
+use crate::cli::{Stage, StageId};
+use crate::context::Context;
+use crate::render::Tone;
 use std::io;
 
 pub use ai::AiStage;
@@ -39,48 +42,95 @@ pub use locale::LocaleStage;
 pub use network::NetworkStage;
 pub use packages::PackagesStage;
 pub use retro::RetroSoftwareStage;
+pub use script::{load_scripts, ScriptedStage, StageScript};
 pub use services::ServicesStage;
 pub use system::SystemStage;
 pub use xorg::XorgStage;
 
 /// Common trait for all installation stages
+///
+/// A stage writes its output through the `Context` it runs in and should
+/// return early with an `Interrupted` error once `Context::check_exit` says so.
 pub trait InstallationStage {
-    fn name(&self) -> &'static str;
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()>;
+    /// Title shown when the stage starts
+    fn name(&self) -> &str;
+
+    /// Color of the stage's title
+    fn tone(&self) -> Tone {
+        Tone::Header
+    }
+
+    /// Plays the stage out, which may take minutes of simulated time
+    fn run(&self, ctx: &mut Context) -> io::Result<()>;
 }
 
 use crate::config::SimulationConfig;
 
 /// Get selected installation stages in order
-pub fn selected_stages(stages: &[Stage]) -> Vec<Box<dyn InstallationStage>> {
-    let mut result = Vec::new();
-    let config = SimulationConfig::default();
-
+///
+/// Stages added with `Installer::add_stage` are not built from the config
+/// and are left out. A scripted stage that is not among the config's scripts
+/// is an error.
+pub fn selected_stages(
+    stages: &[StageId],
+    config: &SimulationConfig,
+) -> io::Result<Vec<Box<dyn InstallationStage>>> {
+    let mut selected: Vec<Box<dyn InstallationStage>> = Vec::new();
     for stage in stages {
-        let stage_impl: Box<dyn InstallationStage> = match stage {
-            Stage::Bios => Box::new(BiosStage::new(config.bios.clone())),
-            Stage::Boot => Box::new(BootStage::new(config.boot.clone())),
-            Stage::Bootloader => Box::new(BootloaderStage::new(config.bootloader.clone())),
-            Stage::Filesystem => Box::new(FilesystemStage),
-            Stage::System => Box::new(SystemStage),
-            Stage::Network => Box::new(NetworkStage),
-            Stage::Drivers => Box::new(DriversStage),
-            Stage::Initramfs => Box::new(InitramfsStage),
-            Stage::Packages => Box::new(PackagesStage),
-            Stage::Kernel => Box::new(KernelStage::new()),
-            Stage::Compilation => Box::new(CompilationStage::new()),
-            Stage::Deno => Box::new(DenoStage::new()),
-            Stage::Database => Box::new(DatabaseStage),
-            Stage::Xorg => Box::new(XorgStage),
-            Stage::Services => Box::new(ServicesStage),
-            Stage::Retro => Box::new(RetroSoftwareStage),
-            Stage::Locale => Box::new(LocaleStage),
-            Stage::Container => Box::new(ContainerStage::new(config.container.clone())),
-            Stage::Ai => Box::new(AiStage::new(config.ai.clone())),
-            Stage::Cloud => Box::new(CloudStage::new(config.cloud.clone())),
-        };
-        result.push(stage_impl);
+        match stage {
+            StageId::Builtin(stage) => selected.push(builtin_stage(*stage, config)),
+            StageId::Script(name) => {
+                let script = config
+                    .scripts
+                    .iter()
+                    .find(|script| script.id == *name)
+                    .ok_or_else(|| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("unknown stage `{}`, no script of that name is loaded", name),
+                        )
+                    })?;
+                selected.push(Box::new(ScriptedStage::new(script.clone())));
+            }
+            StageId::Custom(_) => {}
+        }
     }
+    Ok(selected)
+}
 
-    result
+fn builtin_stage(stage: Stage, config: &SimulationConfig) -> Box<dyn InstallationStage> {
+    match stage {
+        Stage::Bios => Box::new(BiosStage::new(config.bios.clone())),
+        Stage::Boot => Box::new(BootStage::new(
+            config.boot.clone(),
+            config.logs.kernel.clone(),
+        )),
+        Stage::Bootloader => Box::new(BootloaderStage::new(config.bootloader.clone())),
+        Stage::Filesystem => Box::new(FilesystemStage::new(config.filesystem.clone())),
+        Stage::System => Box::new(SystemStage::new(config.system.clone())),
+        Stage::Network => Box::new(NetworkStage::new(config.network.clone())),
+        Stage::Drivers => Box::new(DriversStage::new(config.drivers.clone())),
+        Stage::Initramfs => Box::new(InitramfsStage::new(config.initramfs.clone())),
+        Stage::Packages => Box::new(PackagesStage::new(config.packages.clone())),
+        Stage::Kernel => Box::new(KernelStage::new(
+            config.kernel.clone(),
+            config.logs.kernel.clone(),
+        )),
+        Stage::Compilation => Box::new(CompilationStage::new(
+            config.compilation.clone(),
+            config.logs.build.clone(),
+        )),
+        Stage::Deno => Box::new(DenoStage::new(
+            config.deno.clone(),
+            config.logs.deno.clone(),
+        )),
+        Stage::Database => Box::new(DatabaseStage::new(config.database.clone())),
+        Stage::Xorg => Box::new(XorgStage),
+        Stage::Services => Box::new(ServicesStage::new(config.services.clone())),
+        Stage::Retro => Box::new(RetroSoftwareStage::new(config.retro.clone())),
+        Stage::Locale => Box::new(LocaleStage::new(config.locale.clone())),
+        Stage::Container => Box::new(ContainerStage::new(config.container.clone())),
+        Stage::Ai => Box::new(AiStage::new(config.ai.clone())),
+        Stage::Cloud => Box::new(CloudStage::new(config.cloud.clone())),
+    }
 }
@@ -1,100 +1,76 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
-use crate::ui::Spinner;
-use colored::*;
+use crate::config::NetworkConfig;
+use crate::context::Context;
+use crate::render::Tone;
+use crate::ui::{Spinner, SpinnerStyle};
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
-pub struct NetworkStage;
+/// Brings up network interfaces and DNS
+pub struct NetworkStage {
+    config: NetworkConfig,
+}
+
+impl NetworkStage {
+    pub fn new(config: NetworkConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for NetworkStage {
     fn name(&self) -> &'static str {
         "Network Configuration"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-        let mut spinner = Spinner::new();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        let mut spinner = Spinner::with_style(SpinnerStyle::Arc);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Configuring network interfaces...".bright_white()
-        );
-        thread::sleep(Duration::from_millis(500));
+        ctx.log(Tone::Bright, "Configuring network interfaces...");
+        ctx.sleep(500);
 
-        let interfaces = ["eth0", "enp0s3", "wlan0"];
-        let interface = interfaces[rng.gen_range(0..interfaces.len())];
+        let interfaces = &self.config.interfaces;
+        let interface = &interfaces[ctx.rng.gen_range(0..interfaces.len())];
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Interface: {}", interface).dimmed()
-        );
-        thread::sleep(Duration::from_millis(300));
+        ctx.log(Tone::Dim, format!("  Interface: {}", interface));
+        ctx.sleep(300);
 
-        if exit_check() {
+        if ctx.check_exit() {
             return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
         }
 
         spinner.animate(
             &format!("Requesting DHCP lease on {}...", interface),
-            2000,
-            exit_check,
+            self.config.dhcp_time,
+            ctx,
         )?;
 
         let ip = format!(
             "192.168.{}.{}",
-            rng.gen_range(0..255),
-            rng.gen_range(2..254)
+            ctx.rng.gen_range(0..255),
+            ctx.rng.gen_range(2..254)
         );
-        let gateway = format!("192.168.{}.1", rng.gen_range(0..255));
+        let gateway = format!("192.168.{}.1", ctx.rng.gen_range(0..255));
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  IP Address: {}", ip).bright_green()
-        );
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Netmask: 255.255.255.0").dimmed()
-        );
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Gateway: {}", gateway).dimmed()
-        );
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  DNS: 8.8.8.8, 8.8.4.4").dimmed()
-        );
-        thread::sleep(Duration::from_millis(600));
+        ctx.log(Tone::Success, format!("  IP Address: {}", ip));
+        ctx.log(Tone::Dim, "  Netmask: 255.255.255.0");
+        ctx.log(Tone::Dim, format!("  Gateway: {}", gateway));
+        ctx.log(Tone::Dim, format!("  DNS: {}", self.config.dns_servers));
+        ctx.sleep(600);
 
-        println!();
-        spinner.animate("Configuring DNS resolution...", 1200, exit_check)?;
+        ctx.blank();
+        spinner.animate("Configuring DNS resolution...", self.config.dns_time, ctx)?;
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Updating /etc/resolv.conf".dimmed()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.log(Tone::Dim, "Updating /etc/resolv.conf");
+        ctx.sleep(400);
 
-        if rng.gen_bool(0.3) {
-            println!();
-            spinner.animate("Testing network connectivity...", 1500, exit_check)?;
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Network is reachable".bright_green()
-            );
+        if ctx.rng.gen_bool(self.config.connectivity_test_chance) {
+            ctx.blank();
+            spinner.animate(
+                "Testing network connectivity...",
+                self.config.connectivity_test_time,
+                ctx,
+            )?;
+            ctx.log(Tone::Success, "Network is reachable");
         }
 
         Ok(())
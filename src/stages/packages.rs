@@ -1,71 +1,84 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::config::PackagesConfig;
+use crate::context::Context;
 use crate::messages::PACKAGES;
+use crate::render::{Line, Tone};
+use crate::ui::progress::apt_size;
 use crate::ui::{ProgressBar, ProgressStyle, Spinner};
-use colored::*;
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
-pub struct PackagesStage;
+/// Downloads and unpacks packages with apt
+pub struct PackagesStage {
+    config: PackagesConfig,
+}
+
+impl PackagesStage {
+    pub fn new(config: PackagesConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for PackagesStage {
     fn name(&self) -> &'static str {
         "Package Installation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
         let mut spinner = Spinner::new();
-        spinner.animate("Reading package lists...", 1200, exit_check)?;
-        spinner.animate("Building dependency tree...", 1500, exit_check)?;
-
-        let mut rng = rand::thread_rng();
+        spinner.animate("Reading package lists...", self.config.read_lists_time, ctx)?;
+        spinner.animate(
+            "Building dependency tree...",
+            self.config.dependency_tree_time,
+            ctx,
+        )?;
 
-        for package in PACKAGES {
-            if exit_check() {
+        for (i, package) in PACKAGES.iter().enumerate() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            println!(
-                "{} Unpacking {}...",
-                LogGenerator::timestamp().dimmed(),
-                package.bright_white()
-            );
-
-            let size_kb: u32 = rng.gen_range(512..8192);
-            let speed_kb: u32 = rng.gen_range(64..512);
+            let size_kb: u32 = ctx.rng.gen_range(self.config.size_kb_range.clone());
+            let speed_kb: u32 = ctx.rng.gen_range(self.config.speed_kb_range.clone());
+            let bytes = size_kb as u64 * 1024;
 
-            let progress = ProgressBar::new(ProgressStyle::Hash);
-            progress.animate(
-                &format!("  ({:.1}MB @ {}KB/s)", size_kb as f32 / 1024.0, speed_kb),
-                rng.gen_range(1000..2500),
-                exit_check,
+            ctx.log_line(
+                Line::styled(Tone::Plain, format!("Get:{} ", i + 1))
+                    .with(
+                        Tone::Dim,
+                        "http://archive.ubuntu.com/ubuntu jammy/main amd64 ",
+                    )
+                    .with(Tone::Bright, *package)
+                    .with(Tone::Plain, format!(" [{}]", apt_size(bytes))),
+            );
+            let progress = ProgressBar::new(ProgressStyle::Apt);
+            progress.animate_transfer(
+                Line::styled(Tone::Plain, *package),
+                bytes,
+                size_kb as u64 * 1000 / speed_kb as u64,
+                ctx,
             )?;
+            ctx.stats.downloaded_bytes += bytes;
 
-            if rng.gen_bool(0.4) {
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    format!("Setting up {}...", package).dimmed()
-                );
-                thread::sleep(Duration::from_millis(300));
+            ctx.log_line(
+                Line::styled(Tone::Plain, "Unpacking ")
+                    .with(Tone::Bright, *package)
+                    .with(Tone::Plain, "..."),
+            );
+            ctx.sleep_range(self.config.unpack_time_range.clone());
+
+            if ctx.rng.gen_bool(self.config.setup_chance) {
+                ctx.log(Tone::Dim, format!("Setting up {}...", package));
+                ctx.sleep(300);
             }
         }
 
-        println!(
-            "\n{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Processing triggers for shared libraries...".dimmed()
-        );
-        thread::sleep(Duration::from_millis(800));
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "ldconfig: /usr/lib/x86_64-linux-gnu/libcrypto.so.1.1".dimmed()
+        ctx.blank();
+        ctx.log(Tone::Dim, "Processing triggers for shared libraries...");
+        ctx.sleep(800);
+        ctx.log(
+            Tone::Dim,
+            "ldconfig: /usr/lib/x86_64-linux-gnu/libcrypto.so.1.1",
         );
 
         Ok(())
@@ -1,69 +1,70 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::config::RetroConfig;
+use crate::context::Context;
 use crate::messages::RETRO_SOFTWARE;
-use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
+use crate::render::{Line, Tone};
+use crate::ui::{ProgressBar, ProgressStyle, Spinner, SpinnerStyle};
 use rand::Rng;
 use std::io;
-use std::thread;
-use std::time::Duration;
 
-pub struct RetroSoftwareStage;
+/// Installs software from the nineties
+pub struct RetroSoftwareStage {
+    config: RetroConfig,
+}
+
+impl RetroSoftwareStage {
+    pub fn new(config: RetroConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for RetroSoftwareStage {
     fn name(&self) -> &'static str {
         "Retro Software Installation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
         for (name, version, size_kb) in RETRO_SOFTWARE {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            println!(
-                "\n{} {} {} {}",
-                LogGenerator::timestamp().dimmed(),
-                "Installing".bright_white(),
-                name.bright_cyan().bold(),
-                format!("v{}", version).dimmed()
+            ctx.blank();
+            ctx.log_line(
+                Line::styled(Tone::Bright, "Installing")
+                    .with(Tone::Plain, " ")
+                    .with(Tone::Highlight, name)
+                    .bold()
+                    .with(Tone::Plain, " ")
+                    .with(Tone::Dim, format!("v{}", version)),
             );
 
-            if rng.gen_bool(0.2) {
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "Checking for previous installation...".dimmed()
-                );
-                thread::sleep(Duration::from_millis(500));
+            if ctx.rng.gen_bool(self.config.previous_install_chance) {
+                ctx.log(Tone::Dim, "Checking for previous installation...");
+                ctx.sleep(500);
             }
 
+            let file = format!("{}-{}.exe", name.to_lowercase().replace(' ', "-"), version);
+            ProgressBar::new(ProgressStyle::Wget).animate_transfer(
+                Line::styled(Tone::Plain, file),
+                *size_kb as u64 * 1024,
+                ctx.rng.gen_range(self.config.download_time_range.clone()),
+                ctx,
+            )?;
+            ctx.stats.downloaded_bytes += *size_kb as u64 * 1024;
+
             let progress = ProgressBar::new(ProgressStyle::Block);
             progress.animate(
                 &format!("  Extracting files ({:.1}MB):", *size_kb as f32 / 1024.0),
-                rng.gen_range(2000..4000),
-                exit_check,
+                ctx.rng.gen_range(self.config.extract_time_range.clone()),
+                ctx,
             )?;
 
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                "  Creating shortcuts...".dimmed()
-            );
-            thread::sleep(Duration::from_millis(400));
+            Spinner::with_style(SpinnerStyle::Dos).animate("Creating shortcuts...", 400, ctx)?;
 
-            if rng.gen_bool(0.3) {
-                println!(
-                    "{} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "  Registering file associations...".dimmed()
-                );
-                thread::sleep(Duration::from_millis(300));
+            if ctx.rng.gen_bool(self.config.file_associations_chance) {
+                ctx.log(Tone::Dim, "  Registering file associations...");
+                ctx.sleep(300);
             }
         }
 
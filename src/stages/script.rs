@@ -0,0 +1,389 @@
+use super::InstallationStage;
+use crate::cli::Stage;
+use crate::config::{config_dir, non_empty, probability};
+use crate::context::Context;
+use crate::render::{Line, Tone};
+use crate::ui::{ProgressBar, ProgressStyle, Spinner, SpinnerStyle};
+use clap::ValueEnum;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+/// A stage described by a data file instead of code
+///
+/// ```toml
+/// name = "Corporate VPN Client"
+///
+/// [[steps]]
+/// type = "log"
+/// text = "Connecting to vpn.example.com..."
+///
+/// [[steps]]
+/// type = "progress"
+/// text = "Downloading client profile"
+/// ms = [1000, 3000]
+/// ```
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct StageScript {
+    /// Name the stage is picked by, taken from the file name
+    #[serde(skip)]
+    pub id: String,
+    /// Title shown when the stage starts
+    pub name: String,
+    #[serde(default = "header_tone")]
+    pub tone: Tone,
+    pub steps: Vec<Step>,
+}
+
+/// One thing a stage script does, in the `type` key of each `[[steps]]` entry
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum Step {
+    /// A timestamped log line
+    Log {
+        text: String,
+        #[serde(default = "plain_tone")]
+        tone: Tone,
+    },
+    /// A line without a timestamp
+    Print {
+        text: String,
+        #[serde(default = "plain_tone")]
+        tone: Tone,
+    },
+    Blank,
+    Sleep {
+        #[serde(deserialize_with = "delay")]
+        ms: Range<u64>,
+    },
+    Spinner {
+        text: String,
+        #[serde(default)]
+        style: SpinnerStyle,
+        #[serde(deserialize_with = "delay")]
+        ms: Range<u64>,
+    },
+    /// A progress bar, counting up to `size` bytes (or units for `cargo`) if given
+    Progress {
+        text: String,
+        #[serde(default)]
+        style: ProgressStyle,
+        size: Option<u64>,
+        #[serde(deserialize_with = "delay")]
+        ms: Range<u64>,
+    },
+    /// Logs `count` different entries of `items`, picked at random
+    Pick {
+        #[serde(deserialize_with = "non_empty")]
+        items: Vec<String>,
+        #[serde(default = "one")]
+        count: usize,
+        #[serde(default = "plain_tone")]
+        tone: Tone,
+        #[serde(default = "pick_delay", deserialize_with = "delay")]
+        ms: Range<u64>,
+    },
+    /// Fails with the given chance, then carries on after `recover`
+    Fail {
+        #[serde(deserialize_with = "probability")]
+        chance: f64,
+        error: String,
+        recover: String,
+        #[serde(default = "fail_delay", deserialize_with = "delay")]
+        ms: Range<u64>,
+    },
+}
+
+fn header_tone() -> Tone {
+    Tone::Header
+}
+
+fn plain_tone() -> Tone {
+    Tone::Plain
+}
+
+fn one() -> usize {
+    1
+}
+
+fn pick_delay() -> Range<u64> {
+    50..300
+}
+
+fn fail_delay() -> Range<u64> {
+    1000..1001
+}
+
+/// Deserializes milliseconds given either as a number or as `[min, max]`
+fn delay<'de, D>(deserializer: D) -> Result<Range<u64>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Delay {
+        Fixed(u64),
+        Range([u64; 2]),
+    }
+
+    match Delay::deserialize(deserializer)? {
+        Delay::Fixed(ms) => {
+            // Kept one below the largest value, so that the range has room
+            let ms = ms.min(u64::MAX - 1);
+            Ok(ms..ms + 1)
+        }
+        Delay::Range([min, max]) if min < max => Ok(min..max),
+        Delay::Range([min, max]) => Err(de::Error::custom(format!(
+            "range [{}, {}] is empty, expected [min, max] with min < max",
+            min, max
+        ))),
+    }
+}
+
+impl StageScript {
+    /// Reads a stage script, treating `.json` files as JSON and anything else as TOML
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        let invalid = |message: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("invalid stage script {}: {}", path.display(), message),
+            )
+        };
+
+        let content = fs::read_to_string(path).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot read stage script {}: {}", path.display(), e),
+            )
+        })?;
+
+        let mut script: Self = if path.extension().is_some_and(|ext| ext == "json") {
+            let mut deserializer = serde_json::Deserializer::from_str(&content);
+            serde_path_to_error::deserialize(&mut deserializer)
+                .map_err(|e| invalid(format!("`{}`: {}", e.path(), e.inner())))?
+        } else {
+            serde_path_to_error::deserialize(toml::Deserializer::new(&content))
+                .map_err(|e| invalid(format!("`{}`: {}", e.path(), e.inner().message())))?
+        };
+
+        script.id = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        if Stage::from_str(&script.id, false).is_ok() {
+            return Err(invalid(format!(
+                "`{}` is already the name of a built-in stage",
+                script.id
+            )));
+        }
+        Ok(script)
+    }
+}
+
+/// Loads every `.toml` and `.json` stage script in `dir`, sorted by name
+///
+/// Without a directory, `~/.config/install-nothing/stages` is used if it exists.
+pub fn load_scripts(dir: Option<&Path>) -> io::Result<Vec<StageScript>> {
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => match config_dir().map(|dir| dir.join("stages")) {
+            Some(dir) if dir.is_dir() => dir,
+            _ => return Ok(Vec::new()),
+        },
+    };
+
+    let entries = fs::read_dir(&dir).map_err(|e| {
+        io::Error::new(
+            e.kind(),
+            format!("cannot read stage scripts in {}: {}", dir.display(), e),
+        )
+    })?;
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "toml" || ext == "json")
+        })
+        .collect();
+    paths.sort();
+
+    let mut scripts: Vec<StageScript> = Vec::with_capacity(paths.len());
+    for path in paths {
+        let script = StageScript::from_file(&path)?;
+        if scripts.iter().any(|other| other.id == script.id) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("stage script `{}` is defined twice", script.id),
+            ));
+        }
+        scripts.push(script);
+    }
+    Ok(scripts)
+}
+
+/// A stage that plays a `StageScript`
+pub struct ScriptedStage {
+    script: StageScript,
+}
+
+impl ScriptedStage {
+    pub fn new(script: StageScript) -> Self {
+        Self { script }
+    }
+}
+
+impl InstallationStage for ScriptedStage {
+    fn name(&self) -> &str {
+        &self.script.name
+    }
+
+    fn tone(&self) -> Tone {
+        self.script.tone
+    }
+
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
+        for step in &self.script.steps {
+            if ctx.check_exit() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
+            }
+
+            match step {
+                Step::Log { text, tone } => ctx.log(*tone, text),
+                Step::Print { text, tone } => ctx.print(*tone, text),
+                Step::Blank => ctx.blank(),
+                Step::Sleep { ms } => ctx.sleep_range(ms.clone()),
+                Step::Spinner { text, style, ms } => {
+                    let ms = ctx.rng.gen_range(ms.clone());
+                    Spinner::with_style(*style).animate(text, ms, ctx)?;
+                }
+                Step::Progress {
+                    text,
+                    style,
+                    size,
+                    ms,
+                } => {
+                    let ms = ctx.rng.gen_range(ms.clone());
+                    let progress = ProgressBar::new(*style);
+                    match size {
+                        Some(size) => {
+                            let label = Line::styled(Tone::Bright, text);
+                            progress.animate_transfer(label, *size, ms, ctx)?;
+                        }
+                        None => progress.animate(text, ms, ctx)?,
+                    }
+                }
+                Step::Pick {
+                    items,
+                    count,
+                    tone,
+                    ms,
+                } => {
+                    let picked: Vec<String> = items
+                        .choose_multiple(&mut ctx.rng, *count)
+                        .cloned()
+                        .collect();
+                    for item in picked {
+                        ctx.log(*tone, item);
+                        ctx.sleep_range(ms.clone());
+                    }
+                }
+                Step::Fail {
+                    chance,
+                    error,
+                    recover,
+                    ms,
+                } => {
+                    if ctx.rng.gen_bool(*chance) {
+                        ctx.log_line(Line::styled(Tone::Plain, error).stamp_tone(Tone::Error));
+                        ctx.sleep_range(ms.clone());
+                        ctx.log_line(Line::styled(Tone::Plain, recover).stamp_tone(Tone::Warning));
+                        ctx.stats.recovered_failures += 1;
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> Result<StageScript, String> {
+        toml::from_str(content).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_parse_steps() {
+        let script = parse(
+            r#"
+            name = "Corporate VPN Client"
+            tone = "info"
+
+            [[steps]]
+            type = "log"
+            text = "Connecting..."
+
+            [[steps]]
+            type = "progress"
+            text = "Downloading profile"
+            style = "hash"
+            ms = [1000, 3000]
+
+            [[steps]]
+            type = "fail"
+            chance = 0.5
+            error = "Error: handshake timed out"
+            recover = "Retrying over TCP..."
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(script.tone, Tone::Info);
+        assert_eq!(script.steps.len(), 3);
+        assert!(matches!(
+            &script.steps[1],
+            Step::Progress { ms, style: ProgressStyle::Hash, .. } if *ms == (1000..3000)
+        ));
+        assert!(matches!(&script.steps[2], Step::Fail { ms, .. } if *ms == (1000..1001)));
+
+        // Only JSON goes that high
+        let script: StageScript = serde_json::from_str(&format!(
+            r#"{{ "name": "Long", "steps": [{{ "type": "sleep", "ms": {} }}] }}"#,
+            u64::MAX
+        ))
+        .unwrap();
+        assert!(matches!(&script.steps[0], Step::Sleep { ms } if !ms.is_empty()));
+    }
+
+    #[test]
+    fn test_reject_invalid_steps() {
+        let steps = [
+            r#"type = "dance""#,
+            r#"type = "log""#,
+            r#"type = "log"
+               text = "hi"
+               colour = "red""#,
+            r#"type = "sleep"
+               ms = [500, 100]"#,
+            r#"type = "fail"
+               chance = 2.0
+               error = "x"
+               recover = "y""#,
+        ];
+        for step in steps {
+            let content = format!("name = \"Test\"\n[[steps]]\n{}", step);
+            assert!(parse(&content).is_err(), "accepted {}", step);
+        }
+    }
+}
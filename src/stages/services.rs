@@ -1,24 +1,26 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
-use colored::*;
-use rand::Rng;
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use crate::config::ServicesConfig;
+use crate::context::Context;
+use crate::render::{Line, Tone};
+use std::io;
 
-pub struct ServicesStage;
+/// Enables and starts system services
+pub struct ServicesStage {
+    config: ServicesConfig,
+}
+
+impl ServicesStage {
+    pub fn new(config: ServicesConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for ServicesStage {
     fn name(&self) -> &'static str {
         "System Services Configuration"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
         let services = [
             ("NetworkManager.service", "Network Manager"),
             ("systemd-resolved.service", "Network Name Resolution"),
@@ -35,44 +37,31 @@ impl InstallationStage for ServicesStage {
             ("apache2.service", "The Apache HTTP Server"),
         ];
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Starting system services...".bright_white()
-        );
-        println!();
+        ctx.log(Tone::Bright, "Starting system services...");
+        ctx.blank();
 
         for (_service, description) in &services {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            print!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("[ ** ] Starting {}...", description).bright_cyan()
-            );
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(rng.gen_range(300..800)));
-            print!("\r");
-            println!(
-                "{} {}",
-                LogGenerator::timestamp().dimmed(),
-                format!("[ OK ] Started {}.", description).bright_green()
-            );
-            thread::sleep(Duration::from_millis(rng.gen_range(100..300)));
+            ctx.log_transient(Line::styled(
+                Tone::Highlight,
+                format!("[ ** ] Starting {}...", description),
+            ));
+            ctx.sleep_range(self.config.start_delay_range.clone());
+            ctx.log(Tone::Success, format!("[ OK ] Started {}.", description));
+            ctx.sleep_range(self.config.settle_delay_range.clone());
         }
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
+        ctx.blank();
+        ctx.log(
+            Tone::Dim,
             format!(
                 "Loaded {} services, {} active",
                 services.len(),
                 services.len()
-            )
-            .dimmed()
+            ),
         );
 
         Ok(())
@@ -1,43 +1,46 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::config::SystemConfig;
+use crate::context::Context;
 use crate::messages::SYSTEM_COMPONENTS;
+use crate::render::{Line, Tone};
 use crate::ui::{ProgressBar, ProgressStyle};
-use colored::*;
 use rand::Rng;
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use std::io;
 
-pub struct SystemStage;
+/// Installs core system components
+pub struct SystemStage {
+    config: SystemConfig,
+}
+
+impl SystemStage {
+    pub fn new(config: SystemConfig) -> Self {
+        Self { config }
+    }
+}
 
 impl InstallationStage for SystemStage {
     fn name(&self) -> &'static str {
         "System Component Installation"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
-
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
         for component in SYSTEM_COMPONENTS {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            print!("{}   - {} ", LogGenerator::timestamp().dimmed(), component);
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(rng.gen_range(300..800)));
-            println!("{}", "[OK]".bright_green());
+            ctx.log_pending(Line::styled(Tone::Plain, format!("  - {} ", component)));
+            ctx.sleep_range(self.config.component_delay_range.clone());
+            ctx.resolve(Line::styled(Tone::Success, "[OK]"));
         }
 
-        println!();
+        ctx.blank();
         let progress = ProgressBar::new(ProgressStyle::Equals);
         progress.animate(
             "Building module dependencies:",
-            rng.gen_range(2000..3500),
-            exit_check,
+            ctx.rng
+                .gen_range(self.config.module_deps_time_range.clone()),
+            ctx,
         )?;
 
         Ok(())
@@ -1,27 +1,18 @@
 use super::InstallationStage;
-use crate::log_generator::LogGenerator;
+use crate::context::Context;
+use crate::render::{Line, Tone};
 use crate::ui::Spinner;
-use colored::*;
 use rand::Rng;
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use std::io;
 
+/// Sets up the X Window System
 pub struct XorgStage;
 
 impl XorgStage {
-    fn load_xorg_modules(
-        &self,
-        rng: &mut rand::rngs::ThreadRng,
-        exit_check: &dyn Fn() -> bool,
-    ) -> io::Result<()> {
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Loading X server modules...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(400));
+    fn load_xorg_modules(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Loading X server modules...");
+        ctx.sleep(400);
 
         let modules = [
             ("fb", "Framebuffer support"),
@@ -35,83 +26,50 @@ impl XorgStage {
         ];
 
         for (module, desc) in &modules {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
-            print!(
-                "{}   [*] {} ",
-                LogGenerator::timestamp().dimmed(),
-                module.bright_white()
+            ctx.log_pending(
+                Line::styled(Tone::Plain, "  [*] ")
+                    .with(Tone::Bright, module)
+                    .with(Tone::Plain, " "),
             );
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(rng.gen_range(120..280)));
-            println!("{}", format!("({})", desc).dimmed());
+            ctx.sleep_range(120..280);
+            ctx.resolve(Line::styled(Tone::Dim, format!("({})", desc)));
         }
 
         Ok(())
     }
 
-    fn initialize_glx(
-        &self,
-        rng: &mut rand::rngs::ThreadRng,
-        _exit_check: &dyn Fn() -> bool,
-    ) -> io::Result<()> {
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Initializing GLX (OpenGL Extension)...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(rng.gen_range(400..700)));
+    fn initialize_glx(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Initializing GLX (OpenGL Extension)...");
+        ctx.sleep_range(400..700);
 
         let gl_versions = ["4.6", "4.5", "4.3"];
-        let gl_version = gl_versions[rng.gen_range(0..gl_versions.len())];
+        let gl_version = gl_versions[ctx.rng.gen_range(0..gl_versions.len())];
 
-        println!(
-            "{}   ├─ GLX version: {}",
-            LogGenerator::timestamp().dimmed(),
-            "1.4".bright_white()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   ├─ OpenGL version: {}",
-            LogGenerator::timestamp().dimmed(),
-            gl_version.bright_white()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   ├─ Mesa driver: {}",
-            LogGenerator::timestamp().dimmed(),
-            "24.1.7".bright_white()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   ├─ GLSL version: {}",
-            LogGenerator::timestamp().dimmed(),
-            "4.60".bright_white()
+        ctx.log_line(Line::styled(Tone::Plain, "  ├─ GLX version: ").with(Tone::Bright, "1.4"));
+        ctx.sleep(200);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ OpenGL version: ").with(Tone::Bright, gl_version),
         );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   └─ Direct rendering: {}",
-            LogGenerator::timestamp().dimmed(),
-            "Yes".bright_green()
+        ctx.sleep(200);
+        ctx.log_line(Line::styled(Tone::Plain, "  ├─ Mesa driver: ").with(Tone::Bright, "24.1.7"));
+        ctx.sleep(200);
+        ctx.log_line(Line::styled(Tone::Plain, "  ├─ GLSL version: ").with(Tone::Bright, "4.60"));
+        ctx.sleep(200);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  └─ Direct rendering: ").with(Tone::Success, "Yes"),
         );
-        thread::sleep(Duration::from_millis(300));
+        ctx.sleep(300);
 
         Ok(())
     }
 
-    fn probe_gpu(
-        &self,
-        rng: &mut rand::rngs::ThreadRng,
-        exit_check: &dyn Fn() -> bool,
-    ) -> io::Result<&'static str> {
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Initializing PCI bus enumeration...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(rng.gen_range(300..600)));
+    fn probe_gpu(&self, ctx: &mut Context) -> io::Result<&'static str> {
+        ctx.log(Tone::Highlight, "Initializing PCI bus enumeration...");
+        ctx.sleep_range(300..600);
 
         let gpu_configs = [
             (
@@ -145,109 +103,96 @@ impl XorgStage {
         ];
 
         let (gpu_name, driver, pci_slot, device_id, modes) =
-            &gpu_configs[rng.gen_range(0..gpu_configs.len())];
+            &gpu_configs[ctx.rng.gen_range(0..gpu_configs.len())];
 
-        println!(
-            "{}   └─ Scanning PCI device {}",
-            LogGenerator::timestamp().dimmed(),
-            pci_slot.bright_white()
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  └─ Scanning PCI device ").with(Tone::Bright, pci_slot),
         );
-        thread::sleep(Duration::from_millis(250));
+        ctx.sleep(250);
 
-        println!(
-            "{}      └─ Device ID: {} {}",
-            LogGenerator::timestamp().dimmed(),
-            device_id.bright_white(),
-            "[VGA compatible controller]".dimmed()
+        ctx.log_line(
+            Line::styled(Tone::Plain, "     └─ Device ID: ")
+                .with(Tone::Bright, device_id)
+                .with(Tone::Plain, " ")
+                .with(Tone::Dim, "[VGA compatible controller]"),
         );
-        thread::sleep(Duration::from_millis(200));
+        ctx.sleep(200);
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("  Detected: {}", gpu_name).bright_green().bold()
-        );
-        thread::sleep(Duration::from_millis(300));
+        ctx.log_line(Line::styled(Tone::Success, format!("  Detected: {}", gpu_name)).bold());
+        ctx.sleep(300);
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("Loading DRM/KMS driver: {}", driver).bright_cyan()
+        ctx.blank();
+        ctx.log(
+            Tone::Highlight,
+            format!("Loading DRM/KMS driver: {}", driver),
         );
-        thread::sleep(Duration::from_millis(rng.gen_range(400..700)));
+        ctx.sleep_range(400..700);
 
-        println!(
-            "{}   ├─ Initializing kernel mode setting (KMS)... {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
+        ctx.log_line(
+            Line::styled(
+                Tone::Plain,
+                "  ├─ Initializing kernel mode setting (KMS)... ",
+            )
+            .with(Tone::Success, "[OK]"),
         );
-        thread::sleep(Duration::from_millis(300));
+        ctx.sleep(300);
 
-        println!(
-            "{}   ├─ Allocating framebuffer memory (256 MB)... {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
+        ctx.log_line(
+            Line::styled(
+                Tone::Plain,
+                "  ├─ Allocating framebuffer memory (256 MB)... ",
+            )
+            .with(Tone::Success, "[OK]"),
         );
-        thread::sleep(Duration::from_millis(250));
+        ctx.sleep(250);
 
-        println!(
-            "{}   ├─ Enabling DPMS (Display Power Management)... {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
+        ctx.log_line(
+            Line::styled(
+                Tone::Plain,
+                "  ├─ Enabling DPMS (Display Power Management)... ",
+            )
+            .with(Tone::Success, "[OK]"),
         );
-        thread::sleep(Duration::from_millis(200));
+        ctx.sleep(200);
 
-        println!(
-            "{}   └─ GPU acceleration: {}",
-            LogGenerator::timestamp().dimmed(),
-            "Enabled".bright_green()
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  └─ GPU acceleration: ").with(Tone::Success, "Enabled"),
         );
-        thread::sleep(Duration::from_millis(300));
+        ctx.sleep(300);
 
         let vram = if driver == &"nvidia" || driver == &"amdgpu" {
-            rng.gen_range(8..=16)
+            ctx.rng.gen_range(8..=16)
         } else if driver == &"vboxvideo" {
             128
         } else {
-            rng.gen_range(4..=8)
+            ctx.rng.gen_range(4..=8)
         };
         let vram_unit = if vram >= 1024 { "GB" } else { "MB" };
         let vram_display = if vram >= 1024 { vram / 1024 } else { vram };
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Querying video memory...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(400));
-        println!(
-            "{}   ├─ Total VRAM: {} {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("{} {}", vram_display, vram_unit).bright_white(),
-            format!("(dedicated)").dimmed()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   └─ Memory type: {}",
-            LogGenerator::timestamp().dimmed(),
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Querying video memory...");
+        ctx.sleep(400);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ Total VRAM: ")
+                .with(Tone::Bright, format!("{} {}", vram_display, vram_unit))
+                .with(Tone::Plain, " ")
+                .with(Tone::Dim, "(dedicated)"),
+        );
+        ctx.sleep(200);
+        ctx.log_line(Line::styled(Tone::Plain, "  └─ Memory type: ").with(
+            Tone::Bright,
             if driver == &"nvidia" || driver == &"amdgpu" {
                 "GDDR6"
             } else {
                 "Shared"
-            }
-            .bright_white()
-        );
-        thread::sleep(Duration::from_millis(250));
+            },
+        ));
+        ctx.sleep(250);
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Enumerating display outputs...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(500));
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Enumerating display outputs...");
+        ctx.sleep(500);
 
         let outputs = if driver == &"nvidia" || driver == &"amdgpu" {
             vec!["DisplayPort-0", "HDMI-0", "DVI-D-0"]
@@ -258,69 +203,59 @@ impl XorgStage {
         };
 
         for (i, output) in outputs.iter().enumerate() {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
             let connected = i == 0;
-            let status = if connected {
-                "Connected".bright_green()
+            let (status_tone, status) = if connected {
+                (Tone::Success, "Connected")
             } else {
-                "Disconnected".dimmed()
+                (Tone::Dim, "Disconnected")
             };
-            println!(
-                "{}   ├─ {}: {}",
-                LogGenerator::timestamp().dimmed(),
-                output.bright_white(),
-                status
+            ctx.log_line(
+                Line::styled(Tone::Plain, "  ├─ ")
+                    .with(Tone::Bright, output)
+                    .with(Tone::Plain, ": ")
+                    .with(status_tone, status),
             );
 
             if connected {
-                let mode = modes[rng.gen_range(0..modes.len())];
+                let mode = modes[ctx.rng.gen_range(0..modes.len())];
                 let refresh = if mode == "3840x2160" {
                     60
-                } else if rng.gen_bool(0.7) {
+                } else if ctx.rng.gen_bool(0.7) {
                     144
                 } else {
                     60
                 };
-                thread::sleep(Duration::from_millis(200));
-                println!(
-                    "{}   │  ├─ Preferred mode: {}@{}Hz",
-                    LogGenerator::timestamp().dimmed(),
-                    mode.bright_white(),
-                    refresh
+                ctx.sleep(200);
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "  │  ├─ Preferred mode: ")
+                        .with(Tone::Bright, mode)
+                        .with(Tone::Plain, format!("@{}Hz", refresh)),
                 );
-                println!(
-                    "{}   │  ├─ Color depth: {} {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "24-bit".bright_white(),
-                    "(TrueColor)".dimmed()
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "  │  ├─ Color depth: ")
+                        .with(Tone::Bright, "24-bit")
+                        .with(Tone::Plain, " ")
+                        .with(Tone::Dim, "(TrueColor)"),
                 );
-                println!(
-                    "{}   │  └─ EDID checksum: {}",
-                    LogGenerator::timestamp().dimmed(),
-                    "Valid".bright_green()
+                ctx.log_line(
+                    Line::styled(Tone::Plain, "  │  └─ EDID checksum: ")
+                        .with(Tone::Success, "Valid"),
                 );
             }
-            thread::sleep(Duration::from_millis(150));
+            ctx.sleep(150);
         }
 
         Ok(gpu_name)
     }
 
-    fn load_extensions(
-        &self,
-        rng: &mut rand::rngs::ThreadRng,
-        exit_check: &dyn Fn() -> bool,
-    ) -> io::Result<()> {
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Loading X server extensions...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(400));
+    fn load_extensions(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Loading X server extensions...");
+        ctx.sleep(400);
 
         let extensions = [
             ("MIT-SHM", "Shared memory support"),
@@ -336,39 +271,30 @@ impl XorgStage {
         ];
 
         for (ext, desc) in &extensions {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
-            print!(
-                "{}   ├─ {} ",
-                LogGenerator::timestamp().dimmed(),
-                ext.bright_white()
+            ctx.log_pending(
+                Line::styled(Tone::Plain, "  ├─ ")
+                    .with(Tone::Bright, ext)
+                    .with(Tone::Plain, " "),
             );
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(rng.gen_range(100..300)));
-            println!(
-                "{} {}",
-                "[LOADED]".bright_green(),
-                format!("({})", desc).dimmed()
+            ctx.sleep_range(100..300);
+            ctx.resolve(
+                Line::styled(Tone::Success, "[LOADED]")
+                    .with(Tone::Plain, " ")
+                    .with(Tone::Dim, format!("({})", desc)),
             );
         }
-        thread::sleep(Duration::from_millis(200));
+        ctx.sleep(200);
 
         Ok(())
     }
 
-    fn detect_input_devices(
-        &self,
-        rng: &mut rand::rngs::ThreadRng,
-        exit_check: &dyn Fn() -> bool,
-    ) -> io::Result<()> {
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Detecting input devices...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(500));
+    fn detect_input_devices(&self, ctx: &mut Context) -> io::Result<()> {
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Detecting input devices...");
+        ctx.sleep(500);
 
         let devices = [
             ("AT Translated Set 2 keyboard", "event0", "keyboard"),
@@ -378,7 +304,7 @@ impl XorgStage {
         ];
 
         for (i, (device, event, device_type)) in devices.iter().enumerate() {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
@@ -387,24 +313,22 @@ impl XorgStage {
             } else {
                 "├─"
             };
-            println!(
-                "{}   {} /dev/input/{} → {}",
-                LogGenerator::timestamp().dimmed(),
-                tree_char,
-                event.bright_white(),
-                device.dimmed()
+            ctx.log_line(
+                Line::styled(Tone::Plain, format!("  {} /dev/input/", tree_char))
+                    .with(Tone::Bright, event)
+                    .with(Tone::Plain, " → ")
+                    .with(Tone::Dim, device),
             );
-            thread::sleep(Duration::from_millis(200));
+            ctx.sleep(200);
 
             let sub_tree_char = if i == devices.len() - 1 { " " } else { "│" };
-            println!(
-                "{}   {}  └─ Driver: {} {}",
-                LogGenerator::timestamp().dimmed(),
-                sub_tree_char,
-                "libinput".bright_white(),
-                format!("[{}]", device_type).dimmed()
+            ctx.log_line(
+                Line::styled(Tone::Plain, format!("  {}  └─ Driver: ", sub_tree_char))
+                    .with(Tone::Bright, "libinput")
+                    .with(Tone::Plain, " ")
+                    .with(Tone::Dim, format!("[{}]", device_type)),
             );
-            thread::sleep(Duration::from_millis(rng.gen_range(150..300)));
+            ctx.sleep_range(150..300);
         }
 
         Ok(())
@@ -416,20 +340,12 @@ impl InstallationStage for XorgStage {
         "X Window System Setup"
     }
 
-    fn run(&self, exit_check: &dyn Fn() -> bool) -> io::Result<()> {
-        println!("\n{}", format!("> {}", self.name()).bright_yellow().bold());
-        println!();
-
-        let mut rng = rand::thread_rng();
+    fn run(&self, ctx: &mut Context) -> io::Result<()> {
         let mut spinner = Spinner::new();
 
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Installing X.Org Server packages...".bright_white().bold()
-        );
-        thread::sleep(Duration::from_millis(400));
-        println!();
+        ctx.log_line(Line::styled(Tone::Bright, "Installing X.Org Server packages...").bold());
+        ctx.sleep(400);
+        ctx.blank();
 
         let packages = [
             ("xserver-xorg-core", "1.21.1-7"),
@@ -447,77 +363,59 @@ impl InstallationStage for XorgStage {
         ];
 
         for (package, version) in &packages {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
-            println!(
-                "{}   [+] {} {}",
-                LogGenerator::timestamp().dimmed(),
-                package.bright_white(),
-                format!("({})", version).dimmed()
+            ctx.log_line(
+                Line::styled(Tone::Plain, "  [+] ")
+                    .with(Tone::Bright, package)
+                    .with(Tone::Plain, " ")
+                    .with(Tone::Dim, format!("({})", version)),
             );
-            thread::sleep(Duration::from_millis(rng.gen_range(150..400)));
+            ctx.sleep_range(150..400);
         }
 
-        println!();
-        spinner.animate(
-            "Configuring X server security policies...",
-            1200,
-            exit_check,
-        )?;
-
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "═══ Graphics Hardware Detection ═══".bright_yellow().bold()
-        );
-        thread::sleep(Duration::from_millis(300));
-        println!();
+        ctx.blank();
+        spinner.animate("Configuring X server security policies...", 1200, ctx)?;
 
-        let _gpu_name = self.probe_gpu(&mut rng, exit_check)?;
+        ctx.blank();
+        ctx.log_line(Line::styled(Tone::Header, "═══ Graphics Hardware Detection ═══").bold());
+        ctx.sleep(300);
+        ctx.blank();
 
-        self.load_xorg_modules(&mut rng, exit_check)?;
-        self.initialize_glx(&mut rng, exit_check)?;
-        self.load_extensions(&mut rng, exit_check)?;
-        self.detect_input_devices(&mut rng, exit_check)?;
+        let _gpu_name = self.probe_gpu(ctx)?;
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Configuring screen parameters...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(500));
-
-        let dpi = rng.gen_range(90..=110);
-        println!(
-            "{}   ├─ Physical size: {} × {} mm",
-            LogGenerator::timestamp().dimmed(),
-            "508".bright_white(),
-            "285".bright_white()
+        self.load_xorg_modules(ctx)?;
+        self.initialize_glx(ctx)?;
+        self.load_extensions(ctx)?;
+        self.detect_input_devices(ctx)?;
+
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Configuring screen parameters...");
+        ctx.sleep(500);
+
+        let dpi = ctx.rng.gen_range(90..=110);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ Physical size: ")
+                .with(Tone::Bright, "508")
+                .with(Tone::Plain, " × ")
+                .with(Tone::Bright, "285")
+                .with(Tone::Plain, " mm"),
         );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   ├─ DPI: {}",
-            LogGenerator::timestamp().dimmed(),
-            format!("{} × {}", dpi, dpi).bright_white()
+        ctx.sleep(200);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ DPI: ")
+                .with(Tone::Bright, format!("{} × {}", dpi, dpi)),
         );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   └─ Virtual size: {}",
-            LogGenerator::timestamp().dimmed(),
-            "3840 × 2160".bright_white()
+        ctx.sleep(200);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  └─ Virtual size: ").with(Tone::Bright, "3840 × 2160"),
         );
-        thread::sleep(Duration::from_millis(300));
+        ctx.sleep(300);
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Scanning font directories...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Scanning font directories...");
+        ctx.sleep(400);
 
         let font_dirs = [
             "/usr/share/fonts/X11/misc",
@@ -528,7 +426,7 @@ impl InstallationStage for XorgStage {
         ];
 
         for (i, dir) in font_dirs.iter().enumerate() {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
             let tree_char = if i == font_dirs.len() - 1 {
@@ -536,43 +434,35 @@ impl InstallationStage for XorgStage {
             } else {
                 "├─"
             };
-            let font_count = rng.gen_range(12..156);
-            println!(
-                "{}   {} {} {} {}",
-                LogGenerator::timestamp().dimmed(),
-                tree_char,
-                dir.bright_white(),
-                format!("[{} fonts]", font_count).dimmed(),
-                "[OK]".bright_green()
+            let font_count = ctx.rng.gen_range(12..156);
+            ctx.log_line(
+                Line::styled(Tone::Plain, format!("  {} ", tree_char))
+                    .with(Tone::Bright, dir)
+                    .with(Tone::Plain, " ")
+                    .with(Tone::Dim, format!("[{} fonts]", font_count))
+                    .with(Tone::Plain, " ")
+                    .with(Tone::Success, "[OK]"),
             );
-            thread::sleep(Duration::from_millis(rng.gen_range(100..250)));
+            ctx.sleep_range(100..250);
         }
 
-        println!();
-        spinner.animate("Building font cache (fc-cache)...", 1800, exit_check)?;
+        ctx.blank();
+        spinner.animate("Building font cache (fc-cache)...", 1800, ctx)?;
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Loading cursor theme...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(400));
-        println!(
-            "{}   └─ Theme: {} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Adwaita".bright_white(),
-            "(24px)".dimmed()
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Loading cursor theme...");
+        ctx.sleep(400);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  └─ Theme: ")
+                .with(Tone::Bright, "Adwaita")
+                .with(Tone::Plain, " ")
+                .with(Tone::Dim, "(24px)"),
         );
-        thread::sleep(Duration::from_millis(300));
+        ctx.sleep(300);
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Writing configuration files...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Writing configuration files...");
+        ctx.sleep(400);
 
         let config_files = [
             "/etc/X11/xorg.conf",
@@ -583,7 +473,7 @@ impl InstallationStage for XorgStage {
         ];
 
         for (i, file) in config_files.iter().enumerate() {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
             let tree_char = if i == config_files.len() - 1 {
@@ -591,100 +481,59 @@ impl InstallationStage for XorgStage {
             } else {
                 "├─"
             };
-            print!(
-                "{}   {} {} ",
-                LogGenerator::timestamp().dimmed(),
-                tree_char,
-                file.bright_white()
+            ctx.log_pending(
+                Line::styled(Tone::Plain, format!("  {} ", tree_char))
+                    .with(Tone::Bright, file)
+                    .with(Tone::Plain, " "),
             );
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(rng.gen_range(200..400)));
-            println!("{}", "[CREATED]".bright_green());
+            ctx.sleep_range(200..400);
+            ctx.resolve(Line::styled(Tone::Success, "[CREATED]"));
         }
 
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "Generating xorg.conf sections...".bright_cyan()
-        );
-        thread::sleep(Duration::from_millis(600));
-
-        println!(
-            "{}   ├─ Section \"ServerLayout\" {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   │  └─ Setting default screen to 0",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(150));
-        println!(
-            "{}   ├─ Section \"InputDevice\" {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   │  ├─ Keyboard: CoreKeyboard",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(150));
-        println!(
-            "{}   │  └─ Pointer: CorePointer",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(150));
-        println!(
-            "{}   ├─ Section \"Monitor\" {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   │  └─ HorizSync: 30.0 - 83.0 kHz",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(150));
-        println!(
-            "{}   ├─ Section \"Device\" {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}   │  ├─ Option \"AccelMethod\" \"sna\"",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(150));
-        println!(
-            "{}   │  └─ Option \"TearFree\" \"true\"",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(150));
-        println!(
-            "{}   └─ Section \"Screen\" {}",
-            LogGenerator::timestamp().dimmed(),
-            "[OK]".bright_green()
-        );
-        thread::sleep(Duration::from_millis(200));
-        println!(
-            "{}      └─ DefaultDepth: 24",
-            LogGenerator::timestamp().dimmed()
-        );
-        thread::sleep(Duration::from_millis(300));
-
-        println!();
-        println!(
-            "{} {}",
-            LogGenerator::timestamp().dimmed(),
-            "X Window System configured successfully!"
-                .bright_green()
-                .bold()
-        );
-        thread::sleep(Duration::from_millis(400));
+        ctx.blank();
+        ctx.log(Tone::Highlight, "Generating xorg.conf sections...");
+        ctx.sleep(600);
+
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ Section \"ServerLayout\" ").with(Tone::Success, "[OK]"),
+        );
+        ctx.sleep(200);
+        ctx.log(Tone::Plain, "  │  └─ Setting default screen to 0");
+        ctx.sleep(150);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ Section \"InputDevice\" ").with(Tone::Success, "[OK]"),
+        );
+        ctx.sleep(200);
+        ctx.log(Tone::Plain, "  │  ├─ Keyboard: CoreKeyboard");
+        ctx.sleep(150);
+        ctx.log(Tone::Plain, "  │  └─ Pointer: CorePointer");
+        ctx.sleep(150);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ Section \"Monitor\" ").with(Tone::Success, "[OK]"),
+        );
+        ctx.sleep(200);
+        ctx.log(Tone::Plain, "  │  └─ HorizSync: 30.0 - 83.0 kHz");
+        ctx.sleep(150);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  ├─ Section \"Device\" ").with(Tone::Success, "[OK]"),
+        );
+        ctx.sleep(200);
+        ctx.log(Tone::Plain, "  │  ├─ Option \"AccelMethod\" \"sna\"");
+        ctx.sleep(150);
+        ctx.log(Tone::Plain, "  │  └─ Option \"TearFree\" \"true\"");
+        ctx.sleep(150);
+        ctx.log_line(
+            Line::styled(Tone::Plain, "  └─ Section \"Screen\" ").with(Tone::Success, "[OK]"),
+        );
+        ctx.sleep(200);
+        ctx.log(Tone::Plain, "     └─ DefaultDepth: 24");
+        ctx.sleep(300);
+
+        ctx.blank();
+        ctx.log_line(
+            Line::styled(Tone::Success, "X Window System configured successfully!").bold(),
+        );
+        ctx.sleep(400);
 
         Ok(())
     }
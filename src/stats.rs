@@ -0,0 +1,156 @@
+use crate::cli::StageId;
+use crate::clock::format_hms;
+use crate::render::{Theme, Tone};
+use serde::{Serialize, Serializer};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Duration;
+
+/// What happened during a session, printed when it ends
+#[derive(Debug, Default, Serialize)]
+pub struct Stats {
+    /// Installation cycles that ran to the end
+    pub cycles: u32,
+    /// How many times each stage was run, skipped ones included
+    pub stages: BTreeMap<StageId, u32>,
+    /// Simulated bytes downloaded by the packages, container and AI stages
+    pub downloaded_bytes: u64,
+    pub warnings: u32,
+    pub retries: u32,
+    /// Simulated failures the installation carried on after
+    pub recovered_failures: u32,
+    #[serde(rename = "elapsed_seconds", serialize_with = "as_seconds")]
+    pub elapsed: Duration,
+}
+
+impl Stats {
+    pub fn stage_run(&mut self, stage: StageId) {
+        *self.stages.entry(stage).or_default() += 1;
+    }
+
+    pub fn stages_run(&self) -> u32 {
+        self.stages.values().sum()
+    }
+
+    /// Writes the session summary to `out`
+    pub fn write_summary(&self, out: &mut impl Write, theme: &Theme) -> io::Result<()> {
+        let rule = theme.paint(
+            "═══════════════════════════════════════",
+            Tone::Highlight,
+            false,
+        );
+        writeln!(out, "{}", rule)?;
+        writeln!(
+            out,
+            "{}",
+            theme.paint("Session summary", Tone::Bright, true)
+        )?;
+        writeln!(out, "{}", rule)?;
+
+        let rows = [
+            ("Elapsed", format_hms(self.elapsed)),
+            ("Cycles completed", self.cycles.to_string()),
+            ("Stages run", self.stages_run().to_string()),
+            ("Downloaded", format_bytes(self.downloaded_bytes)),
+            ("Warnings", self.warnings.to_string()),
+            ("Retries", self.retries.to_string()),
+            ("Failures recovered", self.recovered_failures.to_string()),
+        ];
+        for (label, value) in rows {
+            writeln!(
+                out,
+                "  {:<20}{}",
+                label,
+                theme.paint(&value, Tone::Bright, false)
+            )?;
+        }
+
+        if !self.stages.is_empty() {
+            writeln!(out)?;
+            for (stage, count) in &self.stages {
+                let name = format!("{:<20}", stage.to_string());
+                writeln!(out, "  {}{}", theme.paint(&name, Tone::Dim, false), count)?;
+            }
+        }
+        writeln!(out, "{}", rule)
+    }
+
+    /// Writes the statistics to `path` as JSON
+    pub fn write_report(&self, path: &Path) -> io::Result<()> {
+        let json = serde_json::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, json + "\n").map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot write report {}: {}", path.display(), e),
+            )
+        })
+    }
+}
+
+fn as_seconds<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_f64(duration.as_secs_f64())
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 GiB`
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Stage;
+
+    #[test]
+    fn test_format_bytes() {
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536), "1.5 KiB");
+        assert_eq!(format_bytes(140 * 1024 * 1024 * 1024), "140.0 GiB");
+    }
+
+    #[test]
+    fn test_report_json() {
+        let mut stats = Stats {
+            cycles: 1,
+            elapsed: Duration::from_millis(1500),
+            ..Default::default()
+        };
+        stats.stage_run(Stage::Kernel.into());
+        stats.stage_run(Stage::Kernel.into());
+        stats.stage_run(Stage::Bios.into());
+        stats.stage_run(StageId::Script("vpn".to_string()));
+
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["stages"]["kernel"], 2);
+        assert_eq!(json["stages"]["bios"], 1);
+        assert_eq!(json["stages"]["vpn"], 1);
+        assert_eq!(json["elapsed_seconds"], 1.5);
+        assert_eq!(stats.stages_run(), 4);
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut stats = Stats::default();
+        stats.stage_run(Stage::Boot.into());
+        let mut out = Vec::new();
+        let theme = Theme::preset("no-color").unwrap();
+        stats.write_summary(&mut out, &theme).unwrap();
+
+        let text = String::from_utf8(out).unwrap();
+        assert!(text.contains("  Stages run          1\n"), "{}", text);
+        assert!(text.contains("  boot                1\n"), "{}", text);
+    }
+}
@@ -0,0 +1,248 @@
+use crate::cli::{Order, StageId};
+use crate::clock::{format_hms, Clock};
+use crate::config::SimulationConfig;
+use crate::input::Input;
+use crate::installer::Installer;
+use crate::pacing::Finish;
+use crate::render::{AnsiRenderer, Theme};
+use std::io::{self, BufWriter, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+const IAC: u8 = 255;
+const DONT: u8 = 254;
+const DO: u8 = 253;
+const WONT: u8 = 252;
+const WILL: u8 = 251;
+const SB: u8 = 250;
+const SE: u8 = 240;
+const ECHO: u8 = 1;
+const SUPPRESS_GO_AHEAD: u8 = 3;
+const NAWS: u8 = 31;
+
+/// Sent to every client: we echo and suppress go-ahead, which puts the client
+/// in character mode so hotkeys arrive as they are pressed, and we would
+/// like to know the window size
+const NEGOTIATION: [u8; 9] = [IAC, WILL, ECHO, IAC, WILL, SUPPRESS_GO_AHEAD, IAC, DO, NAWS];
+/// Columns assumed until the client tells its window size
+const DEFAULT_WIDTH: usize = 80;
+/// How long a new client gets to tell its window size before the header is drawn
+const NEGOTIATION_WAIT: Duration = Duration::from_millis(500);
+
+/// Something the client sent
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Received {
+    Char(char),
+    /// The window size, in columns and rows
+    Size(u16, u16),
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Data,
+    /// After an IAC
+    Command,
+    /// After WILL, WONT, DO or DONT, waiting for the option
+    Option,
+    /// Inside a subnegotiation
+    Sub,
+    /// After an IAC inside a subnegotiation
+    SubCommand,
+}
+
+/// Splits what a telnet client sends into keys and window sizes
+struct Parser {
+    state: State,
+    sub: Vec<u8>,
+}
+
+impl Parser {
+    fn new() -> Self {
+        Self {
+            state: State::Data,
+            sub: Vec::new(),
+        }
+    }
+
+    fn feed(&mut self, byte: u8) -> Option<Received> {
+        match (self.state, byte) {
+            (State::Data, IAC) => self.state = State::Command,
+            // Enter comes as CR LF or CR NUL, and only ASCII makes a hotkey
+            (State::Data, b'\r' | b'\n' | 0) => {}
+            (State::Data, byte) if byte.is_ascii() => return Some(Received::Char(byte as char)),
+            (State::Data, _) => {}
+            (State::Command, WILL | WONT | DO | DONT) => self.state = State::Option,
+            (State::Command, SB) => {
+                self.sub.clear();
+                self.state = State::Sub;
+            }
+            (State::Command, _) | (State::Option, _) => self.state = State::Data,
+            (State::Sub, IAC) => self.state = State::SubCommand,
+            (State::Sub, byte) => self.sub.push(byte),
+            (State::SubCommand, IAC) => {
+                self.sub.push(IAC);
+                self.state = State::Sub;
+            }
+            (State::SubCommand, SE) => {
+                self.state = State::Data;
+                if let [NAWS, w1, w2, h1, h2] = self.sub[..] {
+                    let columns = u16::from_be_bytes([w1, w2]);
+                    let rows = u16::from_be_bytes([h1, h2]);
+                    return Some(Received::Size(columns, rows));
+                }
+            }
+            (State::SubCommand, _) => self.state = State::Data,
+        }
+        None
+    }
+}
+
+/// What every session starts from, each one picks its own seed unless one
+/// is given
+pub struct Session {
+    pub stages: Vec<StageId>,
+    pub order: Order,
+    pub seed: Option<u64>,
+    pub speed: f64,
+    pub config: SimulationConfig,
+    pub theme: Theme,
+    pub boss_key: char,
+    pub serious: bool,
+    pub finish: Finish,
+}
+
+/// Runs an installer for every client that connects to `addr`, until the
+/// process is stopped
+pub fn listen(addr: SocketAddr, session: Session) -> io::Result<()> {
+    let listener = TcpListener::bind(addr)
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot listen on {}: {}", addr, e)))?;
+    let session = Arc::new(session);
+    for stream in listener.incoming() {
+        let Ok(stream) = stream else {
+            continue;
+        };
+        let session = Arc::clone(&session);
+        thread::spawn(move || {
+            let peer = stream
+                .peer_addr()
+                .map_or_else(|_| "unknown".to_string(), |addr| addr.to_string());
+            println!("{} connected", peer);
+            match run_session(stream, &session) {
+                Ok(elapsed) => println!("{} left after {}", peer, elapsed),
+                Err(e) if is_disconnect(&e) => println!("{} disconnected", peer),
+                Err(e) => println!("{} dropped: {}", peer, e),
+            }
+        });
+    }
+    Ok(())
+}
+
+fn is_disconnect(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::BrokenPipe
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+    )
+}
+
+/// Runs one client's installer until it ends or the client goes away,
+/// returning how long it ran
+fn run_session(stream: TcpStream, session: &Session) -> io::Result<String> {
+    stream.set_nodelay(true)?;
+    let mut out = stream.try_clone()?;
+    out.write_all(&NEGOTIATION)?;
+
+    let width = Arc::new(AtomicUsize::new(DEFAULT_WIDTH));
+    let (keys, chars) = mpsc::channel();
+    let (sized, size_known) = mpsc::channel();
+    let reader = stream.try_clone()?;
+    let reader_width = Arc::clone(&width);
+    thread::spawn(move || read_client(reader, keys, sized, &reader_width));
+    // Clients that never tell their size start at the default width
+    let _ = size_known.recv_timeout(NEGOTIATION_WAIT);
+
+    let renderer = AnsiRenderer::new(BufWriter::new(out), session.theme.clone()).with_width(width);
+    let mut installer = Installer::new(
+        session.stages.clone(),
+        session.order,
+        session.seed.unwrap_or_else(rand::random),
+        Clock::new(session.speed),
+        session.config.clone(),
+        Box::new(renderer),
+        Input::remote(chars).with_boss_key(session.boss_key),
+    );
+    installer.serious(session.serious);
+    installer.finish(session.finish);
+    let result = installer.run();
+    let elapsed = format_hms(installer.into_stats().elapsed);
+
+    let cancelled = matches!(&result, Err(e) if e.kind() == io::ErrorKind::Interrupted);
+    let mut out = &stream;
+    if cancelled {
+        write!(out, "\r\n\r\nInstallation cancelled by user.\r\n")?;
+    }
+    write!(
+        out,
+        "\r\nThank you for using Universal System Installer!\r\n"
+    )?;
+    // Also ends the reader, which is blocked on the socket
+    stream.shutdown(Shutdown::Both)?;
+    match result {
+        Err(e) if !cancelled => Err(e),
+        _ => Ok(elapsed),
+    }
+}
+
+/// Passes the keys of a client on to its installer, keeping `width` up to
+/// date and saying so on `sized`, until the client disconnects
+fn read_client(mut stream: TcpStream, keys: Sender<char>, sized: Sender<()>, width: &AtomicUsize) {
+    let mut parser = Parser::new();
+    let mut buffer = [0; 256];
+    while let Ok(read @ 1..) = stream.read(&mut buffer) {
+        for &byte in &buffer[..read] {
+            match parser.feed(byte) {
+                // The installer has ended
+                Some(Received::Char(c)) if keys.send(c).is_err() => return,
+                Some(Received::Size(columns, _)) if columns > 0 => {
+                    width.store(columns as usize, Ordering::Relaxed);
+                    let _ = sized.send(());
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn feed(parser: &mut Parser, bytes: &[u8]) -> Vec<Received> {
+        bytes.iter().filter_map(|&byte| parser.feed(byte)).collect()
+    }
+
+    #[test]
+    fn test_parser() {
+        let mut parser = Parser::new();
+        assert_eq!(
+            feed(
+                &mut parser,
+                &[IAC, DO, ECHO, b'n', b'\r', 0, IAC, WILL, NAWS]
+            ),
+            vec![Received::Char('n')]
+        );
+        // 120 columns and 255 rows, the 255 escaped as IAC IAC
+        assert_eq!(
+            feed(
+                &mut parser,
+                &[IAC, SB, NAWS, 0, 120, 0, IAC, IAC, IAC, SE, b'q']
+            ),
+            vec![Received::Size(120, 255), Received::Char('q')]
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use crossterm::terminal;
+use std::io::{self, IsTerminal};
+
+/// Width assumed when the output is not a terminal
+const FALLBACK_WIDTH: usize = 80;
+/// Narrowest a box or rule gets, however small the terminal
+const MIN_WIDTH: usize = 10;
+
+/// Columns of the terminal right now, queried on every call so that resizes
+/// are picked up by the next frame
+///
+/// Without a terminal on stdout the size is not asked for at all, crossterm
+/// would run `tput` for it every time.
+pub fn terminal_width() -> usize {
+    if !io::stdout().is_terminal() {
+        return FALLBACK_WIDTH;
+    }
+    match terminal::size() {
+        Ok((columns, _)) if columns > 0 => columns as usize,
+        _ => FALLBACK_WIDTH,
+    }
+}
+
+/// Number of columns `text` takes up
+pub fn width(text: &str) -> usize {
+    text.chars().map(char_width).sum()
+}
+
+/// Columns of a character, two for emoji such as clock faces and moon phases
+fn char_width(c: char) -> usize {
+    if ('\u{1F300}'..='\u{1FAFF}').contains(&c) {
+        2
+    } else {
+        1
+    }
+}
+
+/// The start of `text` that fits in `max` columns
+pub fn take_width(text: &str, max: usize) -> &str {
+    let mut used = 0;
+    for (index, c) in text.char_indices() {
+        used += char_width(c);
+        if used > max {
+            return &text[..index];
+        }
+    }
+    text
+}
+
+/// Cuts `text` down to `max` columns, ending it with an ellipsis if anything was cut
+pub fn truncate(text: &str, max: usize) -> String {
+    if width(text) <= max {
+        return text.to_string();
+    }
+    if max == 0 {
+        return String::new();
+    }
+    let mut cut = take_width(text, max - 1).to_string();
+    cut.push('…');
+    cut
+}
+
+/// Width of a box or rule that is `preferred` columns wide on a roomy terminal,
+/// leaving the last column free so that the line never wraps
+pub fn fit_width(preferred: usize, columns: usize) -> usize {
+    preferred.min(columns.saturating_sub(1)).max(MIN_WIDTH)
+}
+
+/// A double-lined box `width` columns wide around `lines`
+pub fn framed(lines: &[&str], width: usize) -> Vec<String> {
+    let inner = width.saturating_sub(2);
+    let text_width = inner.saturating_sub(2);
+    let mut framed = vec![format!("╔{}╗", "═".repeat(inner))];
+    for line in lines {
+        framed.push(format!(
+            "║  {:<w$}║",
+            truncate(line, text_width),
+            w = text_width
+        ));
+    }
+    framed.push(format!("╚{}╝", "═".repeat(inner)));
+    framed
+}
+
+/// `text` centered in `width` columns, without trailing padding
+pub fn centered(text: &str, width: usize) -> String {
+    let text = truncate(text, width);
+    format!("{}{}", " ".repeat((width - self::width(&text)) / 2), text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("Loading modules", 20), "Loading modules");
+        assert_eq!(truncate("Loading modules", 8), "Loading…");
+        assert_eq!(truncate("Loading", 0), "");
+        assert_eq!(width("🌕 Moon"), 7);
+        assert_eq!(truncate("🌕🌕🌕", 4), "🌕…");
+    }
+
+    #[test]
+    fn test_framed_fits_width() {
+        let lines = framed(&["Award Modular BIOS v6.00PG, An Energy Star Ally"], 30);
+        assert_eq!(lines.len(), 3);
+        for line in &lines {
+            assert_eq!(width(line), 30, "{}", line);
+        }
+        assert!(lines[1].ends_with("…║"));
+        assert_eq!(fit_width(65, 200), 65);
+        assert_eq!(fit_width(65, 40), 39);
+    }
+}
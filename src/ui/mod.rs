@@ -1,4 +1,7 @@
+pub mod layout;
+mod multi_progress;
 pub mod progress;
 mod spinner;
+pub use multi_progress::MultiProgress;
 pub use progress::{ProgressBar, ProgressStyle};
-pub use spinner::Spinner;
+pub use spinner::{Spinner, SpinnerStyle};
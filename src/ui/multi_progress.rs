@@ -0,0 +1,134 @@
+use super::progress::{ProgressBar, ProgressStyle, Transfer};
+use crate::context::Context;
+use crate::render::{Event, Line, Progress};
+
+/// A task in a `MultiProgress`
+struct Row {
+    id: usize,
+    label: Line,
+    bar: Option<Transfer>,
+}
+
+/// Tasks that run at the same time, such as the layers of a `docker pull`,
+/// drawn as a block of lines that is redrawn in place
+///
+/// Every task is a label, followed by a bar while it has one. A finished task
+/// can be taken out of the block with `complete`, which prints its last frame
+/// above the block.
+pub struct MultiProgress {
+    style: ProgressStyle,
+    rows: Vec<Row>,
+    next_id: usize,
+}
+
+impl MultiProgress {
+    pub fn new(style: ProgressStyle) -> Self {
+        Self {
+            style,
+            rows: Vec::new(),
+            next_id: 0,
+        }
+    }
+
+    /// Adds a task at the bottom of the block and returns its id
+    pub fn add(&mut self, label: Line) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.rows.push(Row {
+            id,
+            label,
+            bar: None,
+        });
+        id
+    }
+
+    /// Changes the label of a task, along with its bar, `None` hiding it
+    pub fn set(&mut self, task: usize, label: Line, bar: Option<Transfer>) {
+        if let Some(row) = self.rows.iter_mut().find(|row| row.id == task) {
+            row.label = label;
+            row.bar = bar;
+        }
+    }
+
+    /// The lines of the block, each fitted into `columns`
+    pub fn lines(&self, columns: usize) -> Vec<Line> {
+        self.rows
+            .iter()
+            .map(|row| self.line(row, columns))
+            .collect()
+    }
+
+    fn line(&self, row: &Row, columns: usize) -> Line {
+        match row.bar {
+            Some(transfer) => ProgressBar::frame(
+                &Progress {
+                    label: &row.label,
+                    fraction: transfer.fraction(),
+                    style: self.style,
+                    width: None,
+                    detail: None,
+                    transfer: Some(transfer),
+                },
+                columns,
+            ),
+            None => row.label.truncate(columns.saturating_sub(1)),
+        }
+    }
+
+    /// Draws a frame of the block
+    pub fn draw(&self, ctx: &mut Context) {
+        let lines = self.lines(ctx.columns());
+        ctx.emit(&Event::Block(&lines));
+    }
+
+    /// Takes a task out of the block and prints its last frame above it
+    pub fn complete(&mut self, ctx: &mut Context, task: usize) {
+        if let Some(index) = self.rows.iter().position(|row| row.id == task) {
+            let row = self.rows.remove(index);
+            let line = self.line(&row, ctx.columns());
+            ctx.emit(&Event::Line(&line));
+        }
+    }
+
+    /// Draws the last frame, which stays on screen
+    pub fn finish(&self, ctx: &mut Context) {
+        self.draw(ctx);
+        ctx.emit(&Event::BlockEnd);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Tone;
+
+    #[test]
+    fn test_lines_follow_tasks() {
+        let mut multi = MultiProgress::new(ProgressStyle::Docker);
+        let first = multi.add(Line::styled(Tone::Plain, "a2abf6c4d29d: Waiting"));
+        let second = multi.add(Line::styled(Tone::Plain, "0c8db6a2c6d7: Waiting"));
+        multi.set(
+            second,
+            Line::styled(Tone::Plain, "0c8db6a2c6d7: Downloading"),
+            Some(Transfer::items(1_000_000, 4_000_000)),
+        );
+
+        let text = |line: &Line| -> String { line.spans.iter().map(|s| s.text.as_str()).collect() };
+        let lines = multi.lines(80);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(text(&lines[0]), "a2abf6c4d29d: Waiting");
+        assert!(
+            text(&lines[1]).ends_with("]      1MB/4MB"),
+            "{}",
+            text(&lines[1])
+        );
+        assert!(lines.iter().all(|line| line.width() < 80));
+
+        multi.set(
+            first,
+            Line::styled(Tone::Plain, "a2abf6c4d29d: Pull complete"),
+            None,
+        );
+        assert_eq!(text(&multi.lines(80)[0]), "a2abf6c4d29d: Pull complete");
+    }
+}
@@ -1,34 +1,148 @@
-use colored::*;
-use std::io::{self, Write};
-use std::thread;
+use super::layout::width;
+use crate::context::Context;
+use crate::render::{Event, Line, Progress, Tone};
+use rand::Rng;
+use serde::Deserialize;
+use std::io;
 use std::time::Duration;
 
-#[derive(Clone, Copy)]
+/// How a progress bar looks
+///
+/// The first four are plain bars with a percentage. The others copy the
+/// progress output of well-known tools and show the counts of a `Transfer`
+/// when there is one: bytes for `wget`, `curl`, `apt`, `pip` and `docker`,
+/// build units for `cargo`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ProgressStyle {
     Hash,
     Equals,
+    #[default]
     Block,
-    #[allow(dead_code)]
     Gradient,
+    /// `linux.tar.xz  45%[=====>      ]  61.69M  5.52MB/s    eta 13s`
+    Wget,
+    /// The `curl` progress meter, a table of sizes, speeds and times
+    Curl,
+    /// `45% [libc6 1,456 kB/3,235 kB 45%]          512 kB/s 3s`
+    Apt,
+    /// `━━━━━━━━━━━━━━━━━━━━━ 12.3/24.6 MB 5.1 MB/s eta 0:00:03`
+    Pip,
+    /// `Building [=======>         ] 45/120: serde`
+    Cargo,
+    /// `a2abf6c4d29d: Downloading [=====>          ]  12.5MB/45.2MB`
+    Docker,
 }
 
+impl ProgressStyle {
+    /// Whether the tool wipes its bar once it is done, like `apt` and `cargo`,
+    /// leaving the line to whatever comes next
+    pub fn is_wiped(self) -> bool {
+        matches!(self, ProgressStyle::Apt | ProgressStyle::Cargo)
+    }
+}
+
+/// The header `curl` prints above its progress meter
+const CURL_HEADER: [&str; 2] = [
+    "  % Total    % Received % Xferd  Average Speed   Time    Time     Time  Current",
+    "                                 Dload  Upload   Total   Spent    Left  Speed",
+];
+
+/// Narrowest bar worth drawing, the label is cut short before the bar gets smaller
+const MIN_WIDTH: usize = 10;
+/// Widest a bar gets on a wide terminal
+const MAX_WIDTH: usize = 50;
+
+/// Counts behind a progress bar: bytes moved, or units built for `cargo`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transfer {
+    pub done: u64,
+    pub total: u64,
+    /// Current throughput in units per second
+    pub rate: f64,
+    pub elapsed: Duration,
+}
+
+impl Transfer {
+    /// A count of units with no notion of speed, as `cargo` shows them
+    pub fn items(done: u64, total: u64) -> Self {
+        Self {
+            done,
+            total,
+            rate: 0.0,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub fn fraction(&self) -> f32 {
+        if self.total == 0 {
+            1.0
+        } else {
+            (self.done as f64 / self.total as f64) as f32
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done >= self.total
+    }
+
+    /// Time left at the current throughput in whole seconds, rounded up so
+    /// that it only says zero once it is done, `None` while there is no
+    /// throughput yet
+    pub fn eta(&self) -> Option<Duration> {
+        (self.rate > 0.0).then(|| {
+            let left = self.total.saturating_sub(self.done) as f64 / self.rate;
+            Duration::from_secs(left.ceil() as u64)
+        })
+    }
+}
+
+/// Draws progress bars in one of the `ProgressStyle`s
 pub struct ProgressBar {
-    width: usize,
+    /// Fixed width, or `None` to scale with the terminal
+    width: Option<usize>,
     style: ProgressStyle,
 }
 
 impl ProgressBar {
+    /// A bar that scales with the width of the terminal
     pub fn new(style: ProgressStyle) -> Self {
-        Self { width: 20, style }
+        Self { width: None, style }
+    }
+
+    pub fn with_width(style: ProgressStyle, width: usize) -> Self {
+        Self {
+            width: Some(width),
+            style,
+        }
+    }
+
+    /// Lays out a bar of `width` (scaled if `None`) after a label of `label`
+    /// columns on a line of `columns`, `extra` being the columns of everything
+    /// else on the line
+    ///
+    /// Returns the width of the bar and the columns left for the label, which
+    /// is less than `label` when the label has to be cut short.
+    pub fn fit(width: Option<usize>, label: usize, extra: usize, columns: usize) -> (usize, usize) {
+        // The last column stays empty so the line does not wrap and `\r` can redraw it
+        let columns = columns.saturating_sub(1);
+        let bar = width.unwrap_or_else(|| (columns / 3).clamp(MIN_WIDTH, MAX_WIDTH));
+        let room = columns.saturating_sub(bar + extra);
+        (bar, label.min(room))
     }
 
-    pub fn render(&self, progress: f32) -> String {
-        let filled = ((progress * self.width as f32) as usize).min(self.width);
-        let empty = self.width - filled;
+    /// The filled and empty parts of the bar, without brackets or colors
+    pub fn cells(&self, progress: f32) -> (String, String) {
+        let width = self.width.unwrap_or(MIN_WIDTH);
+        let filled = ((progress * width as f32) as usize).min(width);
+        let empty = width - filled;
 
         let (fill_char, empty_char) = match self.style {
-            ProgressStyle::Hash => ('#', '.'),
-            ProgressStyle::Equals => ('=', ' '),
+            ProgressStyle::Hash | ProgressStyle::Apt | ProgressStyle::Curl => ('#', '.'),
+            ProgressStyle::Equals
+            | ProgressStyle::Wget
+            | ProgressStyle::Cargo
+            | ProgressStyle::Docker => ('=', ' '),
             ProgressStyle::Block => ('█', '░'),
             ProgressStyle::Gradient => {
                 if filled > empty {
@@ -37,38 +151,494 @@ impl ProgressBar {
                     ('▒', '░')
                 }
             }
+            ProgressStyle::Pip => ('━', '━'),
+        };
+
+        let mut fill = fill_char.to_string().repeat(filled);
+        if matches!(
+            self.style,
+            ProgressStyle::Wget | ProgressStyle::Cargo | ProgressStyle::Docker
+        ) && filled > 0
+        {
+            fill.pop();
+            fill.push('>');
+        }
+        (fill, empty_char.to_string().repeat(empty))
+    }
+
+    /// A frame of `progress` laid out to fit in `columns`
+    pub fn frame(progress: &Progress, columns: usize) -> Line {
+        let percent = format!("{:3.0}%", progress.fraction * 100.0);
+        let detail = progress
+            .detail
+            .map(|detail| format!(" {}", detail))
+            .unwrap_or_default();
+
+        let line = match (progress.style, progress.transfer) {
+            (ProgressStyle::Wget, Some(transfer)) => {
+                let timing = match transfer.eta() {
+                    _ if transfer.is_done() => format!("in {}", short_duration(transfer.elapsed)),
+                    Some(eta) => format!("eta {}", short_duration(eta)),
+                    None => String::new(),
+                };
+                let after = format!(
+                    "] {:>7}  {:>8}    {}",
+                    binary_size(transfer.done),
+                    binary_rate(transfer.rate),
+                    timing
+                );
+                with_bar(progress, &format!(" {}[", percent), &after, columns)
+            }
+            (ProgressStyle::Curl, Some(transfer)) => {
+                let percent = (transfer.fraction() * 100.0) as u32;
+                let average = transfer.done as f64 / transfer.elapsed.as_secs_f64().max(0.001);
+                let eta = transfer.eta();
+                let row = format!(
+                    "{:>3} {:>5}  {:>3} {:>5}    0     0  {:>5}      0 {:>8} {:>8} {:>8} {:>5}",
+                    percent,
+                    curl_size(transfer.total),
+                    percent,
+                    curl_size(transfer.done),
+                    curl_size(average as u64),
+                    curl_time(eta.map(|eta| transfer.elapsed + eta)),
+                    curl_time(Some(transfer.elapsed)),
+                    curl_time(eta),
+                    curl_size(transfer.rate as u64),
+                );
+                Line::styled(Tone::Plain, row)
+            }
+            (ProgressStyle::Apt, Some(transfer)) => {
+                let head = format!("{} [", percent);
+                let counts = format!(
+                    " {}/{} {}]",
+                    grouped(transfer.done / 1000),
+                    apt_size(transfer.total),
+                    percent.trim_start()
+                );
+                let speed = match transfer.eta() {
+                    Some(eta) if !transfer.is_done() => {
+                        format!("{} {}", apt_rate(transfer.rate), apt_duration(eta))
+                    }
+                    _ => apt_rate(transfer.rate),
+                };
+                // The speed is right-aligned, the label gets cut short if it does not fit
+                let columns = columns.saturating_sub(1);
+                let fixed = width(&head) + width(&counts) + width(&speed);
+                let label = progress.label.truncate(columns.saturating_sub(fixed + 1));
+                let padding = columns.saturating_sub(fixed + label.width()).max(1);
+                let mut line = Line::styled(Tone::Plain, head);
+                line.spans.extend(label.spans);
+                line.with(Tone::Plain, counts)
+                    .with(Tone::Plain, " ".repeat(padding))
+                    .with(Tone::Dim, speed)
+            }
+            (ProgressStyle::Pip, Some(transfer)) => {
+                let after = format!(
+                    " {} {} eta {}",
+                    si_pair(transfer.done, transfer.total),
+                    si_rate(transfer.rate),
+                    clock_duration(transfer.eta().unwrap_or_default())
+                );
+                with_bar(progress, " ", &after, columns)
+            }
+            (ProgressStyle::Cargo, Some(transfer)) => {
+                let after = format!(
+                    "] {}/{}{}",
+                    transfer.done,
+                    transfer.total,
+                    detail.replacen(' ', ": ", 1)
+                );
+                with_bar(progress, " [", &after, columns)
+            }
+            (ProgressStyle::Docker, Some(transfer)) => {
+                let after = format!(
+                    "] {:>8}/{}",
+                    docker_size(transfer.done),
+                    docker_size(transfer.total)
+                );
+                with_bar(progress, " [", &after, columns)
+            }
+            _ => with_bar(progress, " [", &format!("] {}{}", percent, detail), columns),
         };
+        line.truncate(columns.saturating_sub(1))
+    }
 
-        format!(
-            "[{}{}] {:3.0}%",
-            fill_char.to_string().repeat(filled).bright_green(),
-            empty_char.to_string().repeat(empty).dimmed(),
-            progress * 100.0
-        )
+    /// Draws a single frame of the bar after `label`
+    pub fn draw(&self, ctx: &mut Context, label: &Line, progress: f32, detail: Option<&str>) {
+        ctx.emit(&Event::Progress(Progress {
+            label,
+            fraction: progress,
+            style: self.style,
+            width: self.width,
+            detail,
+            transfer: None,
+        }));
     }
 
-    pub fn animate(
+    /// Draws a single frame of the bar for `transfer`
+    pub fn draw_transfer(
         &self,
-        message: &str,
-        duration_ms: u64,
-        exit_check: &dyn Fn() -> bool,
-    ) -> io::Result<()> {
+        ctx: &mut Context,
+        label: &Line,
+        transfer: Transfer,
+        detail: Option<&str>,
+    ) {
+        ctx.emit(&Event::Progress(Progress {
+            label,
+            fraction: transfer.fraction(),
+            style: self.style,
+            width: self.width,
+            detail,
+            transfer: Some(transfer),
+        }));
+    }
+
+    /// Fills the bar after `message` over `duration_ms` simulated milliseconds
+    pub fn animate(&self, message: &str, duration_ms: u64, ctx: &mut Context) -> io::Result<()> {
+        self.animate_line(Line::styled(Tone::Bright, message), duration_ms, ctx)
+    }
+
+    /// Like `animate`, with a label made of styled spans
+    pub fn animate_line(&self, label: Line, duration_ms: u64, ctx: &mut Context) -> io::Result<()> {
         let steps = 50;
         let delay = duration_ms / steps;
-        print!("{}", message.bright_white());
-        io::stdout().flush()?;
 
         for i in 0..=steps {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
             let progress = i as f32 / steps as f32;
-            print!("\r{} {}", message.bright_white(), self.render(progress));
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(delay));
+            self.draw(ctx, &label, progress, None);
+            ctx.sleep(delay);
+        }
+        ctx.emit(&Event::ProgressEnd(None));
+        Ok(())
+    }
+
+    /// Moves `total` units in `duration_ms`, at a throughput that wobbles
+    /// around the average but still ends on time
+    pub fn animate_transfer(
+        &self,
+        label: Line,
+        total: u64,
+        duration_ms: u64,
+        ctx: &mut Context,
+    ) -> io::Result<()> {
+        let steps = 50;
+        let delay = (duration_ms / steps).max(1);
+        let shares: Vec<f64> = (0..steps).map(|_| ctx.rng.gen_range(0.4..1.6)).collect();
+        let sum: f64 = shares.iter().sum();
+
+        if self.style == ProgressStyle::Curl {
+            for header in CURL_HEADER {
+                ctx.print(Tone::Plain, header);
+            }
+        }
+
+        let mut transfer = Transfer {
+            done: 0,
+            total,
+            rate: 0.0,
+            elapsed: Duration::ZERO,
+        };
+        let mut moved = 0.0;
+        self.draw_transfer(ctx, &label, transfer, None);
+        for (i, share) in shares.iter().enumerate() {
+            if ctx.check_exit() {
+                return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
+            }
+            ctx.sleep(delay);
+
+            let chunk = total as f64 * share / sum;
+            moved += chunk;
+            let rate = chunk * 1000.0 / delay as f64;
+            // Smoothed like the tools do, so the number can still be read
+            transfer.rate = if i == 0 {
+                rate
+            } else {
+                transfer.rate * 0.7 + rate * 0.3
+            };
+            transfer.done = if i + 1 == shares.len() {
+                total
+            } else {
+                (moved as u64).min(total)
+            };
+            transfer.elapsed += Duration::from_millis(delay);
+            self.draw_transfer(ctx, &label, transfer, None);
         }
-        println!();
+        ctx.emit(&Event::ProgressEnd(None));
         Ok(())
     }
 }
+
+/// `label`, `before`, the bar and `after`, with the bar and label fitted into `columns`
+fn with_bar(progress: &Progress, before: &str, after: &str, columns: usize) -> Line {
+    let extra = width(before) + width(after);
+    let (bar_width, room) =
+        ProgressBar::fit(progress.width, progress.label.width(), extra, columns);
+    let (filled, empty) =
+        ProgressBar::with_width(progress.style, bar_width).cells(progress.fraction);
+    progress
+        .label
+        .truncate(room)
+        .with(Tone::Plain, before)
+        .with(Tone::ProgressFill, filled)
+        .with(Tone::ProgressEmpty, empty)
+        .with(Tone::Plain, after)
+}
+
+/// `61.69M`, as wget writes sizes
+fn binary_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["K", "M", "G", "T"];
+    if bytes < 1024 {
+        return bytes.to_string();
+    }
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.2}{}", value, UNITS[unit])
+}
+
+/// `5.52MB/s`, as wget writes speeds
+fn binary_rate(rate: f64) -> String {
+    if rate < 1024.0 * 1024.0 {
+        format!("{:.0}KB/s", rate / 1024.0)
+    } else if rate < 1024.0 * 1024.0 * 1024.0 {
+        format!("{:.2}MB/s", rate / 1024.0 / 1024.0)
+    } else {
+        format!("{:.2}GB/s", rate / 1024.0 / 1024.0 / 1024.0)
+    }
+}
+
+/// `1m 5s`, as wget writes times
+fn short_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}m {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}m", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// A size in at most five columns, e.g. `47.5M` or `5481k`, as curl writes them
+fn curl_size(bytes: u64) -> String {
+    let kib = bytes / 1024;
+    let mib = bytes as f64 / 1024.0 / 1024.0;
+    if bytes < 100_000 {
+        bytes.to_string()
+    } else if kib < 10_000 {
+        format!("{}k", kib)
+    } else if mib < 100.0 {
+        format!("{:.1}M", mib)
+    } else if mib < 10_000.0 {
+        format!("{:.0}M", mib)
+    } else {
+        format!("{:.1}G", mib / 1024.0)
+    }
+}
+
+/// `0:00:08`, or `--:--:--` when it is not known yet
+fn curl_time(duration: Option<Duration>) -> String {
+    match duration {
+        Some(duration) => clock_duration(duration),
+        None => "--:--:--".to_string(),
+    }
+}
+
+/// `1:02:03`
+fn clock_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        seconds % 3600 / 60,
+        seconds % 60
+    )
+}
+
+/// `3,235 kB`, as apt writes sizes
+pub fn apt_size(bytes: u64) -> String {
+    format!("{} kB", grouped(bytes / 1000))
+}
+
+/// `512 kB/s`, or `12.3 MB/s` once it gets fast
+fn apt_rate(rate: f64) -> String {
+    if rate < 10_000_000.0 {
+        format!("{} kB/s", grouped((rate / 1000.0) as u64))
+    } else {
+        format!("{:.1} MB/s", rate / 1_000_000.0)
+    }
+}
+
+/// `1min 5s`, as apt writes times
+fn apt_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        0..=59 => format!("{}s", seconds),
+        60..=3599 => format!("{}min {}s", seconds / 60, seconds % 60),
+        _ => format!("{}h {}min", seconds / 3600, seconds % 3600 / 60),
+    }
+}
+
+/// `12.3/24.6 MB`, in the unit of the total
+fn si_pair(done: u64, total: u64) -> String {
+    let (divisor, unit) = si_unit(total as f64);
+    format!(
+        "{:.1}/{:.1} {}",
+        done as f64 / divisor,
+        total as f64 / divisor,
+        unit
+    )
+}
+
+/// `5.1 MB/s`
+fn si_rate(rate: f64) -> String {
+    let (divisor, unit) = si_unit(rate);
+    format!("{:.1} {}/s", rate / divisor, unit)
+}
+
+fn si_unit(value: f64) -> (f64, &'static str) {
+    match value {
+        v if v >= 1e9 => (1e9, "GB"),
+        v if v >= 1e6 => (1e6, "MB"),
+        v if v >= 1e3 => (1e3, "kB"),
+        _ => (1.0, "bytes"),
+    }
+}
+
+/// `27.15MB`, four significant digits as docker writes sizes
+fn docker_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "kB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 999.95 && unit < UNITS.len() - 1 {
+        value /= 1000.0;
+        unit += 1;
+    }
+    let decimals = match value {
+        v if v >= 100.0 => 1,
+        v if v >= 10.0 => 2,
+        _ => 3,
+    };
+    let digits = format!("{:.*}", decimals, value);
+    let digits = digits.trim_end_matches('0').trim_end_matches('.');
+    format!("{}{}", digits, UNITS[unit])
+}
+
+/// `1,456`
+fn grouped(value: u64) -> String {
+    let digits = value.to_string();
+    let head = match digits.len() % 3 {
+        0 => 3,
+        rest => rest,
+    };
+    let mut grouped = digits[..head].to_string();
+    for start in (head..digits.len()).step_by(3) {
+        grouped.push(',');
+        grouped.push_str(&digits[start..start + 3]);
+    }
+    grouped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(style: ProgressStyle, label: &str, transfer: Transfer, columns: usize) -> String {
+        let label = Line::styled(Tone::Plain, label);
+        let line = ProgressBar::frame(
+            &Progress {
+                label: &label,
+                fraction: transfer.fraction(),
+                style,
+                width: Some(20),
+                detail: None,
+                transfer: Some(transfer),
+            },
+            columns,
+        );
+        line.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    #[test]
+    fn test_tool_styles() {
+        let transfer = Transfer {
+            done: 45 * 1024 * 1024,
+            total: 100 * 1024 * 1024,
+            rate: 5.0 * 1024.0 * 1024.0,
+            elapsed: Duration::from_secs(9),
+        };
+
+        assert_eq!(
+            frame(ProgressStyle::Wget, "linux.tar.xz", transfer, 80),
+            "linux.tar.xz  45%[========>           ]  45.00M  5.00MB/s    eta 11s"
+        );
+        assert_eq!(
+            frame(ProgressStyle::Curl, "", transfer, 80),
+            " 45  100M   45 45.0M    0     0  5120k      0  0:00:20  0:00:09  0:00:11 5120k"
+        );
+        assert_eq!(
+            frame(ProgressStyle::Pip, "", transfer, 80),
+            " ━━━━━━━━━━━━━━━━━━━━ 47.2/104.9 MB 5.2 MB/s eta 0:00:11"
+        );
+
+        let apt = frame(ProgressStyle::Apt, "libc6", transfer, 80);
+        assert!(
+            apt.starts_with(" 45% [libc6 47,185/104,857 kB 45%]"),
+            "{}",
+            apt
+        );
+        assert!(apt.ends_with("5,242 kB/s 11s"), "{}", apt);
+        assert_eq!(width(&apt), 79);
+
+        assert_eq!(
+            frame(
+                ProgressStyle::Cargo,
+                "Building",
+                Transfer::items(45, 120),
+                80
+            ),
+            "Building [======>             ] 45/120"
+        );
+    }
+
+    #[test]
+    fn test_frame_fits_narrow_terminal() {
+        let transfer = Transfer::items(3, 4);
+        let line = frame(ProgressStyle::Wget, &"x".repeat(100), transfer, 70);
+        assert_eq!(width(&line), 69, "{}", line);
+        assert!(
+            line.starts_with(&format!("{}…  75%[", "x".repeat(19))),
+            "{}",
+            line
+        );
+    }
+
+    #[test]
+    fn test_docker_size() {
+        assert_eq!(docker_size(162), "162B");
+        assert_eq!(docker_size(2_162_000), "2.162MB");
+        assert_eq!(docker_size(27_150_000), "27.15MB");
+        assert_eq!(docker_size(135_000_000), "135MB");
+        assert_eq!(
+            frame(
+                ProgressStyle::Docker,
+                "a2abf6c4d29d: Downloading",
+                Transfer::items(12_500_000, 50_000_000),
+                80
+            ),
+            "a2abf6c4d29d: Downloading [====>               ]   12.5MB/50MB"
+        );
+    }
+
+    #[test]
+    fn test_grouped() {
+        assert_eq!(grouped(7), "7");
+        assert_eq!(grouped(1456), "1,456");
+        assert_eq!(grouped(1234567), "1,234,567");
+    }
+}
@@ -1,44 +1,96 @@
-use colored::*;
-use std::io::{self, Write};
-use std::thread;
-use std::time::Duration;
+use crate::context::Context;
+use crate::render::Event;
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::io;
 
+/// How a spinner looks and how fast it turns
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum SpinnerStyle {
+    /// `| / - \`
+    #[default]
+    Line,
+    /// Braille dots, as modern CLI tools draw them
+    Dots,
+    /// A quarter circle going round
+    Arc,
+    /// `[===  ]`, a bar bouncing between the brackets
+    Bounce,
+    /// Clock faces
+    Clock,
+    /// Moon phases
+    Moon,
+    /// `-\|/` followed by a block cursor, as DOS installers had it
+    Dos,
+}
+
+impl SpinnerStyle {
+    /// The frames of one turn
+    pub fn frames(self) -> &'static [&'static str] {
+        match self {
+            SpinnerStyle::Line => &["|", "/", "-", "\\"],
+            SpinnerStyle::Dots => &["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"],
+            SpinnerStyle::Arc => &["◜", "◠", "◝", "◞", "◡", "◟"],
+            SpinnerStyle::Bounce => &[
+                "[    ]", "[=   ]", "[==  ]", "[=== ]", "[ ===]", "[  ==]", "[   =]", "[    ]",
+                "[   =]", "[  ==]", "[ ===]", "[====]", "[=== ]", "[==  ]", "[=   ]",
+            ],
+            SpinnerStyle::Clock => &[
+                "🕛", "🕐", "🕑", "🕒", "🕓", "🕔", "🕕", "🕖", "🕗", "🕘", "🕙", "🕚",
+            ],
+            SpinnerStyle::Moon => &["🌑", "🌒", "🌓", "🌔", "🌕", "🌖", "🌗", "🌘"],
+            SpinnerStyle::Dos => &["-█", "\\█", "|█", "/█"],
+        }
+    }
+
+    /// Simulated milliseconds each frame stays on screen
+    pub fn interval_ms(self) -> u64 {
+        match self {
+            SpinnerStyle::Dots | SpinnerStyle::Bounce | SpinnerStyle::Moon => 80,
+            SpinnerStyle::Line | SpinnerStyle::Arc | SpinnerStyle::Clock => 100,
+            SpinnerStyle::Dos => 150,
+        }
+    }
+}
+
+/// A spinner in front of a message, left behind as a plain line once it stops
 pub struct Spinner {
-    chars: Vec<char>,
+    style: SpinnerStyle,
     index: usize,
 }
 
 impl Spinner {
     pub fn new() -> Self {
-        Self {
-            chars: vec!['|', '/', '-', '\\'],
-            index: 0,
-        }
+        Self::with_style(SpinnerStyle::default())
     }
 
-    pub fn next(&mut self) -> char {
-        let c = self.chars[self.index];
-        self.index = (self.index + 1) % self.chars.len();
-        c
+    /// A spinner in `style`, unless the user picked one for every stage
+    pub fn with_style(style: SpinnerStyle) -> Self {
+        Self { style, index: 0 }
     }
 
+    /// Spins in front of `message` for `duration_ms` simulated milliseconds
     pub fn animate(
         &mut self,
         message: &str,
         duration_ms: u64,
-        exit_check: &dyn Fn() -> bool,
+        ctx: &mut Context,
     ) -> io::Result<()> {
-        let steps = duration_ms / 100;
+        let style = ctx.spinner.unwrap_or(self.style);
+        let frames = style.frames();
+        let steps = duration_ms / style.interval_ms();
         for _ in 0..steps {
-            if exit_check() {
+            if ctx.check_exit() {
                 return Err(io::Error::new(io::ErrorKind::Interrupted, "User interrupt"));
             }
 
-            print!("\r{} {}", self.next(), message.bright_white());
-            io::stdout().flush()?;
-            thread::sleep(Duration::from_millis(100));
+            let frame = frames[self.index % frames.len()];
+            self.index += 1;
+            ctx.emit(&Event::Spinner { frame, message });
+            ctx.sleep(style.interval_ms());
         }
-        print!("\r  {}\n", message.bright_white());
+        ctx.emit(&Event::SpinnerEnd { message });
         Ok(())
     }
 }
@@ -48,3 +100,23 @@ impl Default for Spinner {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ui::layout::width;
+
+    #[test]
+    fn test_frames_keep_their_width() {
+        for style in SpinnerStyle::value_variants() {
+            let frames = style.frames();
+            let first = width(frames[0]);
+            assert!(
+                frames.iter().all(|frame| width(frame) == first),
+                "{:?}",
+                style
+            );
+        }
+        assert_eq!(width(SpinnerStyle::Moon.frames()[0]), 2);
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// How often the command is checked on
+const POLL: Duration = Duration::from_millis(100);
+
+/// A real command running behind the installer, with its output going to a
+/// log file instead of the screen
+pub struct Wrapped {
+    ended: Arc<AtomicBool>,
+    kill: Sender<()>,
+    waiter: JoinHandle<io::Result<ExitStatus>>,
+}
+
+impl Wrapped {
+    /// Starts `command`, writing what it prints to `log`
+    ///
+    /// The command gets no input, the keys belong to the installer.
+    pub fn spawn(mut command: Command, log: &Path) -> io::Result<Self> {
+        let file = File::create(log).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("cannot create log {}: {}", log.display(), e),
+            )
+        })?;
+        let program = command.get_program().to_string_lossy().into_owned();
+        let mut child = command
+            .stdin(Stdio::null())
+            .stdout(file.try_clone()?)
+            .stderr(file)
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("cannot run {}: {}", program, e)))?;
+
+        let ended = Arc::new(AtomicBool::new(false));
+        let (kill, killed) = mpsc::channel();
+        let waiter_ended = Arc::clone(&ended);
+        let waiter = thread::spawn(move || loop {
+            if let Some(status) = child.try_wait()? {
+                waiter_ended.store(true, Ordering::Relaxed);
+                return Ok(status);
+            }
+            match killed.recv_timeout(POLL) {
+                Ok(()) => child.kill()?,
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return child.wait(),
+            }
+        });
+        Ok(Self {
+            ended,
+            kill,
+            waiter,
+        })
+    }
+
+    /// Set once the command has exited, for `Installer::end_when`
+    pub fn ended(&self) -> Arc<AtomicBool> {
+        Arc::clone(&self.ended)
+    }
+
+    /// Waits for the command to exit
+    pub fn wait(self) -> io::Result<ExitStatus> {
+        self.waiter
+            .join()
+            .unwrap_or_else(|_| Err(io::Error::other("the command was lost")))
+    }
+
+    /// Stops the command and waits for it to exit
+    pub fn kill(self) -> io::Result<ExitStatus> {
+        // It may have exited already, then there is nothing to stop
+        let _ = self.kill.send(());
+        self.wait()
+    }
+}
+
+/// The code a shell would report for `status`, 128 plus the signal for a
+/// command killed by one
+pub fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        if let Some(signal) = status.signal() {
+            return 128 + signal;
+        }
+    }
+    status.code().unwrap_or(1)
+}
+
+/// Runs `line` with the shell
+pub fn shell(line: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(line);
+    command
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_code_and_log() {
+        let log =
+            std::env::temp_dir().join(format!("install-nothing-wrap-{}.log", std::process::id()));
+        let wrapped =
+            Wrapped::spawn(shell("echo building; echo broken >&2; exit 3"), &log).unwrap();
+        let ended = wrapped.ended();
+        let status = wrapped.wait().unwrap();
+        assert!(ended.load(Ordering::Relaxed));
+        assert_eq!(exit_code(status), 3);
+        assert_eq!(std::fs::read_to_string(&log).unwrap(), "building\nbroken\n");
+
+        let wrapped = Wrapped::spawn(shell("sleep 10"), &log).unwrap();
+        assert_eq!(exit_code(wrapped.kill().unwrap()), 128 + 9);
+        std::fs::remove_file(&log).unwrap();
+    }
+}